@@ -5,6 +5,7 @@ use crate::{error::api_error::RestAPIError, model::app_state::AppState};
 
 const API_KEY_HEADER: &str = "x-api-key";
 
+#[allow(dead_code)]
 pub struct ApiKey(pub String);
 
 impl<S> FromRequestParts<S> for ApiKey
@@ -5,16 +5,26 @@ use crate::{
         api_response::RestAPIResponse,
         app_state::AppState,
         vo::{
+            cds_cmd::{CDSCmd, IntoCDS},
             ids_cmd::{IDSCmd, IntoIDS},
+            mds_cmd::{IntoMDS, MDSCmd},
+            pds_cmd::PDSQuery,
             tds_cmd::{IntoTDS, TDSCmd},
         },
     },
 };
 use axum::{
-    extract::{Path, State},
+    body::Bytes,
+    extract::{Path, Query, State},
     response::IntoResponse,
 };
-use mcp_common::xds::{ids::IDS, tds::TDS};
+use mcp_common::{
+    cache::result_cache::get_result_cache,
+    circuit_breaker::circuit_breaker::get_circuit_breakers,
+    cluster::cluster_manager::get_cluster_manager,
+    wasm::wasm_runtime::get_wasm_runtime,
+    xds::{cds::CDS, ids::IDS, mds::MDS, pds::PDS, tds::TDS},
+};
 use mcp_plugin::datasource::datasource::DataSource;
 
 pub async fn handle_put_tds(
@@ -25,6 +35,10 @@ pub async fn handle_put_tds(
 ) -> Result<impl IntoResponse, RestAPIError> {
     let tds = tds_cmd.into_tds(tds_id);
     tds.validate().map_err(RestAPIError::bad_request)?;
+    state
+        .mcp_cache
+        .check_builtin_collision(&tds)
+        .map_err(RestAPIError::bad_request)?;
     state
         .data_source
         .put(&tds.id, &tds)
@@ -127,3 +141,208 @@ pub async fn handle_get_all_ids(
         .map_err(RestAPIError::internal)?;
     Ok(RestAPIResponse::success(list))
 }
+
+pub async fn handle_put_mds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(mds_id): Path<String>,
+    ValidatedJson(mds_cmd): ValidatedJson<MDSCmd>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let mds = mds_cmd.into_mds(mds_id);
+    mds.validate().map_err(RestAPIError::bad_request)?;
+    state
+        .data_source
+        .put(&mds.id, &mds)
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(mds))
+}
+
+pub async fn handle_get_mds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let mds = state
+        .data_source
+        .get::<MDS>(&id)
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(mds))
+}
+
+pub async fn handle_get_all_mds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let list = state
+        .data_source
+        .get_all::<MDS>()
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(list))
+}
+
+pub async fn handle_del_mds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let res = state
+        .data_source
+        .delete(&id)
+        .await
+        .map_err(RestAPIError::internal)?;
+    // not every DataSource watches deletes; the tool sync drops the
+    // mirrored tools once the server is gone from the cache
+    state.mcp_cache.remove_mds(&id);
+    Ok(RestAPIResponse::success(format!(
+        "MDS `{id}` delete result: {res}"
+    )))
+}
+
+/// Uploads a WASM component (binary or text format) as the raw body.
+pub async fn handle_put_pds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(pds_id): Path<String>,
+    Query(query): Query<PDSQuery>,
+    wasm: Bytes,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let name = query.name.unwrap_or_else(|| pds_id.clone());
+    let pds = PDS::new(pds_id, name, &wasm);
+    pds.validate().map_err(RestAPIError::bad_request)?;
+    get_wasm_runtime()
+        .map_err(RestAPIError::internal)?
+        .check(&pds)
+        .await
+        .map_err(RestAPIError::bad_request)?;
+    state
+        .data_source
+        .put(&pds.id, &pds)
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(pds.summary()))
+}
+
+pub async fn handle_get_pds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let pds = state
+        .data_source
+        .get::<PDS>(&id)
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(pds.summary()))
+}
+
+pub async fn handle_get_all_pds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let list = state
+        .data_source
+        .get_all::<PDS>()
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(
+        list.iter().map(PDS::summary).collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn handle_del_pds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let res = state
+        .data_source
+        .delete(&id)
+        .await
+        .map_err(RestAPIError::internal)?;
+    // not every DataSource watches deletes
+    state.mcp_cache.remove_pds(&id);
+    Ok(RestAPIResponse::success(format!(
+        "PDS `{id}` delete result: {res}"
+    )))
+}
+
+pub async fn handle_put_cds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(cds_id): Path<String>,
+    ValidatedJson(cds_cmd): ValidatedJson<CDSCmd>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let cds = cds_cmd.into_cds(cds_id);
+    cds.validate().map_err(RestAPIError::bad_request)?;
+    state
+        .mcp_cache
+        .check_cluster_name(&cds)
+        .map_err(RestAPIError::bad_request)?;
+    state
+        .data_source
+        .put(&cds.id, &cds)
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(cds))
+}
+
+pub async fn handle_get_cds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let cds = state
+        .data_source
+        .get::<CDS>(&id)
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(cds))
+}
+
+pub async fn handle_get_all_cds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let list = state
+        .data_source
+        .get_all::<CDS>()
+        .await
+        .map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(list))
+}
+
+pub async fn handle_del_cds(
+    State(state): State<AppState>,
+    _api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let res = state
+        .data_source
+        .delete(&id)
+        .await
+        .map_err(RestAPIError::internal)?;
+    // not every DataSource watches deletes
+    state.mcp_cache.remove_cds(&id);
+    Ok(RestAPIResponse::success(format!(
+        "CDS `{id}` delete result: {res}"
+    )))
+}
+
+/// Live endpoint health and load of every loaded cluster.
+pub async fn handle_get_clusters(_api_key: ApiKey) -> Result<impl IntoResponse, RestAPIError> {
+    Ok(RestAPIResponse::success(get_cluster_manager().snapshots()))
+}
+
+pub async fn handle_get_circuit_breakers(
+    _api_key: ApiKey,
+) -> Result<impl IntoResponse, RestAPIError> {
+    let breakers = get_circuit_breakers().map_err(RestAPIError::internal)?;
+    Ok(RestAPIResponse::success(breakers.snapshots()))
+}
+
+pub async fn handle_get_result_cache(_api_key: ApiKey) -> Result<impl IntoResponse, RestAPIError> {
+    Ok(RestAPIResponse::success(get_result_cache().snapshots()))
+}
@@ -6,7 +6,11 @@ use axum::{
     response::{sse::Event, IntoResponse, Response, Sse},
     Json,
 };
-use futures::{stream::select, StreamExt};
+use anyhow::anyhow;
+use futures::{
+    stream::{self, select},
+    StreamExt,
+};
 use mcp_common::{
     enums::ids_protocol_type::IdsProtoType,
     sse::{
@@ -18,12 +22,21 @@ use mcp_common::{
 };
 use mcp_core::{
     error::dyn_execute_error::DynExecuteError,
-    mcp::protocol::mcp_protocol::{self, Requestx},
+    mcp::{
+        notifier::ToolNotifier,
+        protocol::mcp_protocol::{self, DynExecuteResult, Requestx},
+    },
     model::spec::protocol_method::ProtocolMethod,
 };
-use serde_json::{from_str, Value};
-use tokio::time::interval;
-use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, IntervalStream};
+use serde_json::{from_str, json, Value};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::interval,
+};
+use tokio_stream::wrappers::{
+    errors::BroadcastStreamRecvError, BroadcastStream, IntervalStream, UnboundedReceiverStream,
+};
 use tracing::error;
 
 use crate::{
@@ -37,11 +50,9 @@ pub async fn mcp_post(
     State(state): State<AppState>,
     Json(jsonrpc_request): Json<Value>,
 ) -> Result<Response, RestAPIError> {
-    // create a request context for the MCP protocol
-    let reqx = Requestx {
-        mcp_cache: &state.mcp_cache,
-        ids_id: &ids_id,
-    };
+    // Extract header
+    let header_extractor = HeaderExtractor::new(&headers);
+    let session_id = header_extractor.get_str("Mcp-Session-Id");
 
     // find ids
     let ids = state
@@ -49,32 +60,53 @@ pub async fn mcp_post(
         .get_ids(&ids_id)
         .ok_or_else(|| RestAPIError::for_json_rpc(DynExecuteError::IdsNotFound))?;
     let ids_metadata: IDSMetadata = from_str(ids.metadata.as_str())?;
-
-    // [Core] Execute dynamic mcp protocol
-    let result = mcp_protocol::execute_dyn(jsonrpc_request, &reqx)
-        .await
-        .map_err(|err| RestAPIError::for_json_rpc(err))?;
-
-    // Response by ids protocol type
     let proto_type: IdsProtoType = ids_metadata.proto_type.as_str().into();
-    let mut response = match proto_type {
-        IdsProtoType::StreamableStateless => {
-            JSONRpcResponse::with_u16_status(result.respx.http_status, result.response)
-                .into_response()
-        }
-        _ => once_sse(&result.response),
+
+    // [Core] Execute dynamic mcp protocol; tool calls answered over SSE may
+    // relay upstream chunks ahead of the result
+    let relays = proto_type != IdsProtoType::StreamableStateless
+        && jsonrpc_request.get("method").and_then(Value::as_str)
+            == Some(ProtocolMethod::ToolsCall.as_str());
+    let executed = if relays {
+        execute_relayed(&state, &ids_id, session_id.clone(), jsonrpc_request).await?
+    } else {
+        // create a request context for the MCP protocol
+        let reqx = Requestx {
+            mcp_cache: &state.mcp_cache,
+            ids_id: &ids_id,
+            session_id: session_id.as_deref(),
+            notifier: None,
+        };
+        Relayed::Done(mcp_protocol::execute_dyn(jsonrpc_request, &reqx).await)
     };
 
-    // Responsex
-    let resp_protocol_method = result
-        .respx
-        .protocol_method
-        .ok_or_else(|| RestAPIError::for_json_rpc(DynExecuteError::MissingMethod))?;
-    let proto_method = resp_protocol_method.as_str();
+    let (mut response, proto_method, initialize_session_id) = match executed {
+        Relayed::Done(result) => {
+            let result = result.map_err(RestAPIError::for_json_rpc)?;
 
-    // Extract header
-    let header_extractor = HeaderExtractor::new(&headers);
-    let session_id = header_extractor.get_str("Mcp-Session-Id");
+            // Response by ids protocol type
+            let response = match proto_type {
+                IdsProtoType::StreamableStateless => {
+                    JSONRpcResponse::with_u16_status(result.respx.http_status, result.response)
+                        .into_response()
+                }
+                _ => once_sse(&result.response),
+            };
+
+            // Responsex
+            let resp_protocol_method = result
+                .respx
+                .protocol_method
+                .ok_or_else(|| RestAPIError::for_json_rpc(DynExecuteError::MissingMethod))?;
+            (
+                response,
+                resp_protocol_method,
+                result.respx.initialize_session_id,
+            )
+        }
+        Relayed::Streaming(response) => (response, ProtocolMethod::ToolsCall.to_string(), None),
+    };
+    let proto_method = proto_method.as_str();
 
     // Build header
     let mut header_builder = HeaderBuilder::new(&mut response);
@@ -95,10 +127,8 @@ pub async fn mcp_post(
     }
 
     // Use HeaderExtractor to get session ID
-    let resp_session_id = result
-        .respx
-        .initialize_session_id
-        .or_else(|| session_id)
+    let resp_session_id = initialize_session_id
+        .or(session_id)
         .unwrap_or_default();
     header_builder.set_str("Mcp-Session-Id", &resp_session_id)?;
 
@@ -106,13 +136,98 @@ pub async fn mcp_post(
     if proto_method == init_proto_method && proto_type == IdsProtoType::StreamableStateful {
         // session manager
         let session_manager = get_session_manager()?;
-        let session_value = StreamableSession { ids_id: ids_id };
+        let session_value = StreamableSession { ids_id };
         session_manager.put(&resp_session_id, &session_value).await;
     }
 
     Ok(response)
 }
 
+/// How a tool call relaying upstream chunks is answered.
+enum Relayed {
+    // finished before any chunk arrived, answered like any other request
+    Done(Result<DynExecuteResult, DynExecuteError>),
+    // an SSE response relaying the chunks, then the result
+    Streaming(Response),
+}
+
+/// Runs a `tools/call` whose upstream may stream its answer.
+///
+/// The call runs on its own task. If it finishes without a chunk, the result
+/// is handed back to be answered as usual; once a chunk arrives, the answer
+/// becomes an SSE stream of notifications that ends with the result. The task
+/// is aborted when the client goes away, before or while it is streamed to.
+async fn execute_relayed(
+    state: &AppState,
+    ids_id: &str,
+    session_id: Option<String>,
+    jsonrpc_request: Value,
+) -> Result<Relayed, RestAPIError> {
+    let request_id = jsonrpc_request.get("id").cloned().unwrap_or(Value::Null);
+    let progress_token = jsonrpc_request
+        .pointer("/params/_meta/progressToken")
+        .cloned();
+    let (notification_tx, mut notification_rx) = mpsc::unbounded_channel();
+    let (result_tx, result_rx) = oneshot::channel();
+    let mcp_cache = state.mcp_cache.clone();
+    let ids_id = ids_id.to_string();
+    let task = AbortOnDrop(tokio::spawn(async move {
+        let notifier = ToolNotifier::new(notification_tx, progress_token);
+        let reqx = Requestx {
+            mcp_cache: &mcp_cache,
+            ids_id: &ids_id,
+            session_id: session_id.as_deref(),
+            notifier: Some(&notifier),
+        };
+        let result = mcp_protocol::execute_dyn(jsonrpc_request, &reqx).await;
+        // ends the notifications before the result is sent
+        drop(notifier);
+        let _ = result_tx.send(result);
+    }));
+
+    let Some(first) = notification_rx.recv().await else {
+        let result = result_rx
+            .await
+            .map_err(|_| RestAPIError::internal(anyhow!("tool call task ended without a result")))?;
+        return Ok(Relayed::Done(result));
+    };
+    let notifications = stream::once(async { first })
+        .chain(UnboundedReceiverStream::new(notification_rx))
+        .map(|notification| Event::default().data(notification.to_string()));
+    let result = stream::once(async move {
+        // dropping the response drops this future and the task with it
+        let _task = task;
+        let message = match result_rx.await {
+            Ok(Ok(result)) => serde_json::to_string(&result.response)
+                .unwrap_or_else(|_| "null".to_string()),
+            Ok(Err(e)) => json_rpc_error(&request_id, &e.message()),
+            Err(_) => json_rpc_error(&request_id, "tool call task ended without a result"),
+        };
+        Event::default().data(message)
+    });
+    let stream = notifications.chain(result).map(Ok::<Event, Infallible>);
+    Ok(Relayed::Streaming(Sse::new(stream).into_response()))
+}
+
+/// Aborts a task when dropped.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A JSON-RPC internal error, for failures after the SSE response started.
+fn json_rpc_error(id: &Value, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": -32603, "message": message },
+    })
+    .to_string()
+}
+
 pub async fn mcp_get(Path(ids_id): Path<String>) -> Result<impl IntoResponse, RestAPIError> {
     // TODO Last-Event-ID
 
@@ -137,7 +252,7 @@ pub async fn mcp_get(Path(ids_id): Path<String>) -> Result<impl IntoResponse, Re
                 }
             }
         })
-        .map(|event| Ok::<Event, Infallible>(event));
+        .map(Ok::<Event, Infallible>);
 
     // heartbeat stream
     let heartbeat_stream = IntervalStream::new(interval(Duration::from_secs(10)))
@@ -148,3 +263,27 @@ pub async fn mcp_get(Path(ids_id): Path<String>) -> Result<impl IntoResponse, Re
 
     Ok(Sse::new(combined).into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dropped_streams_abort_their_task() {
+        let (started_tx, started_rx) = oneshot::channel();
+        let (finished_tx, finished_rx) = oneshot::channel::<()>();
+        let task = AbortOnDrop(tokio::spawn(async move {
+            let _ = started_tx.send(());
+            std::future::pending::<()>().await;
+            let _ = finished_tx.send(());
+        }));
+        let stream = stream::once(async move {
+            let _task = task;
+        });
+        started_rx.await.unwrap();
+
+        drop(stream);
+        // the aborted task drops its sender without sending
+        assert!(finished_rx.await.is_err());
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::module_inception)]
+
 use std::sync::Arc;
 
 use anyhow::{anyhow, Ok, Result};
@@ -5,7 +7,8 @@ use mcp_common::{
     cache::mcp_cache::McpCache, log::log::init_logging, provider::global_provider::get_app_config,
     sse::broadcast::get_broadcast_tx,
 };
-use mcp_plugin::datasource::factory::DataSourceFactory;
+use mcp_core::tool::native_tool::register_builtin_tds;
+use mcp_plugin::{datasource::factory::DataSourceFactory, sync::mcp_tool_sync::McpToolSync};
 use tokio::net::TcpListener;
 use tracing::info;
 
@@ -29,6 +32,9 @@ async fn main() -> Result<()> {
     let mcp_cache: Arc<McpCache> = Arc::new(McpCache::new());
     info!("McpCache initialized");
 
+    // built-in TDS entries of the #[mcp_tool] native tools
+    register_builtin_tds(&mcp_cache);
+
     // global broadcast tx
     let _ = get_broadcast_tx(Some(1024))?;
     info!("Broadcast tx initialized");
@@ -39,6 +45,10 @@ async fn main() -> Result<()> {
         .map_err(|e| anyhow!("Failed to create data source: {}", e))?;
     info!("DataSource initialized: {:?}", config.data_source);
 
+    // mirror tools of registered upstream MCP servers
+    McpToolSync::spawn(mcp_cache.clone(), ds.clone());
+    info!("McpToolSync started");
+
     // init axum router
     let app_state: AppState = AppState::new(mcp_cache, ds, config.clone());
     let router: axum::Router = create_router(app_state);
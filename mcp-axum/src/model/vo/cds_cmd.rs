@@ -0,0 +1,31 @@
+use mcp_common::xds::cds::{ClusterEndpoint, HealthCheck, LbPolicy, CDS};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CDSCmd {
+    #[validate(length(min = 1, message = "CDS name cannot be empty"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "CDS endpoints must contain at least 1 element"))]
+    pub endpoints: Vec<ClusterEndpoint>,
+    #[serde(default)]
+    pub lb_policy: LbPolicy,
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+}
+
+pub trait IntoCDS {
+    fn into_cds(self, id: String) -> CDS;
+}
+
+impl IntoCDS for CDSCmd {
+    fn into_cds(self, id: String) -> CDS {
+        CDS {
+            id,
+            name: self.name,
+            endpoints: self.endpoints,
+            lb_policy: self.lb_policy,
+            health_check: self.health_check,
+        }
+    }
+}
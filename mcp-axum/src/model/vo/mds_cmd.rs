@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use mcp_common::xds::mds::MDS;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct MDSCmd {
+    #[validate(length(min = 1, message = "MDS name cannot be empty"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "MDS url cannot be empty"))]
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub tls_profile: Option<String>,
+    #[serde(default)]
+    pub tool_prefix: String,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub resync_interval_ms: Option<u64>,
+}
+
+pub trait IntoMDS {
+    fn into_mds(self, id: String) -> MDS;
+}
+
+impl IntoMDS for MDSCmd {
+    fn into_mds(self, id: String) -> MDS {
+        MDS {
+            id,
+            name: self.name,
+            url: self.url,
+            headers: self.headers,
+            tls_profile: self.tls_profile,
+            tool_prefix: self.tool_prefix,
+            include: self.include,
+            exclude: self.exclude,
+            resync_interval_ms: self.resync_interval_ms,
+        }
+    }
+}
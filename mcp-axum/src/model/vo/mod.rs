@@ -1,2 +1,5 @@
 pub mod tds_cmd;
-pub mod ids_cmd;
\ No newline at end of file
+pub mod ids_cmd;
+pub mod mds_cmd;
+pub mod pds_cmd;
+pub mod cds_cmd;
\ No newline at end of file
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Query of a plugin upload; the component itself is the request body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PDSQuery {
+    // Display name, defaults to the plugin id
+    #[serde(default)]
+    pub name: Option<String>,
+}
@@ -1,8 +1,11 @@
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, put},
     Router,
 };
 
+use mcp_common::constants::constants::wasm_consts::MAX_WASM_MODULE_BYTES;
+
 use crate::{
     handler::{admin_handler, healthz_handler, mcp_handler},
     model::app_state::AppState,
@@ -25,9 +28,36 @@ pub fn create_router(app_state: AppState) -> Router {
             "/admin/ids/{ids_id}",
             get(admin_handler::handle_get_ids).delete(admin_handler::handle_del_ids),
         )
+        .route(
+            "/admin/mds/{mds_id}",
+            get(admin_handler::handle_get_mds).delete(admin_handler::handle_del_mds),
+        )
+        .route(
+            "/admin/pds/{pds_id}",
+            get(admin_handler::handle_get_pds).delete(admin_handler::handle_del_pds),
+        )
+        .route(
+            "/admin/cds/{cds_id}",
+            get(admin_handler::handle_get_cds).delete(admin_handler::handle_del_cds),
+        )
         .route("/admin/tds/{tds_id}", put(admin_handler::handle_put_tds))
         .route("/admin/ids/{ids_id}", put(admin_handler::handle_put_ids))
+        .route("/admin/mds/{mds_id}", put(admin_handler::handle_put_mds))
+        .route(
+            "/admin/pds/{pds_id}",
+            put(admin_handler::handle_put_pds).layer(DefaultBodyLimit::max(MAX_WASM_MODULE_BYTES)),
+        )
+        .route("/admin/cds/{cds_id}", put(admin_handler::handle_put_cds))
         .route("/admin/tds", get(admin_handler::handle_get_all_tds))
         .route("/admin/ids", get(admin_handler::handle_get_all_ids))
+        .route("/admin/mds", get(admin_handler::handle_get_all_mds))
+        .route("/admin/pds", get(admin_handler::handle_get_all_pds))
+        .route("/admin/cds", get(admin_handler::handle_get_all_cds))
+        .route("/admin/clusters", get(admin_handler::handle_get_clusters))
+        .route(
+            "/admin/circuit-breakers",
+            get(admin_handler::handle_get_circuit_breakers),
+        )
+        .route("/admin/result-cache", get(admin_handler::handle_get_result_cache))
         .with_state(app_state)
 }
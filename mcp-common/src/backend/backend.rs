@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::{
+    fan_out::FanOutBackend, graphql::GraphqlBackend, grpc::GrpcBackend, mcp::McpBackend,
+    mock::MockBackend, native::NativeBackend, sql::SqlBackend, wasm::WasmBackend,
+    workflow::WorkflowBackend,
+};
+
+/// The kind of upstream a TDS calls.
+///
+/// `http` (REST: `domain` + `method` + `path`) is the default, so TDS entries
+/// without a `backend` keep working unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolBackend {
+    #[default]
+    Http,
+    Graphql(GraphqlBackend),
+    Grpc(GrpcBackend),
+    Sql(SqlBackend),
+    Mcp(McpBackend),
+    Workflow(WorkflowBackend),
+    FanOut(FanOutBackend),
+    Wasm(WasmBackend),
+    Native(NativeBackend),
+    Mock(MockBackend),
+}
+
+impl ToolBackend {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ToolBackend::Http => Ok(()),
+            ToolBackend::Graphql(graphql) => graphql.validate(),
+            ToolBackend::Grpc(grpc) => grpc.validate(),
+            ToolBackend::Sql(sql) => sql.validate(),
+            ToolBackend::Mcp(mcp) => mcp.validate(),
+            ToolBackend::Workflow(workflow) => workflow.validate(),
+            ToolBackend::FanOut(fan_out) => fan_out.validate(),
+            ToolBackend::Wasm(wasm) => wasm.validate(),
+            ToolBackend::Native(native) => native.validate(),
+            ToolBackend::Mock(mock) => mock.validate(),
+        }
+    }
+
+    /// Generates `input_schema` from the backend's own type information:
+    /// the request message for gRPC, the SDL (when configured) for GraphQL,
+    /// the statement's placeholders for SQL.
+    pub fn generated_input_schema(&self) -> Option<Result<HashMap<String, Value>>> {
+        match self {
+            ToolBackend::Http => None,
+            ToolBackend::Graphql(graphql) => graphql
+                .schema_file
+                .as_ref()
+                .map(|_| graphql.input_schema()),
+            ToolBackend::Grpc(grpc) => Some(grpc.input_schema()),
+            ToolBackend::Sql(sql) => Some(sql.input_schema()),
+            ToolBackend::Mcp(_)
+            | ToolBackend::Workflow(_)
+            | ToolBackend::FanOut(_)
+            | ToolBackend::Wasm(_)
+            | ToolBackend::Native(_)
+            | ToolBackend::Mock(_) => None,
+        }
+    }
+}
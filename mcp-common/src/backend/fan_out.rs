@@ -0,0 +1,125 @@
+use std::{collections::HashSet, time::Duration};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    constants::constants::fan_out_consts::{DEFAULT_BRANCH_TIMEOUT_MS, MAX_FAN_OUT_MEMBERS},
+    transform::template,
+};
+
+/// A tool that calls several other tools at once, e.g. the regional
+/// endpoints of one search API, and merges what they return.
+///
+/// Members run concurrently, each with its own timeout. Their results are
+/// merged by `merge`:
+/// - `concat`: one JSON array of every member's items, in member order; an
+///   array result adds its items, any other result adds itself.
+/// - `keyed`: one JSON object with each member's result under its id.
+/// - `first_success`: the result of whichever member succeeds first; the
+///   others are cancelled.
+///
+/// Members that fail or time out are listed after the merged result instead
+/// of failing the call; only when every member fails is the result an error.
+///
+/// ```json
+/// {
+///   "type": "fan_out",
+///   "members": [
+///     { "id": "eu", "tool_id": "search_eu" },
+///     { "id": "us", "tool_id": "search_us", "timeout_ms": 3000 },
+///     { "id": "ap", "tool_id": "search_ap", "arguments": { "q": "{{ args.query }}" } }
+///   ],
+///   "merge": "concat",
+///   "timeout_ms": 5000
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanOutBackend {
+    pub members: Vec<FanOutMember>,
+    #[serde(default)]
+    pub merge: FanOutMerge,
+    // Time limit of each member, unless the member sets its own
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanOutMember {
+    // Unique within the fan-out, names the member in results and failures
+    pub id: String,
+    // The TDS called, run with its own backend, limits and result cache
+    pub tool_id: String,
+    // Arguments of the call, templates as in workflow steps; without it the
+    // member gets the fan-out's own arguments
+    #[serde(default)]
+    pub arguments: Option<Map<String, Value>>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// How member results are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FanOutMerge {
+    #[default]
+    Concat,
+    Keyed,
+    FirstSuccess,
+}
+
+impl FanOutBackend {
+    pub fn validate(&self) -> Result<()> {
+        if self.members.is_empty() {
+            return Err(anyhow!("fan_out has no members"));
+        }
+        if self.members.len() > MAX_FAN_OUT_MEMBERS {
+            return Err(anyhow!(
+                "fan_out has more than {} members",
+                MAX_FAN_OUT_MEMBERS
+            ));
+        }
+        if self.timeout_ms == Some(0) {
+            return Err(anyhow!("timeout_ms must be greater than zero"));
+        }
+        let mut ids = HashSet::new();
+        for member in &self.members {
+            if member.id.is_empty() {
+                return Err(anyhow!("fan_out member id is empty"));
+            }
+            if !ids.insert(member.id.as_str()) {
+                return Err(anyhow!("duplicate fan_out member `{}`", member.id));
+            }
+            member
+                .validate()
+                .map_err(|e| anyhow!("member `{}`: {}", member.id, e))?;
+        }
+        Ok(())
+    }
+
+    /// Time limit of a member's call.
+    pub fn timeout(&self, member: &FanOutMember) -> Duration {
+        Duration::from_millis(
+            member
+                .timeout_ms
+                .or(self.timeout_ms)
+                .unwrap_or(DEFAULT_BRANCH_TIMEOUT_MS),
+        )
+    }
+}
+
+impl FanOutMember {
+    fn validate(&self) -> Result<()> {
+        if self.tool_id.is_empty() {
+            return Err(anyhow!("tool_id is empty"));
+        }
+        if self.timeout_ms == Some(0) {
+            return Err(anyhow!("timeout_ms must be greater than zero"));
+        }
+        for value in self.arguments.iter().flat_map(Map::values) {
+            template::check_json(value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use anyhow::{anyhow, Context, Result};
+use graphql_parser::{
+    parse_query, parse_schema,
+    query::{self, Definition, OperationDefinition, Type, VariableDefinition},
+    schema::{self, TypeDefinition},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// A tool backed by a stored GraphQL operation.
+///
+/// The request is POSTed to `domain` + `path` of the TDS. Each variable of the
+/// operation is filled from the tool argument of the same name, unless
+/// `variables` maps it to another argument. The result is the response's
+/// `data`; GraphQL `errors` make the call an `isError` result.
+///
+/// ```json
+/// {
+///   "type": "graphql",
+///   "query": "query User($id: ID!) { user(id: $id) { name email } }",
+///   "variables": { "id": "user_id" },
+///   "schema_file": "schemas/users.graphql"
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphqlBackend {
+    // The stored query or mutation document
+    pub query: String,
+    // Operation to run when the document holds several
+    pub operation_name: Option<String>,
+    // GraphQL variable -> tool argument name
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    // SDL file used to generate `input_schema` when the TDS has none
+    pub schema_file: Option<String>,
+}
+
+impl GraphqlBackend {
+    pub fn validate(&self) -> Result<()> {
+        let doc = parse_query::<String>(&self.query)
+            .map_err(|e| anyhow!("Invalid GraphQL document: {}", e))?;
+        let variables = operation_variables(&doc, self.operation_name.as_deref())?;
+        for name in self.variables.keys() {
+            if !variables.iter().any(|v| &v.name == name) {
+                return Err(anyhow!("`variables` maps unknown variable `${}`", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn argument_name<'a>(&'a self, variable: &'a str) -> &'a str {
+        self.variables
+            .get(variable)
+            .map(String::as_str)
+            .unwrap_or(variable)
+    }
+
+    /// Builds the JSON request body: query, operation name and the variables
+    /// taken from the tool arguments. Absent arguments are left out so server
+    /// side defaults apply.
+    pub fn request_body(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        let doc = parse_query::<String>(&self.query)?;
+        let mut variables = Map::new();
+        for def in operation_variables(&doc, self.operation_name.as_deref())? {
+            if let Some(value) = args.get(self.argument_name(&def.name)) {
+                variables.insert(def.name.clone(), value.clone());
+            }
+        }
+        let mut body = json!({ "query": self.query, "variables": variables });
+        if let Some(name) = &self.operation_name {
+            body["operationName"] = json!(name);
+        }
+        Ok(body)
+    }
+
+    /// Generates a JSON Schema for the tool arguments from the operation's
+    /// variable definitions, resolving input types against `schema_file`.
+    pub fn input_schema(&self) -> Result<HashMap<String, Value>> {
+        let path = self
+            .schema_file
+            .as_ref()
+            .ok_or_else(|| anyhow!("No schema_file to generate input_schema from"))?;
+        let sdl = read_sdl(path)?;
+        let schema_doc =
+            parse_schema::<String>(&sdl).map_err(|e| anyhow!("Invalid GraphQL schema: {}", e))?;
+        let types: HashMap<&str, &TypeDefinition<String>> = schema_doc
+            .definitions
+            .iter()
+            .filter_map(|def| match def {
+                schema::Definition::TypeDefinition(t) => Some((type_name(t), t)),
+                _ => None,
+            })
+            .collect();
+
+        let doc = parse_query::<String>(&self.query)?;
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for def in operation_variables(&doc, self.operation_name.as_deref())? {
+            let arg = self.argument_name(&def.name).to_string();
+            let mut prop = type_schema(&def.var_type, &types, &mut HashSet::new());
+            if let Some(default) = &def.default_value {
+                prop["default"] = const_value(default);
+            }
+            if matches!(def.var_type, Type::NonNullType(_)) && def.default_value.is_none() {
+                required.push(json!(arg));
+            }
+            properties.insert(arg, prop);
+        }
+
+        Ok(HashMap::from([
+            ("type".to_string(), json!("object")),
+            ("properties".to_string(), Value::Object(properties)),
+            ("required".to_string(), Value::Array(required)),
+        ]))
+    }
+}
+
+/// Reads an SDL file. Schemas are generated from `McpCache::insert_tds`, which
+/// is sync but runs on runtime workers, so the read is moved off the worker
+/// where the runtime allows it.
+fn read_sdl(path: &str) -> Result<String> {
+    let read = || {
+        fs::read_to_string(path).with_context(|| format!("Failed to read GraphQL schema {}", path))
+    };
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) if runtime.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(read)
+        }
+        _ => read(),
+    }
+}
+
+/// Splits a GraphQL response into `data`, or the joined `errors` messages.
+pub fn unwrap_response(body: &str) -> std::result::Result<Value, String> {
+    let response: Value = serde_json::from_str(body)
+        .map_err(|e| format!("GraphQL response is not JSON: {}", e))?;
+    if let Some(errors) = response.get("errors").and_then(Value::as_array) {
+        if !errors.is_empty() {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|err| {
+                    let message = err
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error");
+                    match err.get("path").and_then(Value::as_array) {
+                        Some(path) => {
+                            let path: Vec<String> = path
+                                .iter()
+                                .map(|p| p.as_str().map_or_else(|| p.to_string(), str::to_string))
+                                .collect();
+                            format!("{} (at {})", message, path.join("."))
+                        }
+                        None => message.to_string(),
+                    }
+                })
+                .collect();
+            return Err(format!("GraphQL errors: {}", messages.join("; ")));
+        }
+    }
+    Ok(response.get("data").cloned().unwrap_or(Value::Null))
+}
+
+fn operation_variables<'d, 'a>(
+    doc: &'d query::Document<'a, String>,
+    operation_name: Option<&str>,
+) -> Result<&'d [VariableDefinition<'a, String>]> {
+    let operations: Vec<(Option<&String>, &[VariableDefinition<'a, String>])> = doc
+        .definitions
+        .iter()
+        .filter_map(|def| match def {
+            Definition::Operation(OperationDefinition::Query(q)) => {
+                Some((q.name.as_ref(), q.variable_definitions.as_slice()))
+            }
+            Definition::Operation(OperationDefinition::Mutation(m)) => {
+                Some((m.name.as_ref(), m.variable_definitions.as_slice()))
+            }
+            Definition::Operation(OperationDefinition::SelectionSet(_)) => Some((None, &[][..])),
+            Definition::Operation(OperationDefinition::Subscription(_)) => None,
+            Definition::Fragment(_) => None,
+        })
+        .collect();
+    match (operation_name, operations.as_slice()) {
+        (None, [(_, variables)]) => Ok(variables),
+        (None, []) => Err(anyhow!("Document has no query or mutation")),
+        (None, _) => Err(anyhow!("Document has several operations, set operation_name")),
+        (Some(name), ops) => ops
+            .iter()
+            .find(|(op_name, _)| op_name.is_some_and(|n| n == name))
+            .map(|(_, variables)| *variables)
+            .ok_or_else(|| anyhow!("Operation `{}` not found in document", name)),
+    }
+}
+
+fn type_name<'a>(def: &'a TypeDefinition<String>) -> &'a str {
+    match def {
+        TypeDefinition::Scalar(t) => &t.name,
+        TypeDefinition::Object(t) => &t.name,
+        TypeDefinition::Interface(t) => &t.name,
+        TypeDefinition::Union(t) => &t.name,
+        TypeDefinition::Enum(t) => &t.name,
+        TypeDefinition::InputObject(t) => &t.name,
+    }
+}
+
+fn type_schema(
+    ty: &Type<String>,
+    types: &HashMap<&str, &TypeDefinition<String>>,
+    // input objects being expanded, to stop on recursive types
+    visiting: &mut HashSet<String>,
+) -> Value {
+    match ty {
+        Type::NonNullType(inner) => type_schema(inner, types, visiting),
+        Type::ListType(inner) => json!({ "type": "array", "items": type_schema(inner, types, visiting) }),
+        Type::NamedType(name) => match name.as_str() {
+            "Int" => json!({ "type": "integer" }),
+            "Float" => json!({ "type": "number" }),
+            "String" | "ID" => json!({ "type": "string" }),
+            "Boolean" => json!({ "type": "boolean" }),
+            other => match types.get(other) {
+                Some(TypeDefinition::Enum(e)) => json!({
+                    "type": "string",
+                    "enum": e.values.iter().map(|v| v.name.clone()).collect::<Vec<_>>(),
+                }),
+                Some(TypeDefinition::InputObject(input)) if visiting.insert(other.to_string()) => {
+                    let mut properties = Map::new();
+                    let mut required = Vec::new();
+                    for field in &input.fields {
+                        let mut prop = type_schema(&field.value_type, types, visiting);
+                        if let Some(description) = &field.description {
+                            prop["description"] = json!(description);
+                        }
+                        if matches!(field.value_type, Type::NonNullType(_))
+                            && field.default_value.is_none()
+                        {
+                            required.push(json!(field.name));
+                        }
+                        properties.insert(field.name.clone(), prop);
+                    }
+                    visiting.remove(other);
+                    json!({ "type": "object", "properties": properties, "required": required })
+                }
+                Some(TypeDefinition::InputObject(_)) => json!({ "type": "object" }),
+                // custom scalars accept any JSON value
+                _ => json!({ "description": format!("GraphQL type {}", other) }),
+            },
+        },
+    }
+}
+
+fn const_value(value: &query::Value<String>) -> Value {
+    match value {
+        query::Value::Int(n) => n.as_i64().map_or(Value::Null, |n| json!(n)),
+        query::Value::Float(f) => json!(f),
+        query::Value::String(s) => json!(s),
+        query::Value::Boolean(b) => json!(b),
+        query::Value::Enum(e) => json!(e),
+        query::Value::List(items) => Value::Array(items.iter().map(const_value).collect()),
+        query::Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), const_value(v)))
+                .collect(),
+        ),
+        query::Value::Null | query::Value::Variable(_) => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    fn backend() -> GraphqlBackend {
+        let schema_file = test_dir().join("users.graphql");
+        fs::write(
+            &schema_file,
+            "input UserFilter { name: String, active: Boolean! }\ntype Query { users(filter: UserFilter!, first: Int): [String] }",
+        )
+        .unwrap();
+        GraphqlBackend {
+            query: "query Users($filter: UserFilter!, $first: Int = 10) { users(filter: $filter, first: $first) }".to_string(),
+            operation_name: None,
+            variables: HashMap::from([("first".to_string(), "limit".to_string())]),
+            schema_file: Some(schema_file.display().to_string()),
+        }
+    }
+
+    fn check(schema: HashMap<String, Value>) {
+        assert_eq!(schema["required"], json!(["filter"]));
+        assert_eq!(schema["properties"]["limit"]["default"], json!(10));
+        assert_eq!(
+            schema["properties"]["filter"]["properties"]["active"]["type"],
+            json!("boolean")
+        );
+    }
+
+    #[test]
+    fn input_schema_is_generated_from_the_sdl() {
+        check(backend().input_schema().unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn input_schema_reads_the_sdl_off_the_runtime_worker() {
+        check(backend().input_schema().unwrap());
+    }
+
+    #[test]
+    fn request_body_maps_variables_to_arguments() {
+        let args = HashMap::from([
+            ("filter".to_string(), json!({ "active": true })),
+            ("limit".to_string(), json!(5)),
+        ]);
+        let body = backend().request_body(&args).unwrap();
+        assert_eq!(body["variables"], json!({ "filter": { "active": true }, "first": 5 }));
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use prost_reflect::{
+    DescriptorPool, DeserializeOptions, DynamicMessage, FieldDescriptor, Kind, MessageDescriptor,
+    MethodDescriptor, SerializeOptions,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+// Decoded descriptor pools keyed by the SHA-256 of the inline descriptor set,
+// or by the path of the file
+static DESCRIPTOR_POOLS: Lazy<DashMap<String, CachedPool>> = Lazy::new(DashMap::new);
+
+struct CachedPool {
+    // modification time and size of a file when it was read; a file that
+    // changed since is read again
+    stamp: Option<(SystemTime, u64)>,
+    pool: DescriptorPool,
+}
+
+/// A tool backed by a gRPC unary method.
+///
+/// The TDS `domain` is the endpoint (e.g. "http://orders:50051"). Request and
+/// response messages are transcoded to and from JSON with the descriptors in a
+/// FileDescriptorSet, built with
+/// `protoc --include_imports --descriptor_set_out=orders.pb orders.proto` and
+/// either uploaded inline (base64) or read from a file.
+///
+/// ```json
+/// {
+///   "type": "grpc",
+///   "method": "orders.v1.OrderService/GetOrder",
+///   "descriptor_set": "CpQBChJvcmRlcnMvdjEvb3JkZXIucHJvdG8S...",
+///   "metadata": { "x-tenant": "acme" }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcBackend {
+    // Fully qualified method: "package.Service/Method" or "package.Service.Method"
+    pub method: String,
+    // Base64-encoded FileDescriptorSet
+    pub descriptor_set: Option<String>,
+    // Path of a FileDescriptorSet file, used when `descriptor_set` is unset
+    pub descriptor_set_file: Option<String>,
+    // Request metadata (gRPC headers) sent with every call
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl GrpcBackend {
+    pub fn validate(&self) -> Result<()> {
+        self.method_descriptor().map(|_| ())
+    }
+
+    fn descriptor_pool(&self) -> Result<DescriptorPool> {
+        let (source, stamp) = match (&self.descriptor_set, &self.descriptor_set_file) {
+            (Some(encoded), _) => {
+                let digest = hex::encode(Sha256::digest(encoded));
+                (format!("sha256:{}", digest), None)
+            }
+            (None, Some(path)) => {
+                let metadata = fs::metadata(path)
+                    .with_context(|| format!("Failed to read descriptor set {}", path))?;
+                (format!("file:{}", path), Some((metadata.modified()?, metadata.len())))
+            }
+            (None, None) => return Err(anyhow!("descriptor_set or descriptor_set_file is required")),
+        };
+        if let Some(cached) = DESCRIPTOR_POOLS.get(&source) {
+            if cached.stamp == stamp {
+                return Ok(cached.pool.clone());
+            }
+        }
+        let bytes = match (&self.descriptor_set, &self.descriptor_set_file) {
+            (Some(encoded), _) => STANDARD
+                .decode(encoded)
+                .map_err(|e| anyhow!("descriptor_set is not valid base64: {}", e))?,
+            (None, Some(path)) => fs::read(path)
+                .with_context(|| format!("Failed to read descriptor set {}", path))?,
+            (None, None) => unreachable!(),
+        };
+        let pool = DescriptorPool::decode(bytes.as_slice())
+            .map_err(|e| anyhow!("Invalid FileDescriptorSet: {}", e))?;
+        DESCRIPTOR_POOLS.insert(
+            source,
+            CachedPool {
+                stamp,
+                pool: pool.clone(),
+            },
+        );
+        Ok(pool)
+    }
+
+    pub fn method_descriptor(&self) -> Result<MethodDescriptor> {
+        let (service_name, method_name) = self
+            .method
+            .rsplit_once('/')
+            .or_else(|| self.method.rsplit_once('.'))
+            .ok_or_else(|| anyhow!("method must be `package.Service/Method`"))?;
+        let pool = self.descriptor_pool()?;
+        let service = pool
+            .get_service_by_name(service_name)
+            .ok_or_else(|| anyhow!("Service `{}` not in descriptor set", service_name))?;
+        let method = service
+            .methods()
+            .find(|m| m.name() == method_name)
+            .ok_or_else(|| anyhow!("Method `{}` not found on `{}`", method_name, service_name))?;
+        if method.is_client_streaming() || method.is_server_streaming() {
+            return Err(anyhow!("Only unary methods are supported: {}", self.method));
+        }
+        Ok(method)
+    }
+
+    /// The HTTP/2 path of the method, e.g. "/orders.v1.OrderService/GetOrder".
+    pub fn path(&self) -> Result<String> {
+        let method = self.method_descriptor()?;
+        Ok(format!("/{}/{}", method.parent_service().full_name(), method.name()))
+    }
+
+    /// Transcodes tool arguments into the request message (proto3 JSON mapping).
+    pub fn request_message(&self, args: &HashMap<String, Value>) -> Result<DynamicMessage> {
+        let input = self.method_descriptor()?.input();
+        DynamicMessage::deserialize_with_options(
+            input,
+            json!(args),
+            &DeserializeOptions::new().deny_unknown_fields(false),
+        )
+        .map_err(|e| anyhow!("Arguments do not match the request message: {}", e))
+    }
+
+    /// Generates a JSON Schema for the tool arguments from the request message.
+    pub fn input_schema(&self) -> Result<HashMap<String, Value>> {
+        let input = self.method_descriptor()?.input();
+        let schema = message_schema(&input, &mut HashSet::new());
+        match schema {
+            Value::Object(map) => Ok(map.into_iter().collect()),
+            _ => Ok(HashMap::new()),
+        }
+    }
+}
+
+/// Transcodes a response message to JSON, keeping fields at their defaults so
+/// the model sees the full shape.
+pub fn message_to_json(message: &DynamicMessage) -> Result<Value> {
+    let options = SerializeOptions::new().skip_default_fields(false);
+    Ok(message.serialize_with_options(serde_json::value::Serializer, &options)?)
+}
+
+fn message_schema(message: &MessageDescriptor, visiting: &mut HashSet<String>) -> Value {
+    match message.full_name() {
+        "google.protobuf.Timestamp" => return json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" => return json!({ "type": "string" }),
+        "google.protobuf.Struct" => return json!({ "type": "object" }),
+        "google.protobuf.Value" | "google.protobuf.Any" => return json!({}),
+        name if name.starts_with("google.protobuf.") && name.ends_with("Value") => {
+            // wrapper types map to their single `value` field
+            return message
+                .get_field_by_name("value")
+                .map_or(json!({}), |f| kind_schema(&f.kind(), visiting));
+        }
+        _ => {}
+    }
+    // recursive messages are cut off at the second visit
+    if !visiting.insert(message.full_name().to_string()) {
+        return json!({ "type": "object" });
+    }
+    let mut properties = Map::new();
+    for field in message.fields() {
+        properties.insert(field.json_name().to_string(), field_schema(&field, visiting));
+    }
+    visiting.remove(message.full_name());
+    json!({ "type": "object", "properties": properties })
+}
+
+fn field_schema(field: &FieldDescriptor, visiting: &mut HashSet<String>) -> Value {
+    if field.is_map() {
+        let value_schema = match field.kind() {
+            Kind::Message(entry) => kind_schema(&entry.map_entry_value_field().kind(), visiting),
+            _ => json!({}),
+        };
+        return json!({ "type": "object", "additionalProperties": value_schema });
+    }
+    let schema = kind_schema(&field.kind(), visiting);
+    if field.is_list() {
+        json!({ "type": "array", "items": schema })
+    } else {
+        schema
+    }
+}
+
+fn kind_schema(kind: &Kind, visiting: &mut HashSet<String>) -> Value {
+    match kind {
+        Kind::Double | Kind::Float => json!({ "type": "number" }),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Uint32 | Kind::Fixed32 => {
+            json!({ "type": "integer" })
+        }
+        // proto3 JSON writes 64-bit integers as strings but accepts both
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 | Kind::Uint64 | Kind::Fixed64 => {
+            json!({ "type": ["integer", "string"] })
+        }
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::String => json!({ "type": "string" }),
+        Kind::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        Kind::Enum(e) => json!({
+            "type": "string",
+            "enum": e.values().map(|v| v.name().to_string()).collect::<Vec<_>>(),
+        }),
+        Kind::Message(message) => message_schema(message, visiting),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{echo_descriptor_set, test_dir};
+
+    #[test]
+    fn inline_descriptor_sets_are_cached_by_their_hash() {
+        let encoded = STANDARD.encode(echo_descriptor_set());
+        let backend = GrpcBackend {
+            method: "test.Echo/Get".to_string(),
+            descriptor_set: Some(encoded.clone()),
+            descriptor_set_file: None,
+            metadata: HashMap::new(),
+        };
+        assert_eq!(backend.path().unwrap(), "/test.Echo/Get");
+
+        let key = format!("sha256:{}", hex::encode(Sha256::digest(&encoded)));
+        assert!(DESCRIPTOR_POOLS.contains_key(&key));
+        assert!(!DESCRIPTOR_POOLS.contains_key(&encoded));
+    }
+
+    #[test]
+    fn descriptor_set_files_are_read_again_when_they_change() {
+        let path = test_dir().join("echo.pb");
+        fs::write(&path, echo_descriptor_set()).unwrap();
+        let backend = GrpcBackend {
+            method: "test.Echo/Get".to_string(),
+            descriptor_set: None,
+            descriptor_set_file: Some(path.to_string_lossy().into_owned()),
+            metadata: HashMap::new(),
+        };
+        assert_eq!(backend.path().unwrap(), "/test.Echo/Get");
+
+        fs::write(&path, b"not a descriptor set").unwrap();
+        let err = backend.path().unwrap_err();
+        assert!(err.to_string().starts_with("Invalid FileDescriptorSet"), "{}", err);
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// A tool proxied to a tool of another MCP server.
+///
+/// The server's Streamable HTTP endpoint is `domain` + `path` of the TDS.
+/// dynmcp initializes a session with it on first use and forwards the
+/// arguments as they are; the upstream result, non-text content included,
+/// is relayed unchanged.
+///
+/// ```json
+/// {
+///   "type": "mcp",
+///   "tool": "search_issues",
+///   "headers": { "Authorization": "Bearer ..." }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpBackend {
+    // Tool name on the upstream server
+    pub tool: String,
+    // Headers sent with every request to the upstream, e.g. its auth
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl McpBackend {
+    pub fn validate(&self) -> Result<()> {
+        if self.tool.is_empty() {
+            return Err(anyhow!("tool is empty"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_json_path::JsonPath;
+
+use crate::{constants::constants::mock_consts::MAX_MOCK_LATENCY_MS, transform::template};
+
+/// Canned responses instead of an upstream, for developing agents without
+/// touching real APIs.
+///
+/// Rules are tried in order and the first match answers; `default` answers
+/// when none does. A rule matches when every listed `arguments` value equals
+/// the tool argument of the same name and its `predicate`, a JSONPath filter
+/// expression on the arguments (`@`), selects them. The same shape serves as
+/// the TDS `mock`, used instead of the backend while the calling IDS is in
+/// mock mode.
+///
+/// Strings in a response `body` are templates seeing the arguments as
+/// `args`. The body goes through `post_response` and `response_transform`
+/// like an upstream body, and a non-2xx `status` makes it an error result.
+/// A `content_type` of `image/*` or `audio/*` returns the body, base64
+/// encoded data, as an MCP image or audio item instead of text; it goes
+/// through `post_response` and the result cap but not `response_transform`.
+///
+/// ```json
+/// {
+///   "type": "mock",
+///   "rules": [
+///     {
+///       "arguments": { "city": "Paris" },
+///       "response": { "body": { "city": "Paris", "temp_c": 18 }, "latency_ms": 300 }
+///     },
+///     {
+///       "predicate": "@.days > 7",
+///       "response": { "status": 400, "body": "forecasts reach 7 days ahead, not {{ args.days }}" }
+///     }
+///   ],
+///   "default": { "body": { "city": "{{ args.city }}", "temp_c": 21 } }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockBackend {
+    #[serde(default)]
+    pub rules: Vec<MockRule>,
+    // Response when no rule matches; without it the call fails
+    #[serde(default)]
+    pub default: Option<MockResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockRule {
+    // Arguments that must be present with exactly these values
+    #[serde(default)]
+    pub arguments: Option<Map<String, Value>>,
+    // JSONPath filter expression on the arguments, e.g. "@.amount > 100"
+    #[serde(default)]
+    pub predicate: Option<String>,
+    pub response: MockResponse,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockResponse {
+    // Simulated upstream status, default 200
+    #[serde(default)]
+    pub status: Option<u16>,
+    // Delay before answering, at most 60s and never past the tool's call timeout
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    // image/* and audio/* return MCP media content, anything else text
+    #[serde(default)]
+    pub content_type: Option<String>,
+    // Text, or JSON sent as text; strings are templates
+    #[serde(default)]
+    pub body: Value,
+}
+
+impl MockBackend {
+    pub fn validate(&self) -> Result<()> {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.arguments.is_none() && rule.predicate.is_none() {
+                return Err(anyhow!(
+                    "rule {} has neither arguments nor predicate, use default instead",
+                    i
+                ));
+            }
+            if let Some(predicate) = &rule.predicate {
+                predicate_path(predicate).map_err(|e| anyhow!("rule {}: {}", i, e))?;
+            }
+            rule.response
+                .validate()
+                .map_err(|e| anyhow!("rule {}: {}", i, e))?;
+        }
+        if let Some(default) = &self.default {
+            default.validate().map_err(|e| anyhow!("default: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// The response of the first matching rule, else the default.
+    pub fn respond(&self, args: &HashMap<String, Value>) -> Result<Option<&MockResponse>> {
+        for rule in &self.rules {
+            if rule.matches(args)? {
+                return Ok(Some(&rule.response));
+            }
+        }
+        Ok(self.default.as_ref())
+    }
+}
+
+impl MockRule {
+    fn matches(&self, args: &HashMap<String, Value>) -> Result<bool> {
+        let arguments_match = self.arguments.as_ref().is_none_or(|expected| {
+            expected
+                .iter()
+                .all(|(name, value)| args.get(name) == Some(value))
+        });
+        if !arguments_match {
+            return Ok(false);
+        }
+        match &self.predicate {
+            Some(predicate) => {
+                // the filter runs over a one-element array so `@` is the arguments
+                let document = Value::Array(vec![serde_json::to_value(args)?]);
+                Ok(!predicate_path(predicate)?.query(&document).is_empty())
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+impl MockResponse {
+    fn validate(&self) -> Result<()> {
+        if let Some(status) = self.status {
+            if !(100..=599).contains(&status) {
+                return Err(anyhow!("status {} is not an HTTP status", status));
+            }
+        }
+        if self.latency_ms.is_some_and(|latency| latency > MAX_MOCK_LATENCY_MS) {
+            return Err(anyhow!("latency_ms is capped at {}", MAX_MOCK_LATENCY_MS));
+        }
+        if self.media_type().is_some() && !self.body.is_string() {
+            return Err(anyhow!("image and audio bodies must be base64 strings"));
+        }
+        template::check_json(&self.body)
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status.unwrap_or(200)
+    }
+
+    /// The MCP content type for image and audio responses.
+    pub fn media_type(&self) -> Option<&'static str> {
+        let content_type = self.content_type.as_deref()?;
+        if content_type.starts_with("image/") {
+            Some("image")
+        } else if content_type.starts_with("audio/") {
+            Some("audio")
+        } else {
+            None
+        }
+    }
+}
+
+fn predicate_path(predicate: &str) -> Result<JsonPath> {
+    JsonPath::parse(&format!("$[?{}]", predicate))
+        .map_err(|e| anyhow!("invalid predicate `{}`: {}", predicate, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn backend() -> MockBackend {
+        serde_json::from_value(json!({
+            "rules": [
+                { "arguments": { "city": "Paris" }, "response": { "body": "paris" } },
+                { "predicate": "@.days > 7", "response": { "status": 400, "body": "too far" } },
+                {
+                    "arguments": { "city": "Oslo" },
+                    "predicate": "@.days > 3",
+                    "response": { "body": "oslo, later" }
+                }
+            ],
+            "default": { "body": "default" }
+        }))
+        .unwrap()
+    }
+
+    fn respond(backend: &MockBackend, args: Value) -> Option<Value> {
+        let args: HashMap<String, Value> = serde_json::from_value(args).unwrap();
+        backend.respond(&args).unwrap().map(|response| response.body.clone())
+    }
+
+    #[test]
+    fn the_first_matching_rule_answers() {
+        let backend = backend();
+        backend.validate().unwrap();
+        // the first two rules both match
+        assert_eq!(
+            respond(&backend, json!({ "city": "Paris", "days": 10 })),
+            Some(json!("paris"))
+        );
+        assert_eq!(
+            respond(&backend, json!({ "city": "Rome", "days": 10 })),
+            Some(json!("too far"))
+        );
+        // arguments and predicate must both match
+        assert_eq!(
+            respond(&backend, json!({ "city": "Oslo", "days": 5 })),
+            Some(json!("oslo, later"))
+        );
+        assert_eq!(
+            respond(&backend, json!({ "city": "Oslo", "days": 2 })),
+            Some(json!("default"))
+        );
+        // values are compared as JSON, not as text
+        assert_eq!(
+            respond(&backend, json!({ "city": ["Paris"] })),
+            Some(json!("default"))
+        );
+    }
+
+    #[test]
+    fn without_a_default_unmatched_calls_get_no_response() {
+        let backend = MockBackend {
+            default: None,
+            ..backend()
+        };
+        assert_eq!(respond(&backend, json!({ "city": "Rome", "days": 1 })), None);
+    }
+
+    #[test]
+    fn rules_need_a_valid_condition() {
+        let unconditional: MockBackend = serde_json::from_value(json!({
+            "rules": [{ "response": { "body": "always" } }]
+        }))
+        .unwrap();
+        assert!(unconditional.validate().is_err());
+
+        let broken: MockBackend = serde_json::from_value(json!({
+            "rules": [{ "predicate": "@.days >", "response": { "body": "never" } }]
+        }))
+        .unwrap();
+        assert!(broken.validate().is_err());
+    }
+}
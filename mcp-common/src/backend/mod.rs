@@ -0,0 +1,10 @@
+pub mod backend;
+pub mod fan_out;
+pub mod graphql;
+pub mod grpc;
+pub mod mcp;
+pub mod mock;
+pub mod native;
+pub mod sql;
+pub mod wasm;
+pub mod workflow;
@@ -0,0 +1,29 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// A tool implemented in Rust inside dynmcp, registered with `#[mcp_tool]`.
+///
+/// Every native tool gets a built-in TDS (`builtin/<tool>`) at startup that
+/// IDS entries can list like any other; further TDS entries may expose the
+/// same tool under another name or with their own limits.
+///
+/// ```json
+/// {
+///   "type": "native",
+///   "tool": "current_time"
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NativeBackend {
+    // Name the tool was registered under
+    pub tool: String,
+}
+
+impl NativeBackend {
+    pub fn validate(&self) -> Result<()> {
+        if self.tool.is_empty() {
+            return Err(anyhow!("tool is empty"));
+        }
+        Ok(())
+    }
+}
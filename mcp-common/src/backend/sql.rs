@@ -0,0 +1,236 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    constants::constants::sql_consts::{DEFAULT_SQL_MAX_ROWS, DEFAULT_SQL_TIMEOUT_MS},
+    provider::global_provider::get_app_config, sql::sql_pool_provider::validate_pool_config,
+};
+
+// Leading keywords of statements that only read
+const READ_KEYWORDS: [&str; 8] = [
+    "SELECT", "WITH", "SHOW", "EXPLAIN", "DESCRIBE", "DESC", "VALUES", "TABLE",
+];
+
+/// A tool backed by a named, parameterized SQL statement.
+///
+/// `pool` names a database from `[sql.pools]`. Each `:name` placeholder in the
+/// statement is bound, as a parameter and never by string splicing, from the
+/// tool argument of the same name; absent arguments bind `NULL`. Rows come
+/// back as a JSON array of objects keyed by column name, other statements as
+/// `{ "rows_affected": n }`.
+///
+/// Tools are read-only unless `read_only` is turned off: the statement must
+/// start with a reading keyword (SELECT, WITH, SHOW, ...) and runs in a
+/// read-only transaction (`query_only` on SQLite), so writes hidden in a CTE
+/// are still refused by the database.
+///
+/// ```json
+/// {
+///   "type": "sql",
+///   "pool": "reporting",
+///   "statement": "SELECT id, total, created_at FROM orders WHERE customer_id = :customer_id AND (:status IS NULL OR status = :status)",
+///   "max_rows": 200,
+///   "timeout_ms": 5000
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlBackend {
+    // Pool name from `[sql.pools]`
+    pub pool: String,
+    // A single statement with `:name` placeholders
+    pub statement: String,
+    // Rows returned at most; further rows are dropped and the result says so
+    pub max_rows: Option<usize>,
+    // Time limit of the call, including the wait for a connection
+    pub timeout_ms: Option<u64>,
+    // Only reading statements, run in a read-only transaction
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+}
+
+fn default_read_only() -> bool {
+    true
+}
+
+/// A statement rewritten for positional binding.
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    // The statement with every `:name` replaced by `?`
+    pub sql: String,
+    // Argument name of each `?`, in order (a name may repeat)
+    pub params: Vec<String>,
+}
+
+impl SqlBackend {
+    pub fn validate(&self) -> Result<()> {
+        let config = get_app_config()?;
+        let pool = config
+            .sql
+            .pools
+            .get(&self.pool)
+            .ok_or_else(|| anyhow!("unknown sql pool `{}`", self.pool))?;
+        validate_pool_config(&self.pool, pool)?;
+        if pool.read_only && !self.read_only {
+            return Err(anyhow!("sql pool `{}` is read-only", self.pool));
+        }
+        if self.max_rows == Some(0) || self.timeout_ms == Some(0) {
+            return Err(anyhow!("max_rows and timeout_ms must be greater than zero"));
+        }
+        self.prepare()?;
+        if self.read_only && !self.returns_rows() {
+            return Err(anyhow!(
+                "read-only tools only run statements starting with {}",
+                READ_KEYWORDS.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn max_rows(&self) -> usize {
+        self.max_rows.unwrap_or(DEFAULT_SQL_MAX_ROWS)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_SQL_TIMEOUT_MS))
+    }
+
+    /// Whether the statement yields rows, judged by its leading keyword.
+    pub fn returns_rows(&self) -> bool {
+        let keyword = leading_keyword(&self.statement);
+        READ_KEYWORDS.contains(&keyword.as_str())
+    }
+
+    /// Rewrites `:name` placeholders to `?`, skipping string literals, quoted
+    /// identifiers, comments and `::` casts. Rejects more than one statement.
+    pub fn prepare(&self) -> Result<PreparedStatement> {
+        let chars: Vec<char> = self.statement.chars().collect();
+        let mut sql = String::with_capacity(self.statement.len());
+        let mut params = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '\'' | '"' | '`' => {
+                    let end = quoted_end(&chars, i);
+                    sql.extend(&chars[i..end]);
+                    i = end;
+                }
+                '-' if chars.get(i + 1) == Some(&'-') => {
+                    let end = chars[i..]
+                        .iter()
+                        .position(|&c| c == '\n')
+                        .map_or(chars.len(), |p| i + p);
+                    sql.extend(&chars[i..end]);
+                    i = end;
+                }
+                '/' if chars.get(i + 1) == Some(&'*') => {
+                    let end = (i + 2..chars.len().saturating_sub(1))
+                        .find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+                        .map_or(chars.len(), |j| j + 2);
+                    sql.extend(&chars[i..end]);
+                    i = end;
+                }
+                ':' if chars.get(i + 1) == Some(&':') => {
+                    sql.push_str("::");
+                    i += 2;
+                }
+                ':' if chars
+                    .get(i + 1)
+                    .is_some_and(|c| c.is_ascii_alphabetic() || *c == '_') =>
+                {
+                    let end = (i + 1..chars.len())
+                        .find(|&j| !(chars[j].is_ascii_alphanumeric() || chars[j] == '_'))
+                        .unwrap_or(chars.len());
+                    params.push(chars[i + 1..end].iter().collect());
+                    sql.push('?');
+                    i = end;
+                }
+                ';' => {
+                    let rest: String = chars[i + 1..].iter().collect();
+                    if !skip_leading_comments(&rest).trim().is_empty() {
+                        return Err(anyhow!("statement must be a single SQL statement"));
+                    }
+                    break;
+                }
+                _ => {
+                    sql.push(c);
+                    i += 1;
+                }
+            }
+        }
+        if sql.trim().is_empty() {
+            return Err(anyhow!("statement is empty"));
+        }
+        Ok(PreparedStatement { sql, params })
+    }
+
+    /// Values for the placeholders of `prepared`, `NULL` for absent arguments.
+    pub fn parameters(&self, prepared: &PreparedStatement, args: &HashMap<String, Value>) -> Vec<Value> {
+        prepared
+            .params
+            .iter()
+            .map(|name| args.get(name).cloned().unwrap_or(Value::Null))
+            .collect()
+    }
+
+    /// Generates an `input_schema` listing the statement's placeholders.
+    /// Column types are unknown here, so properties carry no `type`.
+    pub fn input_schema(&self) -> Result<HashMap<String, Value>> {
+        let prepared = self.prepare()?;
+        let mut properties = Map::new();
+        for name in prepared.params {
+            properties.insert(
+                name.clone(),
+                json!({ "description": format!("Value bound to :{}", name) }),
+            );
+        }
+        Ok(HashMap::from([
+            ("type".to_string(), json!("object")),
+            ("properties".to_string(), Value::Object(properties)),
+        ]))
+    }
+}
+
+/// Index just past the literal or quoted identifier opening at `start`.
+/// Doubled quotes and backslash escapes stay inside the literal.
+fn quoted_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if quote != '`' => i += 2,
+            c if c == quote && chars.get(i + 1) == Some(&quote) => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+fn skip_leading_comments(sql: &str) -> String {
+    let mut out = String::new();
+    let mut rest = sql;
+    loop {
+        rest = rest.trim_start();
+        if let Some(line) = rest.strip_prefix("--") {
+            rest = line.split_once('\n').map_or("", |(_, r)| r);
+        } else if let Some(block) = rest.strip_prefix("/*") {
+            rest = block.split_once("*/").map_or("", |(_, r)| r);
+        } else {
+            out.push_str(rest);
+            return out;
+        }
+    }
+}
+
+fn leading_keyword(sql: &str) -> String {
+    skip_leading_comments(sql)
+        .trim_start_matches(|c: char| c == '(' || c.is_whitespace())
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
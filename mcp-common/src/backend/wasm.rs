@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+use crate::constants::constants::wasm_consts::{
+    DEFAULT_WASM_FUEL, DEFAULT_WASM_MAX_MEMORY_BYTES, DEFAULT_WASM_TIMEOUT_MS, MAX_WASM_TIMEOUT_MS,
+};
+
+/// A tool implemented by a WASM plugin (a PDS), run in a fresh sandboxed
+/// instance per call.
+///
+/// The component exports `call(arguments: string) -> result<string, string>`
+/// taking the tool arguments as JSON; `ok` becomes the result text, `err` an
+/// `isError` result. It may import `http-call` from `dynmcp:plugin/host`,
+/// which only reaches `allowed_hosts` and uses the TDS's `tls_profile`,
+/// `call_policy` and `response_limits`. Redirects are returned to the plugin
+/// rather than followed. The WIT world is in `wasm::bindings`.
+///
+/// ```json
+/// {
+///   "type": "wasm",
+///   "plugin": "geo-lookup",
+///   "fuel": 500000000,
+///   "max_memory_bytes": 33554432,
+///   "timeout_ms": 5000,
+///   "allowed_hosts": ["api.geo.example.com", "*.internal.example.com"]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmBackend {
+    // Id of the PDS holding the component
+    pub plugin: String,
+    // Fuel (roughly, wasm instructions) one call may burn
+    #[serde(default)]
+    pub fuel: Option<u64>,
+    // Linear memory one instance may grow to
+    #[serde(default)]
+    pub max_memory_bytes: Option<usize>,
+    // Wall-clock time one call may take, default 10s, at most 60s
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    // Hosts `http-call` may reach, `*.` prefixes match subdomains; empty
+    // denies every call
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+impl WasmBackend {
+    pub fn validate(&self) -> Result<()> {
+        if self.plugin.is_empty() {
+            return Err(anyhow!("plugin is empty"));
+        }
+        if self.fuel == Some(0) || self.max_memory_bytes == Some(0) || self.timeout_ms == Some(0) {
+            return Err(anyhow!(
+                "fuel, max_memory_bytes and timeout_ms must be greater than zero"
+            ));
+        }
+        if self.timeout_ms.is_some_and(|ms| ms > MAX_WASM_TIMEOUT_MS) {
+            return Err(anyhow!("timeout_ms must be at most {}", MAX_WASM_TIMEOUT_MS));
+        }
+        if self.allowed_hosts.iter().any(|host| host.is_empty() || host.contains('/')) {
+            return Err(anyhow!("allowed_hosts must be host names"));
+        }
+        Ok(())
+    }
+
+    pub fn fuel(&self) -> u64 {
+        self.fuel.unwrap_or(DEFAULT_WASM_FUEL)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_WASM_TIMEOUT_MS))
+    }
+
+    pub fn max_memory_bytes(&self) -> usize {
+        self.max_memory_bytes.unwrap_or(DEFAULT_WASM_MAX_MEMORY_BYTES)
+    }
+
+    /// Whether `http-call` may reach `host`.
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+                None => host == allowed,
+            }
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::transform::template;
+
+/// A composite tool: a chain (or DAG) of calls to other tools and inline
+/// HTTP requests, run within one `tools/call`.
+///
+/// Steps run one at a time in dependency order. Templates and expressions
+/// (minijinja) see the tool arguments as `args`, the calling IDS's metadata
+/// as `ids`, and every finished step as `steps.<id>` with its `result` (the
+/// parsed JSON, else the text), `text` and `skipped`. The first failing step
+/// ends the workflow with an error naming it.
+///
+/// ```json
+/// {
+///   "type": "workflow",
+///   "steps": [
+///     {
+///       "id": "user",
+///       "call": { "type": "tool", "tool_id": "find_user_by_email" },
+///       "arguments": { "email": "{{ args.email }}" }
+///     },
+///     {
+///       "id": "orders",
+///       "when": "steps.user.result.id",
+///       "call": {
+///         "type": "http",
+///         "method": "GET",
+///         "url": "https://shop.internal/users/{{ steps.user.result.id }}/orders"
+///       }
+///     }
+///   ],
+///   "output": "{{ steps.user.result.name }}: {{ steps.orders.result | length }} orders"
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowBackend {
+    pub steps: Vec<WorkflowStep>,
+    // Template rendering the tool result; defaults to the text of the last
+    // step that ran
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    // Unique within the workflow, the step's key under `steps`
+    pub id: String,
+    // Steps that must finish first; without it the step follows the one
+    // declared before it, `[]` starts it right away
+    #[serde(default)]
+    pub depends_on: Option<Vec<String>>,
+    // Expression; the step is skipped unless it is truthy
+    #[serde(default)]
+    pub when: Option<String>,
+    pub call: StepCall,
+    // Arguments of a `tool` call. Strings are templates, and a string that is
+    // a single `{{ ... }}` keeps the JSON type of its value
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+}
+
+/// What a workflow step calls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepCall {
+    // Another TDS, run with its own backend, limits and result cache
+    Tool { tool_id: String },
+    // A one-off request; header values and `body` are templates, and so are
+    // the path and query of `url`, whose interpolated values are URL-encoded
+    Http {
+        method: String,
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default)]
+        body: Option<Value>,
+    },
+}
+
+impl WorkflowBackend {
+    pub fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            return Err(anyhow!("workflow has no steps"));
+        }
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if step.id.is_empty() {
+                return Err(anyhow!("workflow step id is empty"));
+            }
+            if !ids.insert(step.id.as_str()) {
+                return Err(anyhow!("duplicate workflow step `{}`", step.id));
+            }
+        }
+        for step in &self.steps {
+            step.validate(&ids)
+                .map_err(|e| anyhow!("step `{}`: {}", step.id, e))?;
+        }
+        self.order()?;
+        if let Some(output) = &self.output {
+            template::check(output).map_err(|e| anyhow!("output: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// The steps in the order they run: dependencies first, otherwise as
+    /// declared.
+    pub fn order(&self) -> Result<Vec<&WorkflowStep>> {
+        let mut done: HashSet<&str> = HashSet::new();
+        let mut order = Vec::with_capacity(self.steps.len());
+        while order.len() < self.steps.len() {
+            let next = self.steps.iter().enumerate().find(|(i, step)| {
+                !done.contains(step.id.as_str())
+                    && self.dependencies(*i).iter().all(|dep| done.contains(dep))
+            });
+            let Some((_, step)) = next else {
+                return Err(anyhow!("workflow steps depend on each other in a cycle"));
+            };
+            done.insert(&step.id);
+            order.push(step);
+        }
+        Ok(order)
+    }
+
+    fn dependencies(&self, index: usize) -> Vec<&str> {
+        match &self.steps[index].depends_on {
+            Some(deps) => deps.iter().map(String::as_str).collect(),
+            None if index == 0 => Vec::new(),
+            None => vec![self.steps[index - 1].id.as_str()],
+        }
+    }
+}
+
+impl WorkflowStep {
+    fn validate(&self, ids: &HashSet<&str>) -> Result<()> {
+        for dep in self.depends_on.iter().flatten() {
+            if dep == &self.id {
+                return Err(anyhow!("depends on itself"));
+            }
+            if !ids.contains(dep.as_str()) {
+                return Err(anyhow!("depends on unknown step `{}`", dep));
+            }
+        }
+        if let Some(when) = &self.when {
+            template::check_expression(when)?;
+        }
+        match &self.call {
+            StepCall::Tool { tool_id } => {
+                if tool_id.is_empty() {
+                    return Err(anyhow!("tool_id is empty"));
+                }
+                for value in self.arguments.values() {
+                    template::check_json(value)?;
+                }
+            }
+            StepCall::Http {
+                method,
+                url,
+                headers,
+                body,
+            } => {
+                if method.is_empty() || url.is_empty() {
+                    return Err(anyhow!("http calls need a method and a url"));
+                }
+                if !self.arguments.is_empty() {
+                    return Err(anyhow!("arguments only apply to tool calls, use body"));
+                }
+                template::check_url(url)?;
+                for value in headers.values() {
+                    template::check(value)?;
+                }
+                if let Some(body) = body {
+                    template::check_json(body)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
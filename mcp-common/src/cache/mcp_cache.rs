@@ -1,8 +1,17 @@
-use dashmap::DashMap;
+use anyhow::{anyhow, Result};
+use dashmap::{DashMap, DashSet};
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::xds::{ids::IDS, tds::TDS};
+use crate::{
+    cache::result_cache::get_result_cache,
+    cluster::cluster_manager::get_cluster_manager,
+    constants::constants::mcp_cache_consts::BUILTIN_TDS_PREFIX,
+    rate_limit::rate_limiter::get_rate_limiters,
+    script::script_engine::get_script_engine,
+    wasm::wasm_runtime::get_wasm_runtime,
+    xds::{cds::CDS, ids::{IDSMetadata, IDS}, mds::MDS, pds::PDS, tds::TDS},
+};
 
 #[derive(Clone)]
 pub struct McpCache {
@@ -12,6 +21,25 @@ pub struct McpCache {
 
     // xDS Object: Instance Discovery Service (IDS)
     ids_map: Arc<DashMap<String, IDS>>,
+    // ids of the IDS entries whose metadata sets `mock_mode`
+    mock_mode_ids: Arc<DashSet<String>>,
+
+    // xDS Object: upstream MCP servers whose tools are mirrored (MDS)
+    mds_map: Arc<DashMap<String, MDS>>,
+
+    // xDS Object: WASM tool plugins (PDS), key to plugin id; the compiled
+    // components live in the wasm runtime
+    pds_map: Arc<DashMap<String, String>>,
+
+    // xDS Object: Cluster Discovery Service (CDS), key to cluster name; the
+    // endpoints and their health live in the cluster manager
+    cds_map: Arc<DashMap<String, String>>,
+}
+
+impl Default for McpCache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl McpCache {
@@ -20,6 +48,10 @@ impl McpCache {
             tds_map: Arc::new(DashMap::new()),
             tds_name_map: Arc::new(DashMap::new()),
             ids_map: Arc::new(DashMap::new()),
+            mock_mode_ids: Arc::new(DashSet::new()),
+            mds_map: Arc::new(DashMap::new()),
+            pds_map: Arc::new(DashMap::new()),
+            cds_map: Arc::new(DashMap::new()),
         }
     }
 
@@ -45,7 +77,63 @@ impl McpCache {
             .collect()
     }
 
+    /// Caches a TDS from the data source. Entries that would shadow a
+    /// built-in tool are dropped, see `check_builtin_collision`.
     pub fn insert_tds(&self, key: String, value: TDS) {
+        if let Err(e) = self.check_builtin_collision(&value) {
+            warn!("Skipping TDS {}: {}", value.id, e);
+            return;
+        }
+        self.insert(key, value);
+    }
+
+    /// Caches the TDS of a native tool, under `builtin/<name>`.
+    pub fn insert_builtin_tds(&self, value: TDS) {
+        self.insert(value.id.clone(), value);
+    }
+
+    /// Refuses a data source TDS under the `builtin/` prefix or named like a
+    /// built-in tool, which would otherwise replace it in lookups by name.
+    pub fn check_builtin_collision(&self, tds: &TDS) -> Result<()> {
+        if tds.id.starts_with(BUILTIN_TDS_PREFIX) {
+            return Err(anyhow!("ids under `{}` are reserved", BUILTIN_TDS_PREFIX));
+        }
+        let builtin = self
+            .tds_name_map
+            .get(&tds.name)
+            .is_some_and(|id| id.starts_with(BUILTIN_TDS_PREFIX));
+        if builtin {
+            return Err(anyhow!("`{}` is the name of a built-in tool", tds.name));
+        }
+        Ok(())
+    }
+
+    fn insert(&self, key: String, mut value: TDS) {
+        // cached results may no longer match the new definition
+        get_result_cache().invalidate_tool(&value.id);
+        // its upstream's shared limits are recomputed from the new definition
+        get_rate_limiters().declare_tool(
+            &value.id,
+            value.tds_ext_info.rate_limit_upstream(),
+            value.tds_ext_info.rate_limit.as_ref(),
+        );
+        // GraphQL, gRPC and SQL tools may derive their input schema from the backend
+        if value.input_schema.is_empty() {
+            match value.tds_ext_info.backend.generated_input_schema() {
+                Some(Ok(schema)) => value.input_schema = schema,
+                Some(Err(e)) => warn!("Failed to generate input_schema for {}: {}", value.id, e),
+                None => {}
+            }
+        }
+        // scripts are compiled once here rather than on every call
+        match &value.tds_ext_info.scripts {
+            Some(scripts) => {
+                if let Err(e) = get_script_engine().compile(&value.id, scripts) {
+                    warn!("Failed to compile scripts of {}: {}", value.id, e);
+                }
+            }
+            None => get_script_engine().remove(&value.id),
+        }
         self.tds_map.insert(key, value.clone());
         self.tds_name_map.insert(value.name, value.id);
     }
@@ -53,15 +141,28 @@ impl McpCache {
     pub fn remove_tds(&self, key: &str) {
         if let Some(tool) = self.tds_map.get(key) {
             let name = tool.name.clone();
+            get_result_cache().remove_tool(&tool.id);
+            get_rate_limiters().forget_tool(&tool.id);
+            get_script_engine().remove(&tool.id);
+            drop(tool);
             self.tds_map.remove(key);
             self.tds_name_map.remove(&name);
         }
     }
 
+    /// TDS entries mirrored from the upstream MCP server `mds_id`.
+    pub fn list_tds_mirrored_from(&self, mds_id: &str) -> Vec<TDS> {
+        self.tds_map
+            .iter()
+            .filter(|entry| entry.tds_ext_info.mirrored_from.as_deref() == Some(mds_id))
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
     pub fn list_tds_by_ids_id(&self, ids_id: &str) -> Vec<TDS> {
         debug!(?self.ids_map, "Full ids_map before lookup");
         self.ids_map.get(ids_id).map_or_else(
-            || vec![],
+            Vec::new,
             |ids| {
                 ids.tool_ids
                     .iter()
@@ -72,14 +173,168 @@ impl McpCache {
     }
 
     pub fn insert_ids(&self, key: String, value: IDS) {
-        self.ids_map.insert(key, value.clone());
+        // parsed once here rather than on every tool call
+        let mock_mode = serde_json::from_str::<IDSMetadata>(&value.metadata)
+            .is_ok_and(|metadata| metadata.mock_mode);
+        if mock_mode {
+            self.mock_mode_ids.insert(key.clone());
+        } else {
+            self.mock_mode_ids.remove(&key);
+        }
+        self.ids_map.insert(key, value);
     }
 
     pub fn remove_ids(&self, key: &str) {
+        self.mock_mode_ids.remove(key);
         self.ids_map.remove(key);
     }
 
     pub fn get_ids(&self, id: &str) -> Option<IDS> {
         self.ids_map.get(id).map(|v| v.value().clone())
     }
+
+    /// Whether the IDS answers tool calls from the TDS mocks.
+    pub fn is_mock_mode(&self, id: &str) -> bool {
+        self.mock_mode_ids.contains(id)
+    }
+
+    pub fn insert_mds(&self, key: String, value: MDS) {
+        self.mds_map.insert(key, value);
+    }
+
+    pub fn remove_mds(&self, key: &str) {
+        self.mds_map.remove(key);
+    }
+
+    pub fn list_mds(&self) -> Vec<MDS> {
+        self.mds_map
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    pub async fn insert_pds(&self, key: String, value: PDS) {
+        let loaded = match get_wasm_runtime() {
+            Ok(runtime) => runtime.load(&value).await,
+            Err(e) => Err(e),
+        };
+        match loaded {
+            Ok(()) => {
+                self.pds_map.insert(key, value.id);
+            }
+            Err(e) => warn!("Failed to load wasm plugin {}: {}", value.id, e),
+        }
+    }
+
+    pub fn remove_pds(&self, key: &str) {
+        if let Some((_, id)) = self.pds_map.remove(key) {
+            if let Ok(runtime) = get_wasm_runtime() {
+                runtime.remove(&id);
+            }
+        }
+    }
+
+    /// Loads a CDS from the data source into the cluster manager. Invalid
+    /// entries are dropped, as are ones that would replace the cluster of
+    /// another CDS, see `check_cluster_name`.
+    pub fn insert_cds(&self, key: String, value: CDS) {
+        if let Err(e) = value.validate().and_then(|_| self.check_cluster_name(&value)) {
+            warn!("Skipping CDS {}: {}", value.id, e);
+            return;
+        }
+        let name = value.name.clone();
+        get_cluster_manager().upsert(value);
+        if let Some(old) = self.cds_map.insert(key, name.clone()) {
+            if old != name {
+                get_cluster_manager().remove(&old);
+            }
+        }
+    }
+
+    /// Refuses a CDS named like the cluster of another CDS: clusters are
+    /// looked up by name, so the last one loaded would silently win.
+    pub fn check_cluster_name(&self, cds: &CDS) -> Result<()> {
+        match get_cluster_manager().cluster_id(&cds.name) {
+            Some(id) if id != cds.id => Err(anyhow!(
+                "cluster `{}` is already defined by CDS `{}`",
+                cds.name,
+                id
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn remove_cds(&self, key: &str) {
+        if let Some((_, name)) = self.cds_map.remove(key) {
+            get_cluster_manager().remove(&name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::init_test_config, xds::cds::ClusterEndpoint};
+
+    fn ids(metadata: &str) -> IDS {
+        IDS {
+            id: "team".to_string(),
+            name: "team".to_string(),
+            tool_ids: Vec::new(),
+            metadata: metadata.to_string(),
+        }
+    }
+
+    #[test]
+    fn mock_mode_follows_the_cached_ids_metadata() {
+        let cache = McpCache::new();
+        cache.insert_ids(
+            "team".to_string(),
+            ids(r#"{"proto_type": "streamable-stateless", "mock_mode": true}"#),
+        );
+        assert!(cache.is_mock_mode("team"));
+
+        cache.insert_ids("team".to_string(), ids(r#"{"proto_type": "legacy"}"#));
+        assert!(!cache.is_mock_mode("team"));
+
+        cache.insert_ids("team".to_string(), ids(r#"{"mock_mode": true}"#));
+        assert!(!cache.is_mock_mode("team"), "metadata that does not parse is not mock mode");
+
+        cache.insert_ids(
+            "team".to_string(),
+            ids(r#"{"proto_type": "legacy", "mock_mode": true}"#),
+        );
+        cache.remove_ids("team");
+        assert!(!cache.is_mock_mode("team"));
+    }
+
+    fn cds(id: &str, name: &str, url: &str) -> CDS {
+        CDS {
+            id: id.to_string(),
+            name: name.to_string(),
+            endpoints: vec![ClusterEndpoint {
+                url: url.to_string(),
+                weight: 1,
+            }],
+            lb_policy: Default::default(),
+            health_check: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_and_clashing_cds_are_not_loaded() {
+        init_test_config();
+        let cache = McpCache::new();
+        let cluster_id = |name| get_cluster_manager().cluster_id(name);
+
+        cache.insert_cds("first".to_string(), cds("first", "shared", "http://a.test"));
+        cache.insert_cds("second".to_string(), cds("second", "shared", "http://b.test"));
+        assert_eq!(cluster_id("shared").as_deref(), Some("first"));
+        assert!(cache.check_cluster_name(&cds("second", "shared", "http://b.test")).is_err());
+        // the CDS that defines the cluster may still change it
+        assert!(cache.check_cluster_name(&cds("first", "shared", "http://c.test")).is_ok());
+
+        cache.insert_cds("bad".to_string(), cds("bad", "no-scheme", "a.test"));
+        assert_eq!(cluster_id("no-scheme"), None);
+    }
 }
@@ -1 +1,2 @@
-pub mod mcp_cache;
\ No newline at end of file
+pub mod mcp_cache;
+pub mod result_cache;
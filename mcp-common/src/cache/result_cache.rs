@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+
+static RESULT_CACHE: Lazy<ToolResultCache> = Lazy::new(ToolResultCache::new);
+
+/// Returns the global tool result cache.
+pub fn get_result_cache() -> &'static ToolResultCache {
+    &RESULT_CACHE
+}
+
+/// Who a cached result is shared with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheScope {
+    // every caller of the tool
+    #[default]
+    Global,
+    // callers coming through the same IDS
+    Instance,
+    // callers sharing an `Mcp-Session-Id`
+    Session,
+}
+
+/// Opt-in result caching of a TDS, for read-only lookups.
+///
+/// ```json
+/// { "ttl_ms": 60000, "max_entries": 500, "scope": "instance" }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultCacheConfig {
+    pub ttl_ms: u64,
+    #[serde(default = "default_max_entries")]
+    pub max_entries: u64,
+    #[serde(default)]
+    pub scope: CacheScope,
+}
+
+fn default_max_entries() -> u64 {
+    1_000
+}
+
+impl ResultCacheConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.ttl_ms == 0 || self.max_entries == 0 {
+            return Err(anyhow!("ttl_ms and max_entries must be greater than zero"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    // changes on every invalidation; a call that started under an older
+    // generation must not cache its result
+    generation: AtomicU64,
+}
+
+/// Per-tool cache statistics, returned by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultCacheSnapshot {
+    pub tool_id: String,
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+}
+
+/// Tool call results keyed by tool id, scope and normalized arguments.
+///
+/// Each tool gets its own moka cache so TTL and size bound are per TDS, and a
+/// TDS change simply drops the tool's cache (see `McpCache::insert_tds`).
+/// Counters outlive invalidations and are dropped with the tool.
+pub struct ToolResultCache {
+    caches: DashMap<String, Cache<String, Value>>,
+    counters: DashMap<String, CacheCounters>,
+    // source of generations, unique across tools and their re-creations
+    next_generation: AtomicU64,
+}
+
+impl ToolResultCache {
+    fn new() -> Self {
+        Self {
+            caches: DashMap::new(),
+            counters: DashMap::new(),
+            next_generation: AtomicU64::new(1),
+        }
+    }
+
+    fn counters(&self, tool_id: &str) -> dashmap::mapref::one::Ref<'_, String, CacheCounters> {
+        if let Some(counters) = self.counters.get(tool_id) {
+            return counters;
+        }
+        self.counters
+            .entry(tool_id.to_string())
+            .or_insert_with(|| CacheCounters {
+                generation: AtomicU64::new(self.next_generation.fetch_add(1, Ordering::Relaxed)),
+                ..Default::default()
+            })
+            .downgrade()
+    }
+
+    /// The tool's current generation, taken before a call whose result may be
+    /// cached and handed back to `insert`.
+    pub fn generation(&self, tool_id: &str) -> u64 {
+        self.counters(tool_id).generation.load(Ordering::SeqCst)
+    }
+
+    /// Builds the cache key for one call, or `None` when the scope can't be
+    /// resolved (e.g. session scope without a session id).
+    pub fn key(
+        config: &ResultCacheConfig,
+        args: &HashMap<String, Value>,
+        ids_id: &str,
+        session_id: Option<&str>,
+    ) -> Option<String> {
+        let principal = match config.scope {
+            CacheScope::Global => "",
+            CacheScope::Instance => ids_id,
+            CacheScope::Session => session_id?,
+        };
+        let args = serde_json::to_value(args).ok()?;
+        Some(format!("{}\u{0}{}", principal, canonical_json(&args)))
+    }
+
+    pub async fn get(&self, tool_id: &str, key: &str) -> Option<Value> {
+        let hit = match self.caches.get(tool_id).map(|c| c.value().clone()) {
+            Some(cache) => cache.get(key).await,
+            None => None,
+        };
+        let counters = self.counters(tool_id);
+        match hit {
+            Some(_) => counters.hits.fetch_add(1, Ordering::Relaxed),
+            None => counters.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        hit
+    }
+
+    /// Caches a result, unless the tool was invalidated since `generation`
+    /// was taken: the call may have run against the old definition.
+    pub async fn insert(
+        &self,
+        tool_id: &str,
+        config: &ResultCacheConfig,
+        key: String,
+        value: Value,
+        generation: u64,
+    ) {
+        let is_current = || {
+            self.counters
+                .get(tool_id)
+                .is_some_and(|counters| counters.generation.load(Ordering::SeqCst) == generation)
+        };
+        if !is_current() {
+            debug!("Tool result cache skipped a stale result for tool: {}", tool_id);
+            return;
+        }
+        let cache = self
+            .caches
+            .entry(tool_id.to_string())
+            .or_insert_with(|| {
+                Cache::builder()
+                    .max_capacity(config.max_entries)
+                    .time_to_live(Duration::from_millis(config.ttl_ms))
+                    .build()
+            })
+            .value()
+            .clone();
+        // checked again after taking the cache: a later invalidation drops
+        // this cache instance, along with what is inserted into it
+        if !is_current() {
+            return;
+        }
+        cache.insert(key, value).await;
+    }
+
+    /// Drops every cached result of a tool; called when its TDS changes.
+    pub fn invalidate_tool(&self, tool_id: &str) {
+        // the generation moves first: an `insert` that still sees the old one
+        // has already taken the cache instance removed below
+        let counters = self.counters(tool_id);
+        counters.generation.store(
+            self.next_generation.fetch_add(1, Ordering::SeqCst),
+            Ordering::SeqCst,
+        );
+        let removed = self.caches.remove(tool_id).is_some();
+        if removed {
+            debug!("Tool result cache invalidated for tool: {}", tool_id);
+            counters.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drops a deleted tool's results and counters.
+    pub fn remove_tool(&self, tool_id: &str) {
+        self.caches.remove(tool_id);
+        self.counters.remove(tool_id);
+    }
+
+    pub fn snapshots(&self) -> Vec<ResultCacheSnapshot> {
+        self.counters
+            .iter()
+            .map(|entry| ResultCacheSnapshot {
+                tool_id: entry.key().clone(),
+                entries: self
+                    .caches
+                    .get(entry.key())
+                    .map_or(0, |cache| cache.entry_count()),
+                hits: entry.hits.load(Ordering::Relaxed),
+                misses: entry.misses.load(Ordering::Relaxed),
+                invalidations: entry.invalidations.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// JSON with object keys sorted at every depth, so equal arguments give
+/// equal keys regardless of the order the client sent them in.
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), canonical_json(v)))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn config() -> ResultCacheConfig {
+        ResultCacheConfig {
+            ttl_ms: 60_000,
+            max_entries: 10,
+            scope: CacheScope::Global,
+        }
+    }
+
+    #[tokio::test]
+    async fn results_are_cached_under_the_current_generation() {
+        let cache = ToolResultCache::new();
+        assert_eq!(cache.get("tool", "k").await, None);
+        let generation = cache.generation("tool");
+        cache.insert("tool", &config(), "k".into(), json!(1), generation).await;
+        assert_eq!(cache.get("tool", "k").await, Some(json!(1)));
+
+        let snapshot = &cache.snapshots()[0];
+        assert_eq!((snapshot.hits, snapshot.misses), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn a_call_finishing_after_an_invalidation_is_not_cached() {
+        let cache = ToolResultCache::new();
+        let generation = cache.generation("tool");
+        // the TDS changes while the call is in flight
+        cache.invalidate_tool("tool");
+        cache.insert("tool", &config(), "k".into(), json!("stale"), generation).await;
+        assert_eq!(cache.get("tool", "k").await, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn racing_an_invalidation_never_leaves_a_stale_result() {
+        let cache = std::sync::Arc::new(ToolResultCache::new());
+        for _ in 0..2_000 {
+            let generation = cache.generation("tool");
+            let inserting = cache.clone();
+            let insert = tokio::spawn(async move {
+                inserting
+                    .insert("tool", &config(), "k".into(), json!("stale"), generation)
+                    .await
+            });
+            let invalidating = cache.clone();
+            let invalidate = tokio::spawn(async move { invalidating.invalidate_tool("tool") });
+            insert.await.unwrap();
+            invalidate.await.unwrap();
+            assert_eq!(cache.get("tool", "k").await, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn removed_tools_leave_nothing_behind() {
+        let cache = ToolResultCache::new();
+        let generation = cache.generation("tool");
+        cache.remove_tool("tool");
+        cache.insert("tool", &config(), "k".into(), json!(1), generation).await;
+        assert!(cache.snapshots().is_empty());
+        assert!(cache.caches.is_empty());
+
+        // a tool re-created under the same id starts a new generation
+        assert_ne!(cache.generation("tool"), generation);
+    }
+
+    #[test]
+    fn keys_ignore_argument_order() {
+        let a = serde_json::from_value(json!({ "a": 1, "b": { "x": 1, "y": 2 } })).unwrap();
+        let b = serde_json::from_value(json!({ "b": { "y": 2, "x": 1 }, "a": 1 })).unwrap();
+        assert_eq!(
+            ToolResultCache::key(&config(), &a, "ids", None),
+            ToolResultCache::key(&config(), &b, "ids", None)
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::provider::global_provider::get_app_config;
+
+static CIRCUIT_BREAKERS: OnceCell<CircuitBreakerRegistry> = OnceCell::new();
+
+/// Returns the global breaker registry, built from `[circuit_breaker]` on first use.
+pub fn get_circuit_breakers() -> Result<&'static CircuitBreakerRegistry> {
+    CIRCUIT_BREAKERS.get_or_try_init(|| {
+        let config = get_app_config()?;
+        Ok(CircuitBreakerRegistry::new(config.circuit_breaker.clone()))
+    })
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    // Number of most recent calls the rates are computed over
+    pub window_size: usize,
+    // Calls needed in the window before the breaker may trip
+    pub min_calls: usize,
+    // Failure rate (0.0 - 1.0) that opens the circuit
+    pub failure_rate_threshold: f64,
+    // Calls slower than this count as slow
+    pub slow_call_ms: u64,
+    // Slow call rate (0.0 - 1.0) that opens the circuit
+    pub slow_call_rate_threshold: f64,
+    // How long the circuit stays open before probing
+    pub open_duration_ms: u64,
+    // Probe calls allowed (and required to succeed) while half-open
+    pub half_open_calls: usize,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_size: 50,
+            min_calls: 20,
+            failure_rate_threshold: 0.5,
+            slow_call_ms: 10_000,
+            slow_call_rate_threshold: 0.8,
+            open_duration_ms: 30_000,
+            half_open_calls: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CallOutcome {
+    failed: bool,
+    slow: bool,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: CircuitState,
+    window: VecDeque<CallOutcome>,
+    opened_at: Option<Instant>,
+    // half-open bookkeeping
+    probes_in_flight: usize,
+    probes_succeeded: usize,
+}
+
+/// Point-in-time view of one breaker, returned by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitSnapshot {
+    pub key: String,
+    pub state: CircuitState,
+    pub calls: usize,
+    pub failure_rate: f64,
+    pub slow_call_rate: f64,
+    // Milliseconds until an open circuit starts probing
+    pub retry_in_ms: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    key: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(key: &str, config: CircuitBreakerConfig) -> Self {
+        Self {
+            key: key.to_string(),
+            inner: Mutex::new(BreakerInner {
+                state: CircuitState::Closed,
+                window: VecDeque::with_capacity(config.window_size),
+                opened_at: None,
+                probes_in_flight: 0,
+                probes_succeeded: 0,
+            }),
+            config,
+        }
+    }
+
+    /// Asks permission to call the upstream. Returns `None` while the circuit
+    /// is open, or half-open with all probe slots taken.
+    pub fn try_acquire(self: &Arc<Self>) -> Option<BreakerPermit> {
+        self.admit().map(|probe_of| BreakerPermit {
+            breaker: self.clone(),
+            started: Instant::now(),
+            probe_of,
+            recorded: false,
+        })
+    }
+
+    /// Whether calls are rejected now: the circuit is open and not yet due
+    /// for probing.
+    pub fn is_rejecting(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        let open_for = Duration::from_millis(self.config.open_duration_ms);
+        inner.state == CircuitState::Open
+            && inner.opened_at.is_some_and(|at| at.elapsed() < open_for)
+    }
+
+    // `Some(None)` admits a call, `Some(Some(opened_at))` a probe of the
+    // opening at `opened_at`
+    fn admit(&self) -> Option<Option<Instant>> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CircuitState::Closed => Some(None),
+            CircuitState::Open => {
+                let open_for = Duration::from_millis(self.config.open_duration_ms);
+                if inner.opened_at.is_some_and(|at| at.elapsed() >= open_for) {
+                    info!("Circuit `{}` half-open, probing upstream", self.key);
+                    inner.state = CircuitState::HalfOpen;
+                    inner.probes_in_flight = 1;
+                    inner.probes_succeeded = 0;
+                    Some(inner.opened_at)
+                } else {
+                    None
+                }
+            }
+            CircuitState::HalfOpen => {
+                if inner.probes_in_flight < self.config.half_open_calls {
+                    inner.probes_in_flight += 1;
+                    Some(inner.opened_at)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // Frees the slot of a probe that ended without an outcome, unless the
+    // circuit has moved on since the probe was admitted.
+    fn release(&self, opened_at: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == CircuitState::HalfOpen && inner.opened_at == Some(opened_at) {
+            inner.probes_in_flight = inner.probes_in_flight.saturating_sub(1);
+        }
+    }
+
+    fn record(&self, failed: bool, latency: Duration) {
+        let slow = latency >= Duration::from_millis(self.config.slow_call_ms);
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CircuitState::Closed => {
+                if inner.window.len() == self.config.window_size {
+                    inner.window.pop_front();
+                }
+                inner.window.push_back(CallOutcome { failed, slow });
+                let (failure_rate, slow_rate) = rates(&inner.window);
+                if inner.window.len() >= self.config.min_calls
+                    && (failure_rate >= self.config.failure_rate_threshold
+                        || slow_rate >= self.config.slow_call_rate_threshold)
+                {
+                    warn!(
+                        "Circuit `{}` opened: failure_rate={:.2}, slow_call_rate={:.2}",
+                        self.key, failure_rate, slow_rate
+                    );
+                    self.open(&mut inner);
+                }
+            }
+            CircuitState::HalfOpen => {
+                inner.probes_in_flight = inner.probes_in_flight.saturating_sub(1);
+                if failed || slow {
+                    warn!("Circuit `{}` probe failed, re-opening", self.key);
+                    self.open(&mut inner);
+                } else {
+                    inner.probes_succeeded += 1;
+                    if inner.probes_succeeded >= self.config.half_open_calls {
+                        info!("Circuit `{}` closed", self.key);
+                        inner.state = CircuitState::Closed;
+                        inner.window.clear();
+                        inner.opened_at = None;
+                    }
+                }
+            }
+            // a call admitted before the circuit opened; nothing to update
+            CircuitState::Open => {}
+        }
+    }
+
+    pub fn snapshot(&self) -> CircuitSnapshot {
+        let inner = self.inner.lock().unwrap();
+        let (failure_rate, slow_call_rate) = rates(&inner.window);
+        let retry_in_ms = match (inner.state, inner.opened_at) {
+            (CircuitState::Open, Some(at)) => Some(
+                Duration::from_millis(self.config.open_duration_ms)
+                    .saturating_sub(at.elapsed())
+                    .as_millis() as u64,
+            ),
+            _ => None,
+        };
+        CircuitSnapshot {
+            key: self.key.clone(),
+            state: inner.state,
+            calls: inner.window.len(),
+            failure_rate,
+            slow_call_rate,
+            retry_in_ms,
+        }
+    }
+
+    fn open(&self, inner: &mut BreakerInner) {
+        inner.state = CircuitState::Open;
+        inner.opened_at = Some(Instant::now());
+        inner.probes_in_flight = 0;
+        inner.probes_succeeded = 0;
+    }
+}
+
+/// Permission for one call, from `CircuitBreaker::try_acquire`.
+///
+/// The call's latency is measured from the acquire. A permit dropped without
+/// `record` (the caller cancelled the call) says nothing about the upstream:
+/// it is not counted, and only gives back its half-open probe slot.
+#[derive(Debug)]
+pub struct BreakerPermit {
+    breaker: Arc<CircuitBreaker>,
+    started: Instant,
+    // the opening this permit probes, if it was admitted half-open
+    probe_of: Option<Instant>,
+    recorded: bool,
+}
+
+impl BreakerPermit {
+    pub fn record(mut self, failed: bool) {
+        self.recorded = true;
+        self.breaker.record(failed, self.started.elapsed());
+    }
+}
+
+impl Drop for BreakerPermit {
+    fn drop(&mut self) {
+        if let (false, Some(opened_at)) = (self.recorded, self.probe_of) {
+            self.breaker.release(opened_at);
+        }
+    }
+}
+
+fn rates(window: &VecDeque<CallOutcome>) -> (f64, f64) {
+    if window.is_empty() {
+        return (0.0, 0.0);
+    }
+    let total = window.len() as f64;
+    let failed = window.iter().filter(|o| o.failed).count() as f64;
+    let slow = window.iter().filter(|o| o.slow).count() as f64;
+    (failed / total, slow / total)
+}
+
+/// Breakers keyed by upstream (the TDS domain), created on first call.
+pub struct CircuitBreakerRegistry {
+    config: CircuitBreakerConfig,
+    breakers: DashMap<String, Arc<CircuitBreaker>>,
+}
+
+impl CircuitBreakerRegistry {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: DashMap::new(),
+        }
+    }
+
+    /// Returns the breaker for `key`, or `None` when breakers are disabled.
+    pub fn get(&self, key: &str) -> Option<Arc<CircuitBreaker>> {
+        if !self.config.enabled {
+            return None;
+        }
+        let breaker = self
+            .breakers
+            .entry(key.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(key, self.config.clone())));
+        Some(breaker.clone())
+    }
+
+    /// Whether the breaker of `key`, if there is one yet, rejects calls now.
+    pub fn is_rejecting(&self, key: &str) -> bool {
+        self.breakers
+            .get(key)
+            .is_some_and(|breaker| breaker.is_rejecting())
+    }
+
+    pub fn snapshots(&self) -> Vec<CircuitSnapshot> {
+        self.breakers
+            .iter()
+            .map(|entry| entry.value().snapshot())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> Arc<CircuitBreaker> {
+        Arc::new(CircuitBreaker::new(
+            "upstream",
+            CircuitBreakerConfig {
+                window_size: 4,
+                min_calls: 4,
+                open_duration_ms: 0,
+                half_open_calls: 1,
+                ..Default::default()
+            },
+        ))
+    }
+
+    fn trip(breaker: &Arc<CircuitBreaker>) {
+        for _ in 0..4 {
+            breaker.try_acquire().unwrap().record(true);
+        }
+        assert_eq!(breaker.snapshot().state, CircuitState::Open);
+    }
+
+    #[test]
+    fn failures_open_the_circuit() {
+        let breaker = breaker();
+        breaker.try_acquire().unwrap().record(false);
+        assert_eq!(breaker.snapshot().state, CircuitState::Closed);
+        trip(&breaker);
+    }
+
+    #[test]
+    fn a_successful_probe_closes_the_circuit() {
+        let breaker = breaker();
+        trip(&breaker);
+        let probe = breaker.try_acquire().unwrap();
+        assert_eq!(breaker.snapshot().state, CircuitState::HalfOpen);
+        assert!(breaker.try_acquire().is_none(), "only one probe slot");
+        probe.record(false);
+        assert_eq!(breaker.snapshot().state, CircuitState::Closed);
+    }
+
+    #[test]
+    fn a_dropped_probe_only_frees_its_slot() {
+        let breaker = breaker();
+        trip(&breaker);
+        drop(breaker.try_acquire().unwrap());
+        // still half-open, with the slot free again
+        assert_eq!(breaker.snapshot().state, CircuitState::HalfOpen);
+        let probe = breaker.try_acquire().expect("probing again");
+        probe.record(false);
+        assert_eq!(breaker.snapshot().state, CircuitState::Closed);
+    }
+
+    #[test]
+    fn a_dropped_call_is_not_counted() {
+        let breaker = breaker();
+        breaker.try_acquire().unwrap().record(false);
+        drop(breaker.try_acquire().unwrap());
+        let snapshot = breaker.snapshot();
+        assert_eq!((snapshot.calls, snapshot.failure_rate), (1, 0.0));
+    }
+
+    #[test]
+    fn a_stale_probe_does_not_free_a_newer_slot() {
+        let breaker = breaker();
+        trip(&breaker);
+        let stale = breaker.try_acquire().unwrap();
+        breaker.inner.lock().unwrap().state = CircuitState::Open;
+        breaker.inner.lock().unwrap().opened_at = Some(Instant::now());
+        let probe = breaker.try_acquire().unwrap();
+        drop(stale);
+        assert!(breaker.try_acquire().is_none(), "the new probe keeps its slot");
+        probe.record(false);
+        assert_eq!(breaker.snapshot().state, CircuitState::Closed);
+    }
+}
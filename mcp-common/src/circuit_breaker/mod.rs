@@ -0,0 +1 @@
+pub mod circuit_breaker;
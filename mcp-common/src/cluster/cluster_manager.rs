@@ -0,0 +1,400 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    circuit_breaker::circuit_breaker::get_circuit_breakers,
+    http_client::model::{HttpCallPolicy, HttpRequestOptions},
+    provider::global_provider::get_http_client,
+    recording::recorder::{get_recorder, RecordingMode},
+    xds::cds::{HealthCheck, LbPolicy, CDS},
+};
+
+static CLUSTER_MANAGER: Lazy<ClusterManager> = Lazy::new(ClusterManager::new);
+
+/// Returns the global cluster manager.
+pub fn get_cluster_manager() -> &'static ClusterManager {
+    &CLUSTER_MANAGER
+}
+
+/// Live state of one endpoint of a cluster.
+struct Endpoint {
+    url: String,
+    weight: u32,
+    healthy: AtomicBool,
+    in_flight: AtomicUsize,
+    // probes in a row that disagree with `healthy`
+    flips: AtomicU32,
+}
+
+/// A loaded cluster: its endpoints with their health and load.
+pub struct Cluster {
+    cds: CDS,
+    endpoints: Vec<Endpoint>,
+    // round-robin position
+    next: AtomicUsize,
+}
+
+/// The endpoint a call goes to, counted as in flight until dropped.
+pub struct ClusterPick {
+    cluster: Arc<Cluster>,
+    index: usize,
+}
+
+impl ClusterPick {
+    pub fn url(&self) -> &str {
+        &self.cluster.endpoints[self.index].url
+    }
+}
+
+impl Drop for ClusterPick {
+    fn drop(&mut self) {
+        self.cluster.endpoints[self.index]
+            .in_flight
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time view of one endpoint, returned by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointSnapshot {
+    pub url: String,
+    pub weight: u32,
+    pub healthy: bool,
+    pub in_flight: usize,
+}
+
+/// Point-in-time view of one cluster, returned by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterSnapshot {
+    pub name: String,
+    pub lb_policy: LbPolicy,
+    pub endpoints: Vec<EndpointSnapshot>,
+}
+
+impl Cluster {
+    fn new(cds: CDS) -> Self {
+        let endpoints = cds
+            .endpoints
+            .iter()
+            .map(|endpoint| Endpoint {
+                url: endpoint.url.trim_end_matches('/').to_string(),
+                weight: endpoint.weight,
+                healthy: AtomicBool::new(true),
+                in_flight: AtomicUsize::new(0),
+                flips: AtomicU32::new(0),
+            })
+            .collect();
+        Self {
+            cds,
+            endpoints,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Picks the endpoint for a call with `args`, among the healthy ones
+    /// whose circuit is not open.
+    fn pick(self: &Arc<Self>, args: &HashMap<String, Value>) -> Result<ClusterPick> {
+        let all = (0..self.endpoints.len()).collect::<Vec<_>>();
+        let healthy = all
+            .iter()
+            .copied()
+            .filter(|&i| self.endpoints[i].healthy.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        // the breakers are keyed on the endpoint url, the domain of the call
+        let available = match get_circuit_breakers() {
+            Ok(breakers) => healthy
+                .iter()
+                .copied()
+                .filter(|&i| !breakers.is_rejecting(&self.endpoints[i].url))
+                .collect(),
+            Err(_) => healthy.clone(),
+        };
+        // with every endpoint down, trying them beats failing every call
+        let candidates = [available, healthy, all]
+            .into_iter()
+            .find(|candidates| !candidates.is_empty())
+            .unwrap_or_default();
+        let index = match &self.cds.lb_policy {
+            LbPolicy::RoundRobin => self.round_robin(&candidates),
+            LbPolicy::LeastRequests => self.least_requests(&candidates),
+            LbPolicy::ConsistentHash { argument } => match args.get(argument) {
+                Some(value) => self.consistent_hash(&candidates, value),
+                None => self.round_robin(&candidates),
+            },
+        }
+        .ok_or_else(|| anyhow!("Cluster `{}` has no endpoint to call", self.cds.name))?;
+        self.endpoints[index].in_flight.fetch_add(1, Ordering::Relaxed);
+        Ok(ClusterPick {
+            cluster: self.clone(),
+            index,
+        })
+    }
+
+    fn round_robin(&self, candidates: &[usize]) -> Option<usize> {
+        let total = candidates
+            .iter()
+            .map(|&i| self.endpoints[i].weight as usize)
+            .sum::<usize>();
+        if total == 0 {
+            return None;
+        }
+        let mut slot = self.next.fetch_add(1, Ordering::Relaxed) % total;
+        for &i in candidates {
+            let weight = self.endpoints[i].weight as usize;
+            if slot < weight {
+                return Some(i);
+            }
+            slot -= weight;
+        }
+        None
+    }
+
+    fn least_requests(&self, candidates: &[usize]) -> Option<usize> {
+        // fewest in flight per unit of weight, compared without division
+        let load = |i: usize| self.endpoints[i].in_flight.load(Ordering::Relaxed) as u64;
+        let weight = |i: usize| self.endpoints[i].weight as u64;
+        candidates
+            .iter()
+            .copied()
+            .min_by(|&a, &b| (load(a) * weight(b)).cmp(&(load(b) * weight(a))))
+    }
+
+    /// Weighted rendezvous hashing: a key keeps its endpoint as long as that
+    /// endpoint stays in rotation, and only the keys of a removed endpoint
+    /// move.
+    fn consistent_hash(&self, candidates: &[usize], value: &Value) -> Option<usize> {
+        let key = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let score = |i: usize| {
+            let endpoint = &self.endpoints[i];
+            let mut hasher = DefaultHasher::new();
+            (&key, &endpoint.url).hash(&mut hasher);
+            // a uniform draw in (0, 1)
+            let draw = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+            let draw = draw.max(f64::MIN_POSITIVE);
+            endpoint.weight as f64 / -draw.ln()
+        };
+        candidates
+            .iter()
+            .copied()
+            .max_by(|&a, &b| score(a).total_cmp(&score(b)))
+    }
+
+    /// Records a probe result, flipping the endpoint's health once enough
+    /// probes in a row disagree with it.
+    fn record_probe(&self, index: usize, passed: bool, health_check: &HealthCheck) {
+        let endpoint = &self.endpoints[index];
+        let healthy = endpoint.healthy.load(Ordering::Relaxed);
+        if passed == healthy {
+            endpoint.flips.store(0, Ordering::Relaxed);
+            return;
+        }
+        let threshold = match healthy {
+            true => health_check.unhealthy_threshold(),
+            false => health_check.healthy_threshold(),
+        };
+        if endpoint.flips.fetch_add(1, Ordering::Relaxed) + 1 >= threshold {
+            endpoint.flips.store(0, Ordering::Relaxed);
+            endpoint.healthy.store(passed, Ordering::Relaxed);
+            match passed {
+                true => info!("Cluster `{}` endpoint healthy: {}", self.cds.name, endpoint.url),
+                false => warn!("Cluster `{}` endpoint unhealthy: {}", self.cds.name, endpoint.url),
+            }
+        }
+    }
+
+    fn snapshot(&self) -> ClusterSnapshot {
+        ClusterSnapshot {
+            name: self.cds.name.clone(),
+            lb_policy: self.cds.lb_policy.clone(),
+            endpoints: self
+                .endpoints
+                .iter()
+                .map(|endpoint| EndpointSnapshot {
+                    url: endpoint.url.clone(),
+                    weight: endpoint.weight,
+                    healthy: endpoint.healthy.load(Ordering::Relaxed),
+                    in_flight: endpoint.in_flight.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Loaded clusters by name, with their health checks.
+///
+/// A changed CDS replaces the cluster as a whole: endpoints start healthy
+/// again and calls already running finish on the endpoint they were given.
+/// Health checks run on a task that ends when its cluster is replaced or
+/// removed.
+pub struct ClusterManager {
+    clusters: DashMap<String, Arc<Cluster>>,
+}
+
+impl ClusterManager {
+    fn new() -> Self {
+        Self {
+            clusters: DashMap::new(),
+        }
+    }
+
+    pub fn upsert(&self, cds: CDS) {
+        let name = cds.name.clone();
+        let cluster = Arc::new(Cluster::new(cds));
+        // probes are not recorded, and nothing is sent in replay
+        let replaying =
+            get_recorder().is_ok_and(|recorder| recorder.mode() == RecordingMode::Replay);
+        if let Some(health_check) = cluster.cds.health_check.clone().filter(|_| !replaying) {
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => {
+                    runtime.spawn(run_health_checks(Arc::downgrade(&cluster), health_check));
+                }
+                Err(_) => warn!("No runtime for the health checks of cluster `{}`", name),
+            }
+        }
+        info!(
+            "Loaded cluster `{}` with {} endpoints",
+            name,
+            cluster.endpoints.len()
+        );
+        self.clusters.insert(name, cluster);
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.clusters.remove(name);
+    }
+
+    /// Picks an endpoint of cluster `name` for a call with `args`.
+    pub fn pick(&self, name: &str, args: &HashMap<String, Value>) -> Result<ClusterPick> {
+        let cluster = self
+            .clusters
+            .get(name)
+            .map(|c| c.value().clone())
+            .ok_or_else(|| anyhow!("Unknown cluster `{}`", name))?;
+        cluster.pick(args)
+    }
+
+    /// The id of the CDS cluster `name` was loaded from.
+    pub fn cluster_id(&self, name: &str) -> Option<String> {
+        self.clusters.get(name).map(|c| c.cds.id.clone())
+    }
+
+    pub fn snapshots(&self) -> Vec<ClusterSnapshot> {
+        self.clusters
+            .iter()
+            .map(|cluster| cluster.snapshot())
+            .collect()
+    }
+}
+
+/// Probes every endpoint of a cluster each interval, until the cluster is
+/// dropped.
+async fn run_health_checks(cluster: Weak<Cluster>, health_check: HealthCheck) {
+    let mut interval = tokio::time::interval(Duration::from_millis(health_check.interval_ms()));
+    loop {
+        interval.tick().await;
+        let Some(cluster) = cluster.upgrade() else {
+            return;
+        };
+        let mut probes = tokio::task::JoinSet::new();
+        for (i, endpoint) in cluster.endpoints.iter().enumerate() {
+            let url = format!("{}{}", endpoint.url, health_check.path);
+            let health_check = health_check.clone();
+            probes.spawn(async move { (i, probe(&url, &health_check).await) });
+        }
+        while let Some(Ok((i, passed))) = probes.join_next().await {
+            cluster.record_probe(i, passed, &health_check);
+        }
+    }
+}
+
+async fn probe(url: &str, health_check: &HealthCheck) -> bool {
+    let Ok(http_client) = get_http_client() else {
+        return false;
+    };
+    let options = HttpRequestOptions::<Value> {
+        method: "GET".to_string(),
+        tls_profile: health_check.tls_profile.clone(),
+        call_policy: Some(HttpCallPolicy {
+            timeout_ms: Some(health_check.timeout_ms()),
+            max_retries: Some(0),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    // error statuses, timeouts and refused connections all fail the probe
+    http_client
+        .request_uri::<Value, String>(url, options)
+        .await
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::init_test_config, xds::cds::ClusterEndpoint};
+
+    fn cluster(name: &str, endpoints: &[(&str, u32)]) -> Arc<Cluster> {
+        Arc::new(Cluster::new(CDS {
+            id: name.to_string(),
+            name: name.to_string(),
+            endpoints: endpoints
+                .iter()
+                .map(|&(url, weight)| ClusterEndpoint {
+                    url: url.to_string(),
+                    weight,
+                })
+                .collect(),
+            lb_policy: LbPolicy::RoundRobin,
+            health_check: None,
+        }))
+    }
+
+    #[test]
+    fn clusters_without_a_usable_endpoint_fail_the_pick() {
+        init_test_config();
+        let args = HashMap::new();
+        assert!(cluster("empty", &[]).pick(&args).is_err());
+        assert!(cluster("weightless", &[("http://a.test", 0)])
+            .pick(&args)
+            .is_err());
+    }
+
+    #[test]
+    fn endpoints_with_an_open_circuit_are_skipped() {
+        init_test_config();
+        let cluster = cluster(
+            "breaker",
+            &[("http://open.cluster.test", 1), ("http://closed.cluster.test", 1)],
+        );
+        let breaker = get_circuit_breakers()
+            .unwrap()
+            .get("http://open.cluster.test")
+            .unwrap();
+        while !breaker.is_rejecting() {
+            breaker.try_acquire().unwrap().record(true);
+        }
+
+        let args = HashMap::new();
+        for _ in 0..4 {
+            let pick = cluster.pick(&args).unwrap();
+            assert_eq!(pick.url(), "http://closed.cluster.test");
+        }
+    }
+}
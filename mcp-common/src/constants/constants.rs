@@ -9,4 +9,87 @@ pub mod mcp_protocol_consts {
 pub mod mcp_cache_consts {
     pub const ETCD_TDS_PREFIX: &str = "/dynmcp/tds/";
     pub const ETCD_IDS_PREFIX: &str = "/dynmcp/ids/";
-}
\ No newline at end of file
+    pub const ETCD_MDS_PREFIX: &str = "/dynmcp/mds/";
+    pub const ETCD_PDS_PREFIX: &str = "/dynmcp/pds/";
+    pub const ETCD_CDS_PREFIX: &str = "/dynmcp/cds/";
+    // cluster-wide locks, each a key attached to the holder's lease
+    pub const ETCD_LOCK_PREFIX: &str = "/dynmcp/lock/";
+    // id (and cache key) prefix of the built-in TDS entries of native tools
+    pub const BUILTIN_TDS_PREFIX: &str = "builtin/";
+}
+
+pub mod http_client_consts {
+    pub const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5_000;
+    pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+    // retries are opt-in: POSTs to non-idempotent upstreams must not repeat by default
+    pub const DEFAULT_MAX_RETRIES: u32 = 0;
+    // all attempts and the backoff between them
+    pub const DEFAULT_TOTAL_TIMEOUT_MS: u64 = 60_000;
+    pub const DEFAULT_BACKOFF_BASE_MS: u64 = 100;
+    pub const DEFAULT_BACKOFF_MAX_MS: u64 = 5_000;
+    pub const DEFAULT_RETRYABLE_STATUS: &[u16] = &[502, 503, 504];
+    pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+    pub const DEFAULT_MAX_RESULT_BYTES: usize = 100 * 1024;
+    pub const DEFAULT_MAX_PAGES: usize = 10;
+    pub const DEFAULT_MAX_PAGE_ITEMS: usize = 1_000;
+}
+pub mod sql_consts {
+    pub const DEFAULT_SQL_MAX_ROWS: usize = 1_000;
+    pub const DEFAULT_SQL_TIMEOUT_MS: u64 = 10_000;
+}
+pub mod script_consts {
+    pub const DEFAULT_SCRIPT_MAX_OPERATIONS: u64 = 100_000;
+    pub const DEFAULT_SCRIPT_TIMEOUT_MS: u64 = 100;
+    pub const MAX_SCRIPT_OPERATIONS: u64 = 10_000_000;
+    pub const MAX_SCRIPT_TIMEOUT_MS: u64 = 1_000;
+    pub const SCRIPT_MAX_CALL_LEVELS: usize = 32;
+    pub const SCRIPT_MAX_STRING_SIZE: usize = 10 * 1024 * 1024;
+    pub const SCRIPT_MAX_COLLECTION_SIZE: usize = 100_000;
+}
+pub mod wasm_consts {
+    pub const DEFAULT_WASM_FUEL: u64 = 1_000_000_000;
+    pub const DEFAULT_WASM_MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+    pub const DEFAULT_WASM_TIMEOUT_MS: u64 = 10_000;
+    pub const MAX_WASM_TIMEOUT_MS: u64 = 60_000;
+    pub const MAX_WASM_MODULE_BYTES: usize = 1024 * 1024;
+    // How often the engine epoch advances, the granularity of call timeouts
+    pub const WASM_EPOCH_TICK_MS: u64 = 10;
+    // Fuel a plugin burns between yields to the async runtime
+    pub const WASM_FUEL_YIELD_INTERVAL: u64 = 100_000;
+}
+pub mod mock_consts {
+    pub const MAX_MOCK_LATENCY_MS: u64 = 60_000;
+}
+pub mod fan_out_consts {
+    pub const DEFAULT_BRANCH_TIMEOUT_MS: u64 = 10_000;
+    pub const MAX_FAN_OUT_MEMBERS: usize = 32;
+}
+pub mod cluster_consts {
+    pub const DEFAULT_HEALTH_CHECK_INTERVAL_MS: u64 = 10_000;
+    pub const DEFAULT_HEALTH_CHECK_TIMEOUT_MS: u64 = 2_000;
+    pub const DEFAULT_UNHEALTHY_THRESHOLD: u32 = 3;
+    pub const DEFAULT_HEALTHY_THRESHOLD: u32 = 2;
+}
+pub mod egress_consts {
+    // Loopback, private, link-local (cloud metadata included), CGNAT,
+    // benchmarking, multicast and reserved ranges
+    pub const PRIVATE_RANGES: &[&str] = &[
+        "0.0.0.0/8",
+        "10.0.0.0/8",
+        "100.64.0.0/10",
+        "127.0.0.0/8",
+        "169.254.0.0/16",
+        "172.16.0.0/12",
+        "192.0.0.0/24",
+        "192.168.0.0/16",
+        "198.18.0.0/15",
+        "224.0.0.0/4",
+        "240.0.0.0/4",
+        "::/128",
+        "::1/128",
+        "fc00::/7",
+        "fe80::/10",
+        "ff00::/8",
+    ];
+    pub const MAX_REDIRECTS: usize = 10;
+}
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use deadpool::managed::{Manager, Metrics, Pool, RecycleResult};
-use etcd_client::{Client, ConnectOptions, DeleteOptions, GetOptions, PutOptions, WatchOptions};
+use etcd_client::{
+    Client, Compare, CompareOp, ConnectOptions, DeleteOptions, GetOptions, PutOptions, Txn,
+    TxnOp, TxnOpResponse, WatchOptions,
+};
 use std::time::Duration;
 use thiserror::Error;
 
@@ -67,9 +70,9 @@ impl EtcdClientProvider {
     /// Create a new EtcdClientProvider with connection pool.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let etcd = EtcdClientProvider::new(vec!["http://localhost:2379".into()], "".into(), "".into()).await?;
-
+    /// ```
     pub async fn new(endpoints: Vec<String>, username: String, password: String) -> Result<Self> {
         let mgr = EtcdClientManager {
             endpoints,
@@ -83,7 +86,7 @@ impl EtcdClientProvider {
     /// Store a key-value pair in etcd.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// etcd.put("/foo", "bar").await?;
     /// ```
     pub async fn put(&self, key: &str, value: &str) -> Result<()> {
@@ -95,7 +98,7 @@ impl EtcdClientProvider {
     /// Retrieve a value by key.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let val = etcd.get("/foo").await?;
     /// ```
     pub async fn get(&self, key: &str) -> Result<Option<String>> {
@@ -114,7 +117,7 @@ impl EtcdClientProvider {
     /// Delete by key.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let val = etcd.delete("/foo").await?;
     /// ```
     pub async fn delete(&self, key: &str) -> Result<bool> {
@@ -123,10 +126,59 @@ impl EtcdClientProvider {
         Ok(resp.deleted() > 0)
     }
 
+    /// Grant a lease of `ttl` seconds and return its id.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let lease = etcd.grant_lease(10).await?;
+    /// ```
+    pub async fn grant_lease(&self, ttl: i64) -> Result<i64> {
+        let mut client = self.pool.get().await?;
+        Ok(client.lease_grant(ttl, None).await?.id())
+    }
+
+    /// Renew a lease once; returns false when it has already expired.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let alive = etcd.keep_alive(lease).await?;
+    /// ```
+    pub async fn keep_alive(&self, lease: i64) -> Result<bool> {
+        let mut client = self.pool.get().await?;
+        let (mut keeper, mut stream) = client.lease_keep_alive(lease).await?;
+        keeper.keep_alive().await?;
+        Ok(stream.message().await?.is_some_and(|resp| resp.ttl() > 0))
+    }
+
+    /// Create `key` attached to `lease` unless it exists, in one transaction,
+    /// and return the lease the key is attached to.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let held = etcd.create_with_lease("/lock/foo", "", lease).await? == lease;
+    /// ```
+    pub async fn create_with_lease(&self, key: &str, value: &str, lease: i64) -> Result<i64> {
+        let mut client = self.pool.get().await?;
+        let txn = Txn::new()
+            .when([Compare::create_revision(key, CompareOp::Equal, 0)])
+            .and_then([TxnOp::put(key, value, Some(PutOptions::new().with_lease(lease)))])
+            .or_else([TxnOp::get(key, None)]);
+        let resp = client.txn(txn).await?;
+        if resp.succeeded() {
+            return Ok(lease);
+        }
+        let holder = resp.op_responses().into_iter().find_map(|op| match op {
+            TxnOpResponse::Get(get) => get.kvs().first().map(|kv| kv.lease()),
+            _ => None,
+        });
+        // deleted between the compare and the get; taken on the next try
+        Ok(holder.unwrap_or_default())
+    }
+
     /// Get all key-value pairs with a specific prefix.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let pairs = etcd.get_prefix("/foo/").await?;
     /// ```
     pub async fn get_prefix(&self, prefix: &str) -> Result<Vec<(String, String)>> {
@@ -149,7 +201,7 @@ impl EtcdClientProvider {
     /// Start watching a key for changes and call a callback function on updates.
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// etcd.watch("/foo", |event| {
     ///     println!("Watch event: {:?}", event);
     /// }).await?;
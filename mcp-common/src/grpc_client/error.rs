@@ -0,0 +1,43 @@
+use thiserror::Error;
+use tonic::{Code, Status};
+
+use crate::http_client::egress::EgressDenied;
+
+/// Upstream gRPC failures the tool layer reports back as `isError` results.
+#[derive(Debug, Error)]
+pub enum GrpcCallError {
+    #[error("gRPC call failed: {code:?}: {message}")]
+    Status { code: Code, message: String },
+
+    #[error("Invalid gRPC endpoint `{0}`: {1}")]
+    Endpoint(String, String),
+
+    #[error(transparent)]
+    EgressDenied(#[from] EgressDenied),
+}
+
+impl GrpcCallError {
+    /// Whether the failure points at an unhealthy upstream rather than a bad request.
+    pub fn is_upstream_failure(&self) -> bool {
+        match self {
+            GrpcCallError::Status { code, .. } => matches!(
+                code,
+                Code::Unavailable
+                    | Code::DeadlineExceeded
+                    | Code::Internal
+                    | Code::Unknown
+                    | Code::ResourceExhausted
+            ),
+            GrpcCallError::Endpoint(..) | GrpcCallError::EgressDenied(_) => false,
+        }
+    }
+}
+
+impl From<Status> for GrpcCallError {
+    fn from(status: Status) -> Self {
+        GrpcCallError::Status {
+            code: status.code(),
+            message: status.message().to_string(),
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use prost_reflect::{DynamicMessage, MethodDescriptor, Value};
+use tonic::{
+    client::Grpc,
+    codegen::http::uri::PathAndQuery,
+    metadata::{MetadataKey, MetadataValue},
+    transport::Channel,
+    Code, Request, Status,
+};
+use tracing::warn;
+
+use crate::{
+    grpc_client::{dynamic_codec::DynamicCodec, error::GrpcCallError},
+    http_client::{
+        egress::{EgressConnector, EgressDenied, EgressPolicy},
+        model::HttpCallPolicy,
+    },
+    provider::global_provider::get_http_client,
+};
+
+static GRPC_CLIENT: Lazy<GrpcClientProvider> = Lazy::new(GrpcClientProvider::new);
+
+/// Returns the global gRPC client.
+pub fn get_grpc_client() -> &'static GrpcClientProvider {
+    &GRPC_CLIENT
+}
+
+/// Per-call settings of a unary gRPC request.
+#[derive(Debug, Clone)]
+pub struct GrpcCallOptions {
+    pub metadata: HashMap<String, String>,
+    // Resolved call policy: connect timeout, per-attempt deadline (sent to the
+    // server as `grpc-timeout`), total deadline, retries and backoff
+    pub call_policy: HttpCallPolicy,
+    pub max_response_bytes: usize,
+}
+
+/// Calls gRPC methods described at runtime, with one lazily connected channel
+/// per endpoint and connect timeout.
+///
+/// Endpoints are held to the `[http_client.egress]` policy like HTTP
+/// upstreams: checked before every call, and on the addresses connected to.
+/// The egress connector speaks plaintext HTTP/2 only, so `https` endpoints
+/// are refused while the policy is enabled.
+pub struct GrpcClientProvider {
+    channels: DashMap<String, Channel>,
+    // the HTTP client's policy, taken on first use
+    egress: OnceCell<Arc<EgressPolicy>>,
+}
+
+impl GrpcClientProvider {
+    fn new() -> Self {
+        Self {
+            channels: DashMap::new(),
+            egress: OnceCell::new(),
+        }
+    }
+
+    fn egress(&self) -> Result<&Arc<EgressPolicy>, GrpcCallError> {
+        self.egress
+            .get_or_try_init(|| get_http_client().map(|client| client.egress_policy()))
+            .map_err(|e| Status::internal(e.to_string()).into())
+    }
+
+    /// Checks an endpoint against the egress policy, e.g. when a TDS is saved.
+    pub fn check_endpoint(&self, endpoint: &str) -> Result<(), GrpcCallError> {
+        let egress = self.egress()?;
+        if egress.is_enabled() && endpoint.starts_with("https://") {
+            return Err(GrpcCallError::Endpoint(
+                endpoint.to_string(),
+                "https is not supported while [http_client.egress] is enabled".to_string(),
+            ));
+        }
+        Ok(egress.check_url(endpoint)?)
+    }
+
+    fn channel(&self, endpoint: &str, connect_timeout: Duration) -> Result<Channel, GrpcCallError> {
+        let key = format!("{}\u{0}{}", endpoint, connect_timeout.as_millis());
+        if let Some(channel) = self.channels.get(&key) {
+            return Ok(channel.clone());
+        }
+        let builder = Channel::from_shared(endpoint.to_string())
+            .map_err(|e| GrpcCallError::Endpoint(endpoint.to_string(), e.to_string()))?
+            .connect_timeout(connect_timeout);
+        let egress = self.egress()?;
+        let channel = match egress.is_enabled() {
+            true => builder.connect_with_connector_lazy(EgressConnector::new(egress.clone())),
+            false => builder.connect_lazy(),
+        };
+        self.channels.insert(key, channel.clone());
+        Ok(channel)
+    }
+
+    /// Sends one unary request and returns the decoded response message.
+    ///
+    /// Like HTTP calls, failed attempts are retried up to
+    /// `call_policy.max_retries` times when the status points at a transient
+    /// upstream failure, and only for methods marked idempotent
+    /// (`option idempotency_level`) unless the policy opts in.
+    pub async fn unary(
+        &self,
+        endpoint: &str,
+        method: &MethodDescriptor,
+        message: DynamicMessage,
+        options: &GrpcCallOptions,
+    ) -> Result<DynamicMessage, GrpcCallError> {
+        self.check_endpoint(endpoint)?;
+        let policy = &options.call_policy;
+        let max_retries = match is_idempotent(method) || policy.retry_non_idempotent() {
+            true => policy.max_retries(),
+            false => 0,
+        };
+
+        // the deadline covers every attempt and the backoff between them
+        let attempts = async {
+            let mut attempt = 0;
+            loop {
+                let result = self.attempt(endpoint, method, message.clone(), options).await;
+                let retryable = match &result {
+                    Err(GrpcCallError::Status { code, .. }) => is_retryable(*code),
+                    _ => false,
+                };
+                if !retryable || attempt >= max_retries {
+                    return result;
+                }
+                attempt += 1;
+                let delay = policy.backoff(attempt);
+                warn!(
+                    "Retrying gRPC {} {} (attempt {}/{}) in {:?}",
+                    endpoint,
+                    method.full_name(),
+                    attempt,
+                    max_retries,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+        };
+        let total_timeout = policy.total_timeout();
+        match tokio::time::timeout(total_timeout, attempts).await {
+            Ok(result) => result,
+            Err(_) => Err(Status::deadline_exceeded(format!(
+                "No response within {} ms",
+                total_timeout.as_millis()
+            ))
+            .into()),
+        }
+    }
+
+    async fn attempt(
+        &self,
+        endpoint: &str,
+        method: &MethodDescriptor,
+        message: DynamicMessage,
+        options: &GrpcCallOptions,
+    ) -> Result<DynamicMessage, GrpcCallError> {
+        let policy = &options.call_policy;
+        let channel = self.channel(endpoint, policy.connect_timeout())?;
+        let mut grpc = Grpc::new(channel).max_decoding_message_size(options.max_response_bytes);
+
+        let timeout = policy.timeout();
+        let mut request = Request::new(message);
+        request.set_timeout(timeout);
+        for (key, value) in &options.metadata {
+            let key = MetadataKey::from_bytes(key.as_bytes())
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            let value = MetadataValue::try_from(value.as_str())
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            request.metadata_mut().insert(key, value);
+        }
+
+        let path = PathAndQuery::try_from(format!(
+            "/{}/{}",
+            method.parent_service().full_name(),
+            method.name()
+        ))
+        .map_err(|e| Status::internal(e.to_string()))?;
+        let codec = DynamicCodec::new(method.output());
+
+        // a connect refused by the egress connector surfaces as a transport
+        // failure; it is reported as the denial it is
+        let call = async {
+            grpc.ready().await.map_err(|e| match EgressDenied::find(&e) {
+                Some(denied) => GrpcCallError::from(denied),
+                None => Status::unavailable(format!("Service not ready: {}", e)).into(),
+            })?;
+            grpc.unary(request, path, codec)
+                .await
+                .map_err(|status| match EgressDenied::find(&status) {
+                    Some(denied) => denied.into(),
+                    None => status.into(),
+                })
+        };
+        match tokio::time::timeout(timeout, call).await {
+            Ok(Ok(response)) => Ok(response.into_inner()),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(Status::deadline_exceeded(format!(
+                "No response within {} ms",
+                timeout.as_millis()
+            ))
+            .into()),
+        }
+    }
+}
+
+/// Whether the method declares `NO_SIDE_EFFECTS` or `IDEMPOTENT` in its
+/// `idempotency_level` option.
+fn is_idempotent(method: &MethodDescriptor) -> bool {
+    matches!(
+        method
+            .options()
+            .get_field_by_name("idempotency_level")
+            .as_deref(),
+        Some(Value::EnumNumber(1 | 2))
+    )
+}
+
+/// The gRPC counterparts of the HTTP statuses retried by default
+/// (429, 502, 503, 504) and of timeouts.
+fn is_retryable(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unavailable | Code::ResourceExhausted | Code::DeadlineExceeded
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll},
+    };
+
+    use prost_reflect::DescriptorPool;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{
+        body::BoxBody,
+        codegen::{http, BoxFuture, Service},
+        server::{Grpc as GrpcServer, NamedService, UnaryService},
+        transport::Server,
+        Response,
+    };
+
+    use super::*;
+    use crate::{
+        config::config::EgressConfig,
+        test_util::{echo_descriptor_set, init_test_config},
+    };
+
+    /// `test.Echo`: answers every method with the request message, after
+    /// failing the first `failures` calls with UNAVAILABLE.
+    #[derive(Clone)]
+    struct Echo {
+        pool: DescriptorPool,
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl NamedService for Echo {
+        const NAME: &'static str = "test.Echo";
+    }
+
+    impl Service<http::Request<BoxBody>> for Echo {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+            let echo = self.clone();
+            Box::pin(async move {
+                let input = echo.pool.get_message_by_name("test.Msg").unwrap();
+                Ok(GrpcServer::new(DynamicCodec::new(input)).unary(EchoCall(echo), req).await)
+            })
+        }
+    }
+
+    struct EchoCall(Echo);
+
+    impl UnaryService<DynamicMessage> for EchoCall {
+        type Response = DynamicMessage;
+        type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+        fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+            let echo = self.0.clone();
+            Box::pin(async move {
+                if echo.calls.fetch_add(1, Ordering::SeqCst) < echo.failures {
+                    return Err(Status::unavailable("warming up"));
+                }
+                Ok(Response::new(request.into_inner()))
+            })
+        }
+    }
+
+    async fn serve(failures: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let echo = Echo {
+            pool: pool(),
+            failures,
+            calls: calls.clone(),
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(echo)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        (addr, calls)
+    }
+
+    fn pool() -> DescriptorPool {
+        DescriptorPool::decode(echo_descriptor_set().as_slice()).unwrap()
+    }
+
+    fn method(name: &str) -> MethodDescriptor {
+        pool()
+            .get_service_by_name("test.Echo")
+            .unwrap()
+            .methods()
+            .find(|m| m.name() == name)
+            .unwrap()
+    }
+
+    fn message(method: &MethodDescriptor, text: &str) -> DynamicMessage {
+        let mut message = DynamicMessage::new(method.input());
+        message.set_field_by_name("text", Value::String(text.to_string()));
+        message
+    }
+
+    fn options(max_retries: u32) -> GrpcCallOptions {
+        GrpcCallOptions {
+            metadata: HashMap::from([("x-tenant".to_string(), "acme".to_string())]),
+            call_policy: HttpCallPolicy {
+                max_retries: Some(max_retries),
+                backoff_base_ms: Some(1),
+                backoff_max_ms: Some(1),
+                ..Default::default()
+            },
+            max_response_bytes: 1024,
+        }
+    }
+
+    #[tokio::test]
+    async fn unary_calls_round_trip_dynamic_messages() {
+        init_test_config();
+        let (addr, _) = serve(0).await;
+        let method = method("Get");
+        let response = GrpcClientProvider::new()
+            .unary(&format!("http://{}", addr), &method, message(&method, "hi"), &options(0))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.get_field_by_name("text").unwrap().as_str(),
+            Some("hi")
+        );
+    }
+
+    #[tokio::test]
+    async fn idempotent_methods_are_retried_up_to_max_retries() {
+        init_test_config();
+        let (addr, calls) = serve(2).await;
+        let method = method("Get");
+        let client = GrpcClientProvider::new();
+        let endpoint = format!("http://{}", addr);
+
+        let result = client
+            .unary(&endpoint, &method, message(&method, "hi"), &options(2))
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // retries are opt-in
+        calls.store(0, Ordering::SeqCst);
+        let result = client
+            .unary(&endpoint, &method, message(&method, "hi"), &options(0))
+            .await;
+        assert!(matches!(
+            result,
+            Err(GrpcCallError::Status { code: Code::Unavailable, .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn other_methods_are_not_retried_unless_the_policy_opts_in() {
+        init_test_config();
+        let (addr, calls) = serve(1).await;
+        let method = method("Create");
+        let client = GrpcClientProvider::new();
+        let endpoint = format!("http://{}", addr);
+
+        let result = client
+            .unary(&endpoint, &method, message(&method, "hi"), &options(2))
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let mut options = options(2);
+        options.call_policy.retry_non_idempotent = Some(true);
+        let result = client
+            .unary(&endpoint, &method, message(&method, "hi"), &options)
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn channels_are_kept_per_connect_timeout() {
+        init_test_config();
+        let client = GrpcClientProvider::new();
+        let endpoint = "http://127.0.0.1:1";
+        client.channel(endpoint, Duration::from_secs(1)).unwrap();
+        client.channel(endpoint, Duration::from_secs(1)).unwrap();
+        assert_eq!(client.channels.len(), 1);
+        client.channel(endpoint, Duration::from_secs(5)).unwrap();
+        assert_eq!(client.channels.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn endpoints_are_held_to_the_egress_policy() {
+        let (addr, calls) = serve(0).await;
+        let method = method("Get");
+        let client = GrpcClientProvider::new();
+        let policy = EgressPolicy::new(&EgressConfig {
+            enabled: true,
+            ..Default::default()
+        })
+        .unwrap();
+        client.egress.set(Arc::new(policy)).unwrap();
+
+        // the address in the URL, before anything is sent
+        let result = client
+            .unary(&format!("http://{}", addr), &method, message(&method, "hi"), &options(0))
+            .await;
+        assert!(matches!(result, Err(GrpcCallError::EgressDenied(_))));
+
+        // the addresses a name resolves to, when the channel connects
+        let endpoint = format!("http://localhost:{}", addr.port());
+        let result = client
+            .unary(&endpoint, &method, message(&method, "hi"), &options(0))
+            .await;
+        assert!(
+            matches!(&result, Err(GrpcCallError::EgressDenied(denied)) if denied.target == "localhost"),
+            "{:?}",
+            result
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn https_endpoints_are_refused_under_egress() {
+        let method = method("Get");
+        let client = GrpcClientProvider::new();
+        let policy = EgressPolicy::new(&EgressConfig {
+            enabled: true,
+            ..Default::default()
+        })
+        .unwrap();
+        client.egress.set(Arc::new(policy)).unwrap();
+
+        let result = client
+            .unary("https://orders.example.com", &method, message(&method, "hi"), &options(0))
+            .await;
+        assert!(
+            matches!(&result, Err(GrpcCallError::Endpoint(_, reason)) if reason.starts_with("https")),
+            "{:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod dynamic_codec;
+pub mod error;
+pub mod grpc_client_provider;
@@ -0,0 +1,457 @@
+use std::{
+    error::Error as StdError,
+    future::Future,
+    net::IpAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Result};
+use hyper_util::rt::TokioIo;
+use ipnet::IpNet;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect, Url,
+};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tonic::codegen::{http::Uri, Service};
+
+use crate::{
+    config::config::EgressConfig,
+    constants::constants::egress_consts::{MAX_REDIRECTS, PRIVATE_RANGES},
+};
+
+/// A destination the egress policy does not allow.
+#[derive(Debug, Clone, Error)]
+#[error("Egress to `{target}` denied: {reason}")]
+pub struct EgressDenied {
+    pub target: String,
+    pub reason: String,
+}
+
+impl EgressDenied {
+    fn new(target: impl ToString, reason: impl ToString) -> Self {
+        Self {
+            target: target.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// The denial behind a failed request, if the resolver, the connector
+    /// or the redirect policy refused it.
+    pub fn find(e: &(dyn StdError + 'static)) -> Option<EgressDenied> {
+        let mut source = Some(e);
+        while let Some(err) = source {
+            if let Some(denied) = err.downcast_ref::<EgressDenied>() {
+                return Some(denied.clone());
+            }
+            source = err.source();
+        }
+        None
+    }
+}
+
+/// The `[http_client.egress]` policy, ready to check destinations.
+///
+/// URLs are checked before a request is sent and on every redirect; host
+/// names are checked again on the addresses they resolve to when the
+/// connection is made, so a name cannot be re-pointed at a denied address
+/// between the check and the connect (DNS rebinding).
+#[derive(Debug, Default)]
+pub struct EgressPolicy {
+    enabled: bool,
+    // lowercase; a leading "*." matches any subdomain
+    allowed_domains: Vec<String>,
+    allowed_cidrs: Vec<IpNet>,
+    denied_cidrs: Vec<IpNet>,
+    allowed_ports: Vec<u16>,
+}
+
+impl EgressPolicy {
+    pub fn new(config: &EgressConfig) -> Result<Self> {
+        let parse = |cidrs: &mut dyn Iterator<Item = &str>| {
+            cidrs
+                .map(|cidr| {
+                    parse_cidr(cidr).ok_or_else(|| anyhow!("Invalid egress CIDR: {}", cidr))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let private = match config.deny_private {
+            true => PRIVATE_RANGES,
+            false => &[],
+        };
+        Ok(Self {
+            enabled: config.enabled,
+            allowed_domains: config
+                .allowed_domains
+                .iter()
+                .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase())
+                .collect(),
+            allowed_cidrs: parse(&mut config.allowed_cidrs.iter().map(String::as_str))?,
+            denied_cidrs: parse(
+                &mut private
+                    .iter()
+                    .copied()
+                    .chain(config.denied_cidrs.iter().map(String::as_str)),
+            )?,
+            allowed_ports: config.allowed_ports.clone(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Checks the scheme, port and host of an upstream URL. Addresses behind
+    /// a host name are checked when they are resolved.
+    pub fn check_url(&self, url: &str) -> Result<(), EgressDenied> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let parsed = Url::parse(url).map_err(|e| EgressDenied::new(url, e))?;
+        self.check(&parsed)
+    }
+
+    fn check(&self, url: &Url) -> Result<(), EgressDenied> {
+        let denied = |reason: String| Err(EgressDenied::new(url, reason));
+        if !matches!(url.scheme(), "http" | "https") {
+            return denied(format!("scheme `{}` is not http(s)", url.scheme()));
+        }
+        if !self.allowed_ports.is_empty() {
+            match url.port_or_known_default() {
+                Some(port) if self.allowed_ports.contains(&port) => {}
+                Some(port) => return denied(format!("port {} is not allowed", port)),
+                None => return denied("URL has no port".to_string()),
+            }
+        }
+        let Some(host) = url.host_str() else {
+            return denied("URL has no host".to_string());
+        };
+        // IPv6 hosts come bracketed
+        let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() else {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            if !self.allowed_domains.is_empty() && !self.allows_domain(&domain) {
+                return denied(format!("host `{}` is not in allowed_domains", domain));
+            }
+            return Ok(());
+        };
+        if !self.allowed_domains.is_empty() && !self.in_allowed_cidrs(ip) {
+            return denied(format!("address {} is not in allowed_cidrs", ip));
+        }
+        self.check_ip(ip).map_err(|e| EgressDenied::new(url, e.reason))
+    }
+
+    /// Checks an address a request would connect to.
+    pub fn check_ip(&self, ip: IpAddr) -> Result<(), EgressDenied> {
+        if !self.enabled || self.in_allowed_cidrs(ip) {
+            return Ok(());
+        }
+        let ip = ip.to_canonical();
+        if self.denied_cidrs.iter().any(|cidr| cidr.contains(&ip)) {
+            return Err(EgressDenied::new(ip, "address is in a denied range"));
+        }
+        Ok(())
+    }
+
+    fn in_allowed_cidrs(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.allowed_cidrs.iter().any(|cidr| cidr.contains(&ip))
+    }
+
+    fn allows_domain(&self, domain: &str) -> bool {
+        self.allowed_domains
+            .iter()
+            .any(|allowed| match allowed.strip_prefix("*.") {
+                Some(parent) => domain
+                    .strip_suffix(parent)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => domain == allowed,
+            })
+    }
+
+    /// A redirect policy that checks every hop like the first request.
+    pub fn redirect_policy(self: &Arc<Self>) -> redirect::Policy {
+        let policy = self.clone();
+        redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error(format!("more than {} redirects", MAX_REDIRECTS));
+            }
+            match policy.check(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(denied) => attempt.error(denied),
+            }
+        })
+    }
+}
+
+/// Parses "10.0.0.0/8", or a bare address as a single-address range.
+fn parse_cidr(cidr: &str) -> Option<IpNet> {
+    let cidr = cidr.trim();
+    cidr.parse::<IpNet>()
+        .ok()
+        .or_else(|| cidr.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+/// DNS resolver that drops the addresses the egress policy denies, so the
+/// addresses checked are the ones connected to. Wraps the resolver the client
+/// would use otherwise.
+pub struct EgressResolver {
+    inner: Arc<dyn Resolve>,
+    policy: Arc<EgressPolicy>,
+}
+
+impl EgressResolver {
+    pub fn new(inner: Arc<dyn Resolve>, policy: Arc<EgressPolicy>) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl Resolve for EgressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let resolving = self.inner.resolve(name);
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let mut denied = None;
+            let addrs = resolving
+                .await?
+                .filter(|addr| match policy.check_ip(addr.ip()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        denied.get_or_insert(e);
+                        false
+                    }
+                })
+                .collect::<Vec<_>>();
+            if addrs.is_empty() {
+                return Err(no_address(&host, denied).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Connector of gRPC channels that connects only to the addresses the
+/// egress policy allows, checked on the addresses a host name resolves to
+/// like `EgressResolver` does for HTTP.
+#[derive(Clone)]
+pub struct EgressConnector {
+    policy: Arc<EgressPolicy>,
+}
+
+impl EgressConnector {
+    pub fn new(policy: Arc<EgressPolicy>) -> Self {
+        Self { policy }
+    }
+}
+
+impl Service<Uri> for EgressConnector {
+    type Response = TokioIo<TcpStream>;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let Some(host) = uri.host() else {
+                return Err(EgressDenied::new(&uri, "URI has no host").into());
+            };
+            // IPv6 hosts come bracketed
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            });
+            let mut denied = None;
+            let mut failed = None;
+            for addr in tokio::net::lookup_host((host, port)).await? {
+                if let Err(e) = policy.check_ip(addr.ip()) {
+                    denied.get_or_insert(e);
+                    continue;
+                }
+                match TcpStream::connect(addr).await {
+                    Ok(stream) => {
+                        stream.set_nodelay(true)?;
+                        return Ok(TokioIo::new(stream));
+                    }
+                    Err(e) => failed = Some(e),
+                }
+            }
+            match failed {
+                Some(e) => Err(e.into()),
+                None => Err(no_address(host, denied).into()),
+            }
+        })
+    }
+}
+
+/// The denial of a host none of whose addresses may be connected to.
+fn no_address(host: &str, denied: Option<EgressDenied>) -> EgressDenied {
+    let reason = match denied {
+        Some(e) => format!("resolves to {}, which is in a denied range", e.target),
+        None => "resolves to no address".to_string(),
+    };
+    EgressDenied::new(host, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{response::Redirect, routing::get, Router};
+    use reqwest::Client;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        config::config::HttpClientSection,
+        http_client::{
+            error::HttpCallError, http_client_provider::HttpClientProvider,
+            model::HttpRequestOptions, tls_profile::SniResolver,
+        },
+        test_util::serve_on,
+    };
+
+    fn config(allowed_domains: &[&str], allowed_cidrs: &[&str]) -> EgressConfig {
+        EgressConfig {
+            enabled: true,
+            allowed_domains: allowed_domains.iter().map(|d| d.to_string()).collect(),
+            allowed_cidrs: allowed_cidrs.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn policy(allowed_domains: &[&str], allowed_cidrs: &[&str]) -> EgressPolicy {
+        EgressPolicy::new(&config(allowed_domains, allowed_cidrs)).unwrap()
+    }
+
+    fn provider(egress: EgressConfig) -> HttpClientProvider {
+        HttpClientProvider::new(&HttpClientSection {
+            egress,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// Serves "ok" on `addr`, counting the requests it gets.
+    async fn counting(addr: &str) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let seen = hits.clone();
+        let router = Router::new().fallback(get(move || {
+            seen.fetch_add(1, Ordering::SeqCst);
+            async { "ok" }
+        }));
+        let addr = serve_on(addr, router).await;
+        (format!("http://{}/", addr), hits)
+    }
+
+    #[test]
+    fn wildcards_match_subdomains_only() {
+        let policy = policy(&["*.example.com", "api.test"], &[]);
+        assert!(policy.allows_domain("a.example.com"));
+        assert!(policy.allows_domain("a.b.example.com"));
+        assert!(!policy.allows_domain("example.com"));
+        assert!(!policy.allows_domain("badexample.com"));
+        assert!(policy.allows_domain("api.test"));
+        assert!(!policy.allows_domain("www.api.test"));
+        // hosts are compared lowercase and without the root dot
+        assert!(policy.check_url("https://A.Example.COM./x").is_ok());
+        assert!(policy.check_url("https://example.org/").is_err());
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_are_checked_as_ipv4() {
+        let denied = policy(&[], &[]);
+        for ip in ["::ffff:127.0.0.1", "::ffff:169.254.169.254", "::ffff:10.1.2.3"] {
+            assert!(denied.check_ip(ip.parse().unwrap()).is_err(), "{}", ip);
+        }
+        assert!(denied.check_ip("::ffff:93.184.216.34".parse().unwrap()).is_ok());
+        assert!(denied.check_url("http://[::ffff:127.0.0.1]/").is_err());
+
+        let allowed = policy(&[], &["127.0.0.1"]);
+        assert!(allowed.check_ip("::ffff:127.0.0.1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn bracketed_ipv6_hosts_are_checked() {
+        let policy = policy(&[], &[]);
+        assert!(policy.check_url("http://[::1]:8080/").is_err());
+        assert!(policy.check_url("http://[fe80::1]/").is_err());
+        assert!(policy.check_url("http://[fd00::1]/").is_err());
+        assert!(policy.check_url("http://[2606:4700::1111]/").is_ok());
+    }
+
+    #[tokio::test]
+    async fn names_are_checked_on_the_addresses_connected_to() {
+        let (url, hits) = counting("127.0.0.1:0").await;
+        let url = url.replace("127.0.0.1", "localhost");
+        let provider = provider(config(&[], &[]));
+        // nothing in the URL itself is denied
+        assert!(provider.check_egress(&url).is_ok());
+
+        let err = provider
+            .request_uri::<Value, String>(&url, HttpRequestOptions::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<HttpCallError>(),
+                Some(HttpCallError::EgressDenied(denied)) if denied.target == "localhost"
+            ),
+            "{}",
+            err
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn redirects_to_denied_hosts_are_refused() {
+        let (target, hits) = counting("127.0.0.2:0").await;
+        let router = Router::new().fallback(get(move || async move { Redirect::to(&target) }));
+        let source = serve_on("127.0.0.1:0", router).await;
+        let provider = provider(config(&[], &["127.0.0.1"]));
+
+        let err = provider
+            .request_uri::<Value, String>(&format!("http://{}/", source), HttpRequestOptions::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<HttpCallError>(),
+                Some(HttpCallError::EgressDenied(denied)) if denied.target.starts_with("http://127.0.0.2")
+            ),
+            "{}",
+            err
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn denials_are_found_in_reqwest_errors() {
+        let (url, _) = counting("127.0.0.1:0").await;
+        let client = Client::builder()
+            .no_proxy()
+            .dns_resolver(Arc::new(EgressResolver::new(
+                Arc::new(SniResolver::default()),
+                Arc::new(policy(&[], &[])),
+            )))
+            .build()
+            .unwrap();
+
+        let err = client
+            .get(url.replace("127.0.0.1", "localhost"))
+            .send()
+            .await
+            .unwrap_err();
+        let denied = EgressDenied::find(&err).unwrap();
+        assert_eq!(denied.target, "localhost");
+        assert!(denied.reason.contains("denied range"), "{}", denied.reason);
+    }
+}
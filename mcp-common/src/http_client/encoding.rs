@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::CONTENT_TYPE,
+    multipart::{Form, Part},
+    RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// How the request body is put on the wire.
+///
+/// - `json`: the body as JSON (default).
+/// - `form`: a flat object sent as `application/x-www-form-urlencoded`;
+///   arrays repeat the key, nested objects are sent as JSON strings.
+/// - `multipart`: an object sent as `multipart/form-data`. A field whose value
+///   is `{"base64": "...", "filename": "a.pdf", "content_type": "application/pdf"}`
+///   becomes a file part, every other field a text part.
+/// - `xml` / `text`: the body must be a string (usually rendered by a body
+///   template) and is sent as-is with `application/xml` or `text/plain`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestEncoding {
+    #[default]
+    Json,
+    Form,
+    Multipart,
+    Xml,
+    Text,
+}
+
+impl RequestEncoding {
+    /// Whether a body template renders raw text rather than JSON.
+    pub fn is_text(&self) -> bool {
+        matches!(self, RequestEncoding::Xml | RequestEncoding::Text)
+    }
+
+    /// Attaches `body` to the request using this encoding.
+    ///
+    /// `has_content_type` tells whether the caller already set a `Content-Type`
+    /// header, which then wins over the encoding's default.
+    pub fn apply(
+        &self,
+        req: RequestBuilder,
+        body: &Value,
+        has_content_type: bool,
+    ) -> Result<RequestBuilder> {
+        let req = match self {
+            RequestEncoding::Json => req.json(body),
+            RequestEncoding::Form => req.form(&form_pairs(body)?),
+            RequestEncoding::Multipart => req.multipart(multipart_form(body)?),
+            RequestEncoding::Xml => text_body(req, body, "application/xml", has_content_type)?,
+            RequestEncoding::Text => text_body(req, body, "text/plain", has_content_type)?,
+        };
+        Ok(req)
+    }
+}
+
+fn body_object(body: &Value) -> Result<&Map<String, Value>> {
+    body.as_object()
+        .ok_or_else(|| anyhow!("Expected an object body for form encodings"))
+}
+
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn form_pairs(body: &Value) -> Result<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    for (key, value) in body_object(body)? {
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    pairs.push((key.clone(), scalar_to_string(item)));
+                }
+            }
+            other => pairs.push((key.clone(), scalar_to_string(other))),
+        }
+    }
+    Ok(pairs)
+}
+
+fn multipart_form(body: &Value) -> Result<Form> {
+    let mut form = Form::new();
+    for (key, value) in body_object(body)? {
+        let part = match value.get("base64").and_then(Value::as_str) {
+            Some(encoded) => {
+                let bytes = STANDARD
+                    .decode(encoded)
+                    .map_err(|e| anyhow!("Invalid base64 in multipart field `{}`: {}", key, e))?;
+                let mut part = Part::bytes(bytes);
+                if let Some(filename) = value.get("filename").and_then(Value::as_str) {
+                    part = part.file_name(filename.to_string());
+                }
+                if let Some(content_type) = value.get("content_type").and_then(Value::as_str) {
+                    part = part.mime_str(content_type)?;
+                }
+                part
+            }
+            None => Part::text(scalar_to_string(value)),
+        };
+        form = form.part(key.clone(), part);
+    }
+    Ok(form)
+}
+
+fn text_body(
+    req: RequestBuilder,
+    body: &Value,
+    content_type: &str,
+    has_content_type: bool,
+) -> Result<RequestBuilder> {
+    let text = body
+        .as_str()
+        .ok_or_else(|| anyhow!("Expected a string body for {} encoding", content_type))?
+        .to_string();
+    let req = if has_content_type {
+        req
+    } else {
+        req.header(CONTENT_TYPE, content_type)
+    };
+    Ok(req.body(text))
+}
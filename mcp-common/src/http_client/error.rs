@@ -0,0 +1,39 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::http_client::egress::EgressDenied;
+
+/// Upstream failures the tool layer reports back as `isError` results.
+#[derive(Debug, Error)]
+pub enum HttpCallError {
+    #[error("Upstream request timed out after {0} ms")]
+    Timeout(u128),
+
+    #[error("Request failed: {status} - {body}")]
+    Status { status: StatusCode, body: String },
+
+    #[error("Upstream request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    #[error("Upstream response exceeded {0} bytes")]
+    BodyTooLarge(usize),
+
+    #[error(transparent)]
+    EgressDenied(#[from] EgressDenied),
+
+    #[error("Upstream calls are not sent in replay mode")]
+    Replaying,
+}
+
+impl HttpCallError {
+    /// Whether the failure points at an unhealthy upstream rather than a bad request.
+    pub fn is_upstream_failure(&self) -> bool {
+        match self {
+            HttpCallError::Status { status, .. } => status.is_server_error(),
+            HttpCallError::Timeout(_) | HttpCallError::Transport(_) => true,
+            HttpCallError::BodyTooLarge(_)
+            | HttpCallError::EgressDenied(_)
+            | HttpCallError::Replaying => false,
+        }
+    }
+}
@@ -1,57 +1,315 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
 use anyhow::{anyhow, Result};
-use reqwest::{Client, Method, Response, StatusCode};
+use moka::sync::Cache;
+use reqwest::{header::HeaderMap, redirect, Client, Method, RequestBuilder, Response, StatusCode};
 use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+use crate::{
+    config::config::{AppConfig, HttpClientSection},
+    http_client::{
+        egress::{EgressDenied, EgressPolicy, EgressResolver},
+        encoding::RequestEncoding,
+        error::HttpCallError,
+        model::{
+            read_body, HttpCallPolicy, HttpRequestOptions, HttpResponseFormat, JsonResponse,
+            ResponseLimits,
+        },
+        tls_profile::{SniResolver, TlsProfile},
+    },
+    recording::recorder::RecordingMode,
+};
+
+// Connect timeouts and redirect policies are client-level settings in
+// reqwest, so clients are pooled per TLS profile, connect timeout and whether
+// they follow redirects. Clients of a profile with an SNI override are also
+// pinned to one upstream host, so the pool is bounded and idle clients are
+// dropped.
+const MAX_POOLED_CLIENTS: u64 = 256;
+const POOLED_CLIENT_IDLE: Duration = Duration::from_secs(30 * 60);
 
-use crate::http_client::model::{HttpRequestOptions, HttpResponseFormat, JsonResponse};
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    tls_profile: Option<String>,
+    sni_host: Option<String>,
+    connect_timeout: Duration,
+    follow_redirects: bool,
+}
 
 #[derive(Debug, Clone)]
 pub struct HttpClientProvider {
-    client: Client,
+    tls_profiles: HashMap<String, TlsProfile>,
+    clients: Cache<ClientKey, Client>,
+    // global defaults from `[http_client.call_policy]`
+    default_policy: HttpCallPolicy,
+    // global defaults from `[http_client.response_limits]`
+    default_limits: ResponseLimits,
+    // `[http_client.egress]`
+    egress: Arc<EgressPolicy>,
+    // `[recording] mode = "replay"`: answers come from the cassettes and
+    // nothing is sent
+    replaying: bool,
 }
 
 impl HttpClientProvider {
-    pub fn new() -> Result<Self> {
-        let client = Client::builder().build()?;
-        Ok(Self { client })
+    pub fn new(config: &HttpClientSection) -> Result<Self> {
+        config
+            .response_limits
+            .validate()
+            .map_err(|e| anyhow!("Invalid [http_client]: {}", e))?;
+        let tls_profiles = config
+            .tls_profiles
+            .iter()
+            .map(|(name, profile)| (name.clone(), TlsProfile::new(name, profile)))
+            .collect();
+        let provider = Self {
+            tls_profiles,
+            clients: Cache::builder()
+                .max_capacity(MAX_POOLED_CLIENTS)
+                .time_to_idle(POOLED_CLIENT_IDLE)
+                .build(),
+            default_policy: config.call_policy.clone(),
+            default_limits: config.response_limits.clone(),
+            egress: Arc::new(EgressPolicy::new(&config.egress)?),
+            replaying: false,
+        };
+
+        // build the default clients up front so broken profiles fail at startup
+        let connect_timeout = provider.default_policy.connect_timeout();
+        provider.client(None, None, connect_timeout, true)?;
+        for name in config.tls_profiles.keys() {
+            provider.client(Some(name), None, connect_timeout, true)?;
+        }
+        Ok(provider)
     }
 
-    async fn send<T: Serialize + Send + Sync>(
+    /// The provider of `[http_client]`. In replay mode it refuses every
+    /// request, so backends that are not replayed (MCP proxies, WASM
+    /// `http-call`, MDS sync, health probes) cannot reach the network.
+    pub fn from_config(config: &AppConfig) -> Result<Self> {
+        let mut provider = Self::new(&config.http_client)?;
+        provider.replaying = config.recording.mode == RecordingMode::Replay;
+        Ok(provider)
+    }
+
+    pub fn has_tls_profile(&self, name: &str) -> bool {
+        self.tls_profiles.contains_key(name)
+    }
+
+    /// Checks an upstream URL against the egress policy, e.g. when a TDS is
+    /// saved. Calls are checked again when they are sent.
+    pub fn check_egress(&self, url: &str) -> Result<(), EgressDenied> {
+        self.egress.check_url(url)
+    }
+
+    /// The `[http_client.egress]` policy, shared with the other upstream
+    /// clients.
+    pub fn egress_policy(&self) -> Arc<EgressPolicy> {
+        self.egress.clone()
+    }
+
+    /// Resolves a per-call policy against the global defaults.
+    pub fn call_policy(&self, overrides: Option<&HttpCallPolicy>) -> HttpCallPolicy {
+        match overrides {
+            Some(policy) => policy.or(&self.default_policy),
+            None => self.default_policy.clone(),
+        }
+    }
+
+    /// Resolves per-call response limits against the global defaults.
+    pub fn response_limits(&self, overrides: Option<&ResponseLimits>) -> ResponseLimits {
+        match overrides {
+            Some(limits) => limits.or(&self.default_limits),
+            None => self.default_limits.clone(),
+        }
+    }
+
+    fn client(
+        &self,
+        tls_profile: Option<&str>,
+        sni_host: Option<&str>,
+        connect_timeout: Duration,
+        follow_redirects: bool,
+    ) -> Result<Client> {
+        let key = ClientKey {
+            tls_profile: tls_profile.map(str::to_string),
+            sni_host: sni_host.map(str::to_string),
+            connect_timeout,
+            follow_redirects,
+        };
+        if let Some(client) = self.clients.get(&key) {
+            return Ok(client);
+        }
+        let (builder, resolver) = match tls_profile {
+            Some(name) => {
+                let profile = self.tls_profile(name)?;
+                (profile.client_builder()?, profile.resolver(sni_host))
+            }
+            None => (Client::builder(), SniResolver::default()),
+        };
+        // a proxy from HTTP(S)_PROXY would resolve and connect in our place,
+        // past the resolver's checks
+        let builder = match self.egress.is_enabled() {
+            true => builder
+                .no_proxy()
+                .dns_resolver(Arc::new(EgressResolver::new(
+                    Arc::new(resolver),
+                    self.egress.clone(),
+                )))
+                .redirect(self.egress.redirect_policy()),
+            false => builder.dns_resolver(Arc::new(resolver)),
+        };
+        let builder = match follow_redirects {
+            true => builder,
+            false => builder.redirect(redirect::Policy::none()),
+        };
+        let client = builder.connect_timeout(connect_timeout).build()?;
+        self.clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    fn tls_profile(&self, name: &str) -> Result<&TlsProfile> {
+        self.tls_profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown TLS profile: {}", name))
+    }
+
+    fn build_request<T: Serialize + Send + Sync>(
         &self,
+        method: Method,
         url: &str,
         options: &HttpRequestOptions<T>,
-    ) -> Result<Response> {
-        let method = options.method.parse::<Method>()?;
-        let mut req = self.client.request(method, url);
+        policy: &HttpCallPolicy,
+    ) -> Result<RequestBuilder> {
+        let connect_timeout = policy.connect_timeout();
+        let follow_redirects = options.follow_redirects;
+        let mut req = match &options.tls_profile {
+            Some(name) => {
+                let (url, sni_host) = self.tls_profile(name)?.route_url(url)?;
+                self.client(Some(name), sni_host.as_deref(), connect_timeout, follow_redirects)?
+                    .request(method, url)
+            }
+            None => self
+                .client(None, None, connect_timeout, follow_redirects)?
+                .request(method, url),
+        };
+        req = req.timeout(policy.timeout());
 
+        // a multipart Content-Type carries the form's boundary, so the
+        // encoding sets it and a caller's one is dropped
+        let multipart = options.body.is_some() && options.encoding == RequestEncoding::Multipart;
         if let Some(headers) = &options.headers {
             for (k, v) in headers {
+                if multipart && k.eq_ignore_ascii_case("content-type") {
+                    continue;
+                }
                 req = req.header(k, v);
             }
         }
 
         if let Some(body) = &options.body {
-            req = req.json(body);
+            req = match options.encoding {
+                RequestEncoding::Json => req.json(body),
+                encoding => {
+                    let has_content_type = options.headers.as_ref().is_some_and(|headers| {
+                        headers.keys().any(|k| k.eq_ignore_ascii_case("content-type"))
+                    });
+                    encoding.apply(req, &serde_json::to_value(body)?, has_content_type)?
+                }
+            };
+        }
+
+        Ok(req)
+    }
+
+    async fn send<T: Serialize + Send + Sync>(
+        &self,
+        url: &str,
+        options: &HttpRequestOptions<T>,
+    ) -> Result<Response> {
+        let method = options.method.parse::<Method>()?;
+        let policy = self.call_policy(options.call_policy.as_ref());
+        let max_retries = if method.is_idempotent() || policy.retry_non_idempotent() {
+            policy.max_retries()
+        } else {
+            0
+        };
+
+        if self.replaying {
+            return Err(HttpCallError::Replaying.into());
         }
+        self.egress.check_url(url).map_err(HttpCallError::from)?;
 
-        let resp = req.send().await?;
+        // the deadline covers every attempt and the backoff between them
+        let attempts = async {
+            let mut attempt = 0;
+            loop {
+                let result = self
+                    .build_request(method.clone(), url, options, &policy)?
+                    .send()
+                    .await;
+                let retryable = match &result {
+                    Ok(resp) => policy.is_retryable_status(resp.status().as_u16()),
+                    Err(e) => (e.is_timeout() || e.is_connect()) && EgressDenied::find(e).is_none(),
+                };
+                if !retryable || attempt >= max_retries {
+                    return anyhow::Ok(result);
+                }
+                attempt += 1;
+                let delay = policy.backoff(attempt);
+                warn!(
+                    "Retrying {} {} (attempt {}/{}) in {:?}",
+                    method, url, attempt, max_retries, delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+        };
+        let total_timeout = policy.total_timeout();
+        let result = match tokio::time::timeout(total_timeout, attempts).await {
+            Ok(result) => result?,
+            Err(_) => return Err(HttpCallError::Timeout(total_timeout.as_millis()).into()),
+        };
+
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) if e.is_timeout() => {
+                return Err(HttpCallError::Timeout(policy.timeout().as_millis()).into())
+            }
+            Err(e) => match EgressDenied::find(&e) {
+                Some(denied) => return Err(HttpCallError::EgressDenied(denied).into()),
+                None => return Err(HttpCallError::Transport(e).into()),
+            },
+        };
         let status = resp.status();
 
-        if !status.is_success() {
-            let err_text = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("Request failed: {} - {}", status, err_text));
+        // an unfollowed redirect is the caller's to handle
+        let unfollowed = status.is_redirection() && !options.follow_redirects;
+        if !status.is_success() && !unfollowed {
+            let max_bytes = self
+                .response_limits(options.response_limits.as_ref())
+                .max_response_bytes();
+            let body = match read_body(resp, max_bytes).await {
+                Ok((buf, _)) => String::from_utf8_lossy(&buf).into_owned(),
+                Err(_) => String::new(),
+            };
+            return Err(HttpCallError::Status { status, body }.into());
         }
 
         Ok(resp)
     }
 
-    /// Sends an HTTP request and parses the response as a `String` or `Bytes`.
+    /// Sends an HTTP request and parses the response as a `String`, `Bytes` or
+    /// `LimitedText`. `String` and `Bytes` fail when the body exceeds
+    /// `max_response_bytes`; `LimitedText` returns the truncated body instead.
     ///
     /// Example usage (returns `String`):
-    /// ```
+    /// ```ignore
     /// let options = HttpRequestOptions {
     ///     method: "POST".to_string(),
     ///     headers: None,
     ///     body: Some(serde_json::json!({ "hello": "world" })),
+    ///     ..Default::default()
     /// };
     ///
     /// let response: String = client
@@ -63,11 +321,12 @@ impl HttpClientProvider {
     /// ```
     ///
     /// Example usage (returns `Bytes`):
-    /// ```
+    /// ```ignore
     /// let options = HttpRequestOptions {
     ///     method: "GET".to_string(),
     ///     headers: None,
     ///     body: None,
+    ///     ..Default::default()
     /// };
     ///
     /// let response: bytes::Bytes = client
@@ -86,16 +345,47 @@ impl HttpClientProvider {
         T: Serialize + Send + Sync,
         R: HttpResponseFormat + Send,
     {
+        let (status, _, parsed) = self.request_with_headers(url, options).await?;
+        Ok((status, parsed))
+    }
+
+    /// Like `request_uri`, but also returns the response headers.
+    pub async fn request_with_headers<T, R>(
+        &self,
+        url: &str,
+        options: HttpRequestOptions<T>,
+    ) -> Result<(StatusCode, HeaderMap, R)>
+    where
+        T: Serialize + Send + Sync,
+        R: HttpResponseFormat + Send,
+    {
+        let max_bytes = self
+            .response_limits(options.response_limits.as_ref())
+            .max_response_bytes();
         let resp = self.send(url, &options).await?;
         let status = resp.status();
-        let parsed = R::from_response(resp).await?;
-        Ok((status, parsed))
+        let headers = resp.headers().clone();
+        let parsed = R::from_response(resp, max_bytes).await?;
+        Ok((status, headers, parsed))
+    }
+
+    /// Sends an HTTP request and returns the response unread, for bodies that
+    /// are consumed as a stream (e.g. server-sent events).
+    pub async fn request_stream<T>(
+        &self,
+        url: &str,
+        options: HttpRequestOptions<T>,
+    ) -> Result<Response>
+    where
+        T: Serialize + Send + Sync,
+    {
+        self.send(url, &options).await
     }
 
     /// Sends an HTTP request and parses the JSON response into a struct.
     ///
     /// Example usage:
-    /// ```
+    /// ```ignore
     /// #[derive(Deserialize, Debug)]
     /// struct HttpBinResponse {
     ///     json: Option<serde_json::Value>,
@@ -105,6 +395,7 @@ impl HttpClientProvider {
     ///     method: "POST".to_string(),
     ///     headers: None,
     ///     body: Some(serde_json::json!({ "foo": "bar" })),
+    ///     ..Default::default()
     /// };
     ///
     /// let response: HttpBinResponse = client
@@ -123,9 +414,204 @@ impl HttpClientProvider {
         T: Serialize + Send + Sync,
         R: DeserializeOwned,
     {
+        let max_bytes = self
+            .response_limits(options.response_limits.as_ref())
+            .max_response_bytes();
         let resp = self.send(url, &options).await?;
         let status = resp.status();
-        let parsed = JsonResponse::from_response(resp).await?;
+        let parsed = JsonResponse::from_response(resp, max_bytes).await?;
         Ok((status, parsed))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Instant,
+    };
+
+    use axum::{http::StatusCode as AxumStatus, routing::get, Router};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::test_util::serve;
+
+    /// A server answering 503 to the first `failures` requests and 200 after,
+    /// with the number of requests it has seen.
+    async fn flaky(failures: usize) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let seen = hits.clone();
+        let router = Router::new().route(
+            "/",
+            get(move || {
+                let n = seen.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match n < failures {
+                        true => (AxumStatus::SERVICE_UNAVAILABLE, "down"),
+                        false => (AxumStatus::OK, "up"),
+                    }
+                }
+            }),
+        );
+        (format!("http://{}/", serve(router).await), hits)
+    }
+
+    fn options(policy: HttpCallPolicy) -> HttpRequestOptions<Value> {
+        HttpRequestOptions {
+            call_policy: Some(policy),
+            ..Default::default()
+        }
+    }
+
+    fn provider() -> HttpClientProvider {
+        HttpClientProvider::new(&HttpClientSection::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn retryable_statuses_are_retried_up_to_max_retries() {
+        let (url, hits) = flaky(2).await;
+        let policy = HttpCallPolicy {
+            max_retries: Some(2),
+            backoff_base_ms: Some(1),
+            ..Default::default()
+        };
+        let (status, body) = provider()
+            .request_uri::<Value, String>(&url, options(policy))
+            .await
+            .unwrap();
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "up"));
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn calls_are_not_retried_by_default() {
+        let (url, hits) = flaky(1).await;
+        let err = provider()
+            .request_uri::<Value, String>(&url, HttpRequestOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HttpCallError>(),
+            Some(HttpCallError::Status { status, .. }) if *status == StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_at_the_total_deadline() {
+        let (url, hits) = flaky(usize::MAX).await;
+        let policy = HttpCallPolicy {
+            max_retries: Some(1_000),
+            backoff_base_ms: Some(50),
+            backoff_max_ms: Some(50),
+            total_timeout_ms: Some(300),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let err = provider()
+            .request_uri::<Value, String>(&url, options(policy))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HttpCallError>(),
+            Some(HttpCallError::Timeout(300))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(hits.load(Ordering::SeqCst) > 1);
+    }
+
+    #[tokio::test]
+    async fn multipart_bodies_send_one_content_type() {
+        let router = Router::new().route(
+            "/",
+            axum::routing::post(|headers: axum::http::HeaderMap| async move {
+                headers
+                    .get_all("content-type")
+                    .iter()
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }),
+        );
+        let url = format!("http://{}/", serve(router).await);
+        let options = HttpRequestOptions {
+            method: "POST".to_string(),
+            headers: Some(HashMap::from([(
+                "Content-Type".to_string(),
+                "text/plain".to_string(),
+            )])),
+            body: Some(serde_json::json!({ "name": "report" })),
+            encoding: RequestEncoding::Multipart,
+            ..Default::default()
+        };
+        let (_, content_type) = provider()
+            .request_uri::<Value, String>(&url, options)
+            .await
+            .unwrap();
+        assert!(
+            content_type.starts_with("multipart/form-data; boundary=") && !content_type.contains('|'),
+            "{}",
+            content_type
+        );
+    }
+
+    #[tokio::test]
+    async fn replaying_providers_send_nothing() {
+        let (url, hits) = flaky(0).await;
+        let config = AppConfig::from_toml(
+            r#"
+[app]
+host = "127.0.0.1"
+port = 0
+data_source = "etcd"
+api_key = "test"
+
+[log]
+log_level = "info"
+log_dir = "logs"
+log_name = "test"
+
+[data_source]
+
+[recording]
+mode = "replay"
+"#,
+        )
+        .unwrap();
+        let err = HttpClientProvider::from_config(&config)
+            .unwrap()
+            .request_uri::<Value, String>(&url, HttpRequestOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HttpCallError>(),
+            Some(HttpCallError::Replaying)
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn the_client_pool_is_bounded() {
+        let provider = provider();
+        for ms in 1..=MAX_POOLED_CLIENTS + 44 {
+            provider
+                .client(None, None, Duration::from_millis(ms), true)
+                .unwrap();
+        }
+        provider.clients.run_pending_tasks();
+        assert!(provider.clients.entry_count() <= MAX_POOLED_CLIENTS);
+    }
+
+    #[test]
+    fn zero_response_limits_are_rejected() {
+        let config = HttpClientSection {
+            response_limits: ResponseLimits {
+                max_result_bytes: Some(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(HttpClientProvider::new(&config).is_err());
+    }
+}
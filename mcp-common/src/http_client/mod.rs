@@ -1,11 +1,22 @@
+pub mod egress;
+pub mod encoding;
+pub mod error;
 pub mod http_client_provider;
 pub mod model;
+pub mod pagination;
+pub mod stream;
+pub mod tls_profile;
 
 use once_cell::sync::Lazy;
 
-use crate::http_client::http_client_provider::HttpClientProvider;
+use crate::{
+    http_client::http_client_provider::HttpClientProvider,
+    provider::global_provider::get_app_config,
+};
 
 
 pub static HTTP_CLIENT: Lazy<HttpClientProvider> = Lazy::new(|| {
-    HttpClientProvider::new().expect("Failed to initialize global HttpClientProvider")
+    get_app_config()
+        .and_then(|config| HttpClientProvider::from_config(&config))
+        .expect("Failed to initialize global HttpClientProvider")
 });
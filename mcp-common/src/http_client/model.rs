@@ -1,10 +1,20 @@
-use std::collections::HashMap;
-use anyhow::{ Result };
+use std::{collections::HashMap, time::Duration};
+use anyhow::{ anyhow, Result };
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use rand::Rng;
 use reqwest::Response;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    constants::constants::http_client_consts::{
+        DEFAULT_BACKOFF_BASE_MS, DEFAULT_BACKOFF_MAX_MS, DEFAULT_CONNECT_TIMEOUT_MS,
+        DEFAULT_MAX_RESPONSE_BYTES, DEFAULT_MAX_RESULT_BYTES, DEFAULT_MAX_RETRIES,
+        DEFAULT_RETRYABLE_STATUS, DEFAULT_TIMEOUT_MS, DEFAULT_TOTAL_TIMEOUT_MS,
+    },
+    http_client::{encoding::RequestEncoding, error::HttpCallError},
+};
 
 
 #[derive(Debug, Clone)]
@@ -12,30 +22,221 @@ pub struct HttpRequestOptions<T> where T: Serialize {
     pub method: String,
     pub headers: Option<HashMap<String, String>>,
     pub body: Option<T>,
+    // Name of the TLS profile whose client sends this request
+    pub tls_profile: Option<String>,
+    // Per-call overrides of the global timeout and retry policy
+    pub call_policy: Option<HttpCallPolicy>,
+    // How `body` is encoded: JSON, form, multipart, XML or raw text
+    pub encoding: RequestEncoding,
+    // Per-call overrides of the global response size limits
+    pub response_limits: Option<ResponseLimits>,
+    // When false, a 3xx comes back as the response instead of being followed
+    pub follow_redirects: bool,
+}
+
+impl<T: Serialize> Default for HttpRequestOptions<T> {
+    fn default() -> Self {
+        Self {
+            method: "GET".to_string(),
+            headers: None,
+            body: None,
+            tls_profile: None,
+            call_policy: None,
+            encoding: RequestEncoding::default(),
+            response_limits: None,
+            follow_redirects: true,
+        }
+    }
+}
+
+/// Timeout and retry settings for an upstream call.
+///
+/// All fields are optional: a TDS only sets what it wants to override, unset
+/// fields fall back to `[http_client.call_policy]` and then to the built-in
+/// defaults in `http_client_consts`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpCallPolicy {
+    pub connect_timeout_ms: Option<u64>,
+    // Applies to each attempt, from sending the request to reading the body
+    pub timeout_ms: Option<u64>,
+    // Deadline of the whole call: every attempt and the backoff between them
+    pub total_timeout_ms: Option<u64>,
+    pub max_retries: Option<u32>,
+    // Exponential backoff with full jitter: rand(0, min(max, base * 2^attempt))
+    pub backoff_base_ms: Option<u64>,
+    pub backoff_max_ms: Option<u64>,
+    pub retryable_status: Option<Vec<u16>>,
+    // POST and PATCH are only retried when the tool opts in
+    pub retry_non_idempotent: Option<bool>,
+}
+
+impl HttpCallPolicy {
+    /// Fills the unset fields of `self` from `fallback`.
+    pub fn or(&self, fallback: &HttpCallPolicy) -> HttpCallPolicy {
+        HttpCallPolicy {
+            connect_timeout_ms: self.connect_timeout_ms.or(fallback.connect_timeout_ms),
+            timeout_ms: self.timeout_ms.or(fallback.timeout_ms),
+            total_timeout_ms: self.total_timeout_ms.or(fallback.total_timeout_ms),
+            max_retries: self.max_retries.or(fallback.max_retries),
+            backoff_base_ms: self.backoff_base_ms.or(fallback.backoff_base_ms),
+            backoff_max_ms: self.backoff_max_ms.or(fallback.backoff_max_ms),
+            retryable_status: self
+                .retryable_status
+                .clone()
+                .or_else(|| fallback.retryable_status.clone()),
+            retry_non_idempotent: self.retry_non_idempotent.or(fallback.retry_non_idempotent),
+        }
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    }
+
+    pub fn total_timeout(&self) -> Duration {
+        Duration::from_millis(self.total_timeout_ms.unwrap_or(DEFAULT_TOTAL_TIMEOUT_MS))
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES)
+    }
+
+    pub fn retry_non_idempotent(&self) -> bool {
+        self.retry_non_idempotent.unwrap_or(false)
+    }
+
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        match &self.retryable_status {
+            Some(codes) => codes.contains(&status),
+            None => DEFAULT_RETRYABLE_STATUS.contains(&status),
+        }
+    }
+
+    /// Delay before retry number `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.backoff_base_ms.unwrap_or(DEFAULT_BACKOFF_BASE_MS);
+        let max = self.backoff_max_ms.unwrap_or(DEFAULT_BACKOFF_MAX_MS);
+        let cap = base
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(max);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
+    }
+}
+
+/// Size caps for upstream responses.
+///
+/// Like `HttpCallPolicy`, unset fields fall back to `[http_client.response_limits]`
+/// and then to the built-in defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseLimits {
+    // Bytes read from the upstream body; reading stops once the cap is hit
+    pub max_response_bytes: Option<usize>,
+    // Bytes of tool result text returned to the model, after transforms
+    pub max_result_bytes: Option<usize>,
+}
+
+impl ResponseLimits {
+    /// Fills the unset fields of `self` from `fallback`.
+    pub fn or(&self, fallback: &ResponseLimits) -> ResponseLimits {
+        ResponseLimits {
+            max_response_bytes: self.max_response_bytes.or(fallback.max_response_bytes),
+            max_result_bytes: self.max_result_bytes.or(fallback.max_result_bytes),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.max_response_bytes == Some(0) || self.max_result_bytes == Some(0) {
+            return Err(anyhow!("response_limits must be greater than zero"));
+        }
+        Ok(())
+    }
+
+    pub fn max_response_bytes(&self) -> usize {
+        self.max_response_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES)
+    }
+
+    pub fn max_result_bytes(&self) -> usize {
+        self.max_result_bytes.unwrap_or(DEFAULT_MAX_RESULT_BYTES)
+    }
+}
+
+/// Streams the response body, stopping after `max_bytes`.
+///
+/// Returns the bytes read and whether the body was cut off.
+pub async fn read_body(mut resp: Response, max_bytes: usize) -> Result<(Vec<u8>, bool)> {
+    let mut buf = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(HttpCallError::Transport)? {
+        let room = max_bytes - buf.len();
+        if chunk.len() > room {
+            buf.extend_from_slice(&chunk[..room]);
+            return Ok((buf, true));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok((buf, false))
+}
+
+/// Like `read_body`, but fails instead of returning a partial body.
+async fn read_full_body(resp: Response, max_bytes: usize) -> Result<Vec<u8>> {
+    match read_body(resp, max_bytes).await? {
+        (_, true) => Err(HttpCallError::BodyTooLarge(max_bytes).into()),
+        (buf, false) => Ok(buf),
+    }
 }
 
 #[async_trait]
 pub trait HttpResponseFormat: Sized {
-    async fn from_response(resp: Response) -> Result<Self>;
+    /// Builds the response value, reading at most `max_bytes` of the body.
+    async fn from_response(resp: Response, max_bytes: usize) -> Result<Self>;
 }
 
 #[async_trait]
 impl HttpResponseFormat for String {
-    async fn from_response(resp: Response) -> Result<Self> {
-        Ok(resp.text().await?)
+    async fn from_response(resp: Response, max_bytes: usize) -> Result<Self> {
+        let buf = read_full_body(resp, max_bytes).await?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
     }
 }
 
 #[async_trait]
 impl HttpResponseFormat for Bytes {
-    async fn from_response(resp: Response) -> Result<Self> {
-        Ok(resp.bytes().await?)
+    async fn from_response(resp: Response, max_bytes: usize) -> Result<Self> {
+        Ok(Bytes::from(read_full_body(resp, max_bytes).await?))
+    }
+}
+
+/// Response text that is cut off at the size limit instead of failing.
+#[derive(Debug, Clone)]
+pub struct LimitedText {
+    pub text: String,
+    // Whether the upstream body was larger than the limit
+    pub truncated: bool,
+}
+
+#[async_trait]
+impl HttpResponseFormat for LimitedText {
+    async fn from_response(resp: Response, max_bytes: usize) -> Result<Self> {
+        let (mut buf, truncated) = read_body(resp, max_bytes).await?;
+        // don't leave half a UTF-8 sequence at the cut
+        if let Err(e) = std::str::from_utf8(&buf) {
+            if truncated && e.error_len().is_none() {
+                buf.truncate(e.valid_up_to());
+            }
+        }
+        Ok(LimitedText {
+            text: String::from_utf8_lossy(&buf).into_owned(),
+            truncated,
+        })
     }
 }
 
 pub struct JsonResponse;
 impl JsonResponse {
-    pub async fn from_response<R: DeserializeOwned>(resp: Response) -> Result<R> {
-        Ok(resp.json::<R>().await?)
+    pub async fn from_response<R: DeserializeOwned>(resp: Response, max_bytes: usize) -> Result<R> {
+        let buf = read_full_body(resp, max_bytes).await?;
+        Ok(serde_json::from_slice::<R>(&buf)?)
     }
-}
\ No newline at end of file
+}
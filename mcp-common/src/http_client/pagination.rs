@@ -0,0 +1,300 @@
+use anyhow::{anyhow, Result};
+use reqwest::{
+    header::{HeaderMap, LINK},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use crate::constants::constants::http_client_consts::{DEFAULT_MAX_PAGES, DEFAULT_MAX_PAGE_ITEMS};
+
+/// How the next page of a list API is requested.
+///
+/// - `link_header`: follow the `rel="next"` URL of the `Link` response header,
+///   which must stay on the origin of the first request.
+/// - `cursor`: read the next cursor from the body via `next_cursor_path` and
+///   send it back as the `cursor_param` query parameter.
+/// - `page`: increment the `page_param` query parameter, starting from the
+///   value in the first request or `start_page`.
+/// - `offset`: advance the `offset_param` query parameter by `page_size`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum PaginationStyle {
+    LinkHeader,
+    Cursor {
+        cursor_param: String,
+        next_cursor_path: String,
+    },
+    Page {
+        page_param: String,
+        #[serde(default = "default_start_page")]
+        start_page: u64,
+        // Sent as `page_size_param` when set; a shorter page ends the listing
+        page_size: Option<u64>,
+        page_size_param: Option<String>,
+    },
+    Offset {
+        offset_param: String,
+        page_size: u64,
+        // Sent as `limit_param` = `page_size` when set
+        limit_param: Option<String>,
+    },
+}
+
+fn default_start_page() -> u64 {
+    1
+}
+
+/// Pagination settings of a TDS.
+///
+/// The pages' items are concatenated into one JSON array, which is what the
+/// response transform (if any) then sees. Together the pages may not exceed
+/// the tool's `max_response_bytes`.
+///
+/// ```json
+/// {
+///   "style": "cursor",
+///   "cursor_param": "after",
+///   "next_cursor_path": "$.meta.next",
+///   "items_path": "$.data",
+///   "max_pages": 5,
+///   "max_items": 200
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pagination {
+    #[serde(flatten)]
+    pub style: PaginationStyle,
+    // JSONPath to the page's items; without it a top-level array is used
+    pub items_path: Option<String>,
+    pub max_pages: Option<usize>,
+    pub max_items: Option<usize>,
+}
+
+impl Pagination {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(path) = &self.items_path {
+            JsonPath::parse(path).map_err(|e| anyhow!("Invalid items_path: {}", e))?;
+        }
+        if let PaginationStyle::Cursor {
+            next_cursor_path, ..
+        } = &self.style
+        {
+            JsonPath::parse(next_cursor_path)
+                .map_err(|e| anyhow!("Invalid next_cursor_path: {}", e))?;
+        }
+        if let PaginationStyle::Offset { page_size: 0, .. } = &self.style {
+            return Err(anyhow!("page_size must be greater than zero"));
+        }
+        if self.max_pages == Some(0) || self.max_items == Some(0) {
+            return Err(anyhow!("max_pages and max_items must be greater than zero"));
+        }
+        Ok(())
+    }
+
+    pub fn max_pages(&self) -> usize {
+        self.max_pages.unwrap_or(DEFAULT_MAX_PAGES)
+    }
+
+    pub fn max_items(&self) -> usize {
+        self.max_items.unwrap_or(DEFAULT_MAX_PAGE_ITEMS)
+    }
+
+    /// Prepares the first request URL, e.g. adding the page size parameter.
+    pub fn first_url(&self, url: &str) -> Result<String> {
+        match &self.style {
+            PaginationStyle::Page {
+                page_size: Some(size),
+                page_size_param: Some(param),
+                ..
+            } => set_query_param(url, param, &size.to_string()),
+            PaginationStyle::Offset {
+                page_size,
+                limit_param: Some(param),
+                ..
+            } => set_query_param(url, param, &page_size.to_string()),
+            _ => Ok(url.to_string()),
+        }
+    }
+
+    /// Extracts the items of one page.
+    pub fn items(&self, page: &Value) -> Result<Vec<Value>> {
+        let Some(path) = &self.items_path else {
+            return Ok(match page {
+                Value::Array(items) => items.clone(),
+                other => vec![other.clone()],
+            });
+        };
+        let nodes = JsonPath::parse(path)?.query(page).all();
+        Ok(match nodes.as_slice() {
+            [Value::Array(items)] => items.clone(),
+            nodes => nodes.iter().map(|v| (*v).clone()).collect(),
+        })
+    }
+
+    /// Computes the URL of the page after the one fetched from `url`, or
+    /// `None` when the listing is exhausted.
+    pub fn next_url(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        page: &Value,
+        page_items: usize,
+    ) -> Result<Option<String>> {
+        if page_items == 0 {
+            return Ok(None);
+        }
+        match &self.style {
+            PaginationStyle::LinkHeader => {
+                let Some(next) = headers
+                    .get_all(LINK)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .find_map(next_link)
+                else {
+                    return Ok(None);
+                };
+                // relative links resolve against the current page; the upstream
+                // must not send the tool's request (and its headers) elsewhere
+                let current = Url::parse(url)?;
+                let next = current.join(&next)?;
+                if next.origin() != current.origin() {
+                    return Err(anyhow!(
+                        "Link header points to another origin: {}",
+                        next.origin().ascii_serialization()
+                    ));
+                }
+                Ok(Some(next.to_string()))
+            }
+            PaginationStyle::Cursor {
+                cursor_param,
+                next_cursor_path,
+            } => {
+                let cursor = JsonPath::parse(next_cursor_path)?
+                    .query(page)
+                    .first()
+                    .and_then(|v| match v {
+                        Value::String(s) if !s.is_empty() => Some(s.clone()),
+                        Value::Number(n) => Some(n.to_string()),
+                        _ => None,
+                    });
+                match cursor {
+                    Some(cursor) => set_query_param(url, cursor_param, &cursor).map(Some),
+                    None => Ok(None),
+                }
+            }
+            PaginationStyle::Page {
+                page_param,
+                start_page,
+                page_size,
+                ..
+            } => {
+                if page_size.is_some_and(|size| (page_items as u64) < size) {
+                    return Ok(None);
+                }
+                let current = query_param(url, page_param)?
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(*start_page);
+                set_query_param(url, page_param, &(current + 1).to_string()).map(Some)
+            }
+            PaginationStyle::Offset {
+                offset_param,
+                page_size,
+                ..
+            } => {
+                if (page_items as u64) < *page_size {
+                    return Ok(None);
+                }
+                let current = query_param(url, offset_param)?
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(0);
+                set_query_param(url, offset_param, &(current + page_size).to_string()).map(Some)
+            }
+        }
+    }
+}
+
+/// Returns the target of the `rel="next"` entry of a `Link` header value.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let target = parts.next()?.trim();
+        let is_next = parts.any(|param| {
+            let param = param.trim().replace(' ', "");
+            param == "rel=\"next\"" || param == "rel=next"
+        });
+        is_next.then(|| {
+            target
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+fn query_param(url: &str, key: &str) -> Result<Option<String>> {
+    let url = Url::parse(url)?;
+    Ok(url
+        .query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned()))
+}
+
+fn set_query_param(url: &str, key: &str, value: &str) -> Result<String> {
+    let mut url = Url::parse(url)?;
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != key)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, value);
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn link_header(link: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LINK, HeaderValue::from_str(link).unwrap());
+        headers
+    }
+
+    fn next(link: &str) -> Result<Option<String>> {
+        let pagination: Pagination = serde_json::from_value(json!({ "style": "link_header" })).unwrap();
+        pagination.next_url("https://api.example.com/v1/items?page=1", &link_header(link), &json!([1]), 1)
+    }
+
+    #[test]
+    fn link_header_follows_same_origin_links() {
+        assert_eq!(
+            next(r#"</v1/items?page=2>; rel="next""#).unwrap().as_deref(),
+            Some("https://api.example.com/v1/items?page=2")
+        );
+        assert_eq!(
+            next(r#"<https://api.example.com/v1/items?page=2>; rel=next"#).unwrap().as_deref(),
+            Some("https://api.example.com/v1/items?page=2")
+        );
+        assert_eq!(next(r#"</v1/items?page=0>; rel="prev""#).unwrap(), None);
+    }
+
+    #[test]
+    fn link_header_refuses_other_origins() {
+        for link in [
+            r#"<https://evil.example.net/collect>; rel="next""#,
+            r#"<http://api.example.com/v1/items?page=2>; rel="next""#,
+            r#"<https://api.example.com:8443/v1/items?page=2>; rel="next""#,
+            r#"<//evil.example.net/collect>; rel="next""#,
+        ] {
+            assert!(next(link).is_err(), "{}", link);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use reqwest::{header::CONTENT_TYPE, Response};
+use serde_json::Value;
+
+use crate::http_client::{error::HttpCallError, model::LimitedText};
+
+// End-of-stream marker some event-stream APIs send as a last event
+const DONE_MARKER: &str = "[DONE]";
+
+/// Upstream bodies that arrive as a sequence of items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    // Newline-delimited JSON, one item per line
+    Ndjson,
+    // Server-sent events, one item per event `data`
+    EventStream,
+}
+
+impl StreamFormat {
+    /// The stream format of a response, from its `Content-Type`.
+    pub fn of(resp: &Response) -> Option<Self> {
+        let content_type = resp.headers().get(CONTENT_TYPE)?.to_str().ok()?;
+        let media_type = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match media_type.as_str() {
+            "application/x-ndjson" | "application/ndjson" | "application/jsonl"
+            | "application/x-jsonlines" => Some(StreamFormat::Ndjson),
+            "text/event-stream" => Some(StreamFormat::EventStream),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a streamed body into items as the chunks arrive.
+struct ItemSplitter {
+    format: StreamFormat,
+    // bytes after the last complete line
+    pending: Vec<u8>,
+    // `data` lines of the event being read
+    data: Vec<String>,
+}
+
+impl ItemSplitter {
+    fn new(format: StreamFormat) -> Self {
+        Self {
+            format,
+            pending: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Takes a chunk and returns the items it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut items = Vec::new();
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line = self.pending.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            items.extend(self.line(line.trim_end_matches(['\n', '\r'])));
+        }
+        items
+    }
+
+    /// Returns the item left at the end of a complete body.
+    fn finish(&mut self) -> Vec<String> {
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned();
+        let last = self.line(line.trim_end_matches('\r'));
+        // an event-stream may end without the blank line closing its last event
+        last.into_iter().chain(self.line("")).collect()
+    }
+
+    fn line(&mut self, line: &str) -> Option<String> {
+        match self.format {
+            StreamFormat::Ndjson => {
+                let line = line.trim();
+                (!line.is_empty()).then(|| line.to_string())
+            }
+            StreamFormat::EventStream => {
+                if line.is_empty() {
+                    let data = std::mem::take(&mut self.data).join("\n");
+                    return (!data.is_empty() && data != DONE_MARKER).then_some(data);
+                }
+                // `event`, `id`, `retry` and comments carry nothing for the result
+                if let Some(value) = line.strip_prefix("data:") {
+                    self.data
+                        .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Reads a streamed body item by item, handing each item to `on_item` as it
+/// arrives, and stops after `max_bytes`.
+///
+/// The returned text aggregates the items: a JSON array when every item is
+/// JSON, else the items one per line. An item cut off by the size limit is
+/// dropped.
+pub async fn read_items(
+    mut resp: Response,
+    format: StreamFormat,
+    max_bytes: usize,
+    mut on_item: impl FnMut(&str) + Send,
+) -> Result<LimitedText> {
+    let mut splitter = ItemSplitter::new(format);
+    let mut items = Vec::new();
+    let mut read = 0;
+    let mut truncated = false;
+    while let Some(chunk) = resp.chunk().await.map_err(HttpCallError::Transport)? {
+        let room = max_bytes - read;
+        let chunk = if chunk.len() > room {
+            truncated = true;
+            chunk.slice(..room)
+        } else {
+            chunk
+        };
+        read += chunk.len();
+        for item in splitter.push(&chunk) {
+            on_item(&item);
+            items.push(item);
+        }
+        if truncated {
+            break;
+        }
+    }
+    if !truncated {
+        for item in splitter.finish() {
+            on_item(&item);
+            items.push(item);
+        }
+    }
+
+    let json = items
+        .iter()
+        .map(|item| serde_json::from_str::<Value>(item))
+        .collect::<Result<Vec<_>, _>>();
+    let text = match json {
+        Ok(values) if !values.is_empty() => Value::Array(values).to_string(),
+        _ => items.join("\n"),
+    };
+    Ok(LimitedText { text, truncated })
+}
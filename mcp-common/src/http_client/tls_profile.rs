@@ -0,0 +1,246 @@
+use std::fs;
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    tls::Version,
+    Certificate, Client, ClientBuilder, Identity, Url,
+};
+
+use crate::config::config::TlsProfileConfig;
+
+/// DNS resolver of an HTTP client.
+///
+/// A client with an SNI override addresses its requests to the profile's
+/// `server_name`, so that is the name handed to the resolver; it is mapped to
+/// the one upstream host the client is pinned to before the lookup happens.
+/// Every other name, and every name of a client without a route, is looked up
+/// as is.
+#[derive(Debug, Default)]
+pub struct SniResolver {
+    // (server_name, upstream host)
+    route: Option<(String, String)>,
+}
+
+impl SniResolver {
+    /// A resolver sending `server_name` to `host`.
+    pub fn pinned(server_name: &str, host: &str) -> Self {
+        Self {
+            route: Some((server_name.to_string(), host.to_string())),
+        }
+    }
+
+    fn target<'a>(&'a self, name: &'a str) -> &'a str {
+        match &self.route {
+            Some((server_name, host)) if server_name == name => host,
+            _ => name,
+        }
+    }
+}
+
+impl Resolve for SniResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = self.target(name.as_str()).to_string();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect::<Vec<_>>();
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// One `[http_client.tls_profiles.<name>]` entry, ready to build clients from.
+#[derive(Debug, Clone)]
+pub struct TlsProfile {
+    name: String,
+    config: TlsProfileConfig,
+}
+
+impl TlsProfile {
+    pub fn new(name: &str, config: &TlsProfileConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config: config.clone(),
+        }
+    }
+
+    /// The resolver of a client of this profile pinned to `host`, the
+    /// upstream host `route_url` returned.
+    pub fn resolver(&self, host: Option<&str>) -> SniResolver {
+        match (&self.config.server_name, host) {
+            (Some(server_name), Some(host)) => SniResolver::pinned(server_name, host),
+            _ => SniResolver::default(),
+        }
+    }
+
+    /// Returns a client builder with the profile's identity, CA bundles and
+    /// minimum TLS version applied. The caller sets the resolver.
+    pub fn client_builder(&self) -> Result<ClientBuilder> {
+        let name = &self.name;
+        let profile = &self.config;
+        let mut builder = Client::builder();
+
+        for path in &profile.ca_bundles {
+            let pem = fs::read(path)
+                .with_context(|| format!("TLS profile `{}`: failed to read CA bundle {}", name, path))?;
+            for cert in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        match (&profile.client_cert, &profile.client_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let cert = fs::read(cert_path).with_context(|| {
+                    format!("TLS profile `{}`: failed to read client cert {}", name, cert_path)
+                })?;
+                let key = fs::read(key_path).with_context(|| {
+                    format!("TLS profile `{}`: failed to read client key {}", name, key_path)
+                })?;
+                builder = builder.identity(Identity::from_pkcs8_pem(&cert, &key)?);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(anyhow!(
+                    "TLS profile `{}`: client_cert and client_key must be set together",
+                    name
+                ))
+            }
+        }
+
+        if let Some(version) = &profile.min_tls_version {
+            builder = builder.min_tls_version(parse_tls_version(version)?);
+        }
+        Ok(builder)
+    }
+
+    /// Rewrites the request URL for the SNI override, if the profile has one,
+    /// and returns the upstream host the sending client must be pinned to.
+    ///
+    /// The host travels with the request rather than being stored on the
+    /// profile, so concurrent calls to different hosts through one profile
+    /// each reach their own.
+    pub fn route_url(&self, url: &str) -> Result<(Url, Option<String>)> {
+        let mut url = Url::parse(url)?;
+        let Some(server_name) = &self.config.server_name else {
+            return Ok((url, None));
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("URL has no host: {}", url))?
+            .to_string();
+        url.set_host(Some(server_name))?;
+        Ok((url, Some(host)))
+    }
+}
+
+fn parse_tls_version(version: &str) -> Result<Version> {
+    match version {
+        "1.0" => Ok(Version::TLS_1_0),
+        "1.1" => Ok(Version::TLS_1_1),
+        "1.2" => Ok(Version::TLS_1_2),
+        "1.3" => Ok(Version::TLS_1_3),
+        other => Err(anyhow!("Unsupported TLS version: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::{routing::get, Router};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        config::config::HttpClientSection,
+        http_client::{http_client_provider::HttpClientProvider, model::HttpRequestOptions},
+        test_util::serve_on,
+    };
+
+    fn sni_config() -> TlsProfileConfig {
+        TlsProfileConfig {
+            server_name: Some("orders.mesh.internal".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn route_url_returns_the_upstream_host() {
+        let (url, host) = TlsProfile::new("mesh", &sni_config())
+            .route_url("https://10.1.2.3:8443/v1/orders?x=1")
+            .unwrap();
+        assert_eq!(url.as_str(), "https://orders.mesh.internal:8443/v1/orders?x=1");
+        assert_eq!(host.as_deref(), Some("10.1.2.3"));
+
+        let plain = TlsProfile::new("plain", &TlsProfileConfig::default());
+        let (url, host) = plain.route_url("https://api.example.com/x").unwrap();
+        assert_eq!(url.as_str(), "https://api.example.com/x");
+        assert_eq!(host, None);
+    }
+
+    #[test]
+    fn pinned_resolver_only_maps_the_server_name() {
+        let profile = TlsProfile::new("mesh", &sni_config());
+        let resolver = profile.resolver(Some("10.1.2.3"));
+        assert_eq!(resolver.target("orders.mesh.internal"), "10.1.2.3");
+        assert_eq!(resolver.target("proxy.internal"), "proxy.internal");
+        assert_eq!(profile.resolver(None).target("orders.mesh.internal"), "orders.mesh.internal");
+    }
+
+    #[test]
+    fn broken_profiles_are_rejected() {
+        let half_identity = TlsProfileConfig {
+            client_cert: Some("client.pem".to_string()),
+            ..Default::default()
+        };
+        assert!(TlsProfile::new("half", &half_identity).client_builder().is_err());
+
+        let bad_version = TlsProfileConfig {
+            min_tls_version: Some("2.0".to_string()),
+            ..Default::default()
+        };
+        assert!(TlsProfile::new("bad", &bad_version).client_builder().is_err());
+    }
+
+    #[tokio::test]
+    async fn concurrent_calls_through_one_profile_reach_their_own_host() {
+        let a = serve_on("127.0.0.1:0", Router::new().route("/", get(|| async { "a" }))).await;
+        let b = serve_on(
+            &format!("127.0.0.2:{}", a.port()),
+            Router::new().route("/", get(|| async { "b" })),
+        )
+        .await;
+        let provider = HttpClientProvider::new(&HttpClientSection {
+            tls_profiles: HashMap::from([("mesh".to_string(), sni_config())]),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let provider = std::sync::Arc::new(provider);
+
+        let mut calls = tokio::task::JoinSet::new();
+        for i in 0..40 {
+            let (url, expected) = match i % 2 {
+                0 => (format!("http://{}/", a), "a"),
+                _ => (format!("http://{}/", b), "b"),
+            };
+            let provider = provider.clone();
+            calls.spawn(async move {
+                let options = HttpRequestOptions::<Value> {
+                    tls_profile: Some("mesh".to_string()),
+                    ..Default::default()
+                };
+                let (_, body) = provider
+                    .request_uri::<Value, String>(&url, options)
+                    .await
+                    .unwrap();
+                assert_eq!(body, expected, "{} answered by the wrong host", url);
+            });
+        }
+        while let Some(call) = calls.join_next().await {
+            call.unwrap();
+        }
+    }
+}
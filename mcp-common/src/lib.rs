@@ -1,12 +1,28 @@
+#![allow(clippy::module_inception)]
+
+pub mod backend;
 pub mod cache;
+pub mod circuit_breaker;
+pub mod cluster;
 pub mod config;
 pub mod constants;
 pub mod enums;
 pub mod etcd;
+pub mod grpc_client;
 pub mod http_client;
 pub mod log;
+pub mod mcp_client;
 pub mod model;
 pub mod provider;
+pub mod rate_limit;
+pub mod recording;
+pub mod script;
+pub mod sql;
 pub mod sse;
+pub mod transform;
 pub mod xds;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod wasm;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
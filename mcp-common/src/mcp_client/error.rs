@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+/// Upstream MCP failures the tool layer reports back as `isError` results.
+#[derive(Debug, Error)]
+pub enum McpCallError {
+    #[error("Upstream MCP error {code}: {message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("Invalid upstream MCP response: {0}")]
+    Protocol(String),
+}
+
+impl McpCallError {
+    /// Whether the failure points at an unhealthy upstream rather than a bad
+    /// request: an answer that is not valid MCP, or a JSON-RPC internal error.
+    pub fn is_upstream_failure(&self) -> bool {
+        match self {
+            McpCallError::Rpc { code, .. } => *code == -32603,
+            McpCallError::Protocol(_) => true,
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use reqwest::{header::HeaderMap, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+use tracing::{debug, info};
+
+use crate::{
+    constants::constants::mcp_protocol_consts::{PROTOCOL_VERSION, SERVER_NAME, SERVER_VERSION},
+    http_client::{
+        error::HttpCallError,
+        model::{HttpCallPolicy, HttpRequestOptions, ResponseLimits},
+    },
+    mcp_client::error::McpCallError,
+    provider::global_provider::get_http_client,
+};
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+const PROTOCOL_VERSION_HEADER: &str = "MCP-Protocol-Version";
+// Upper bound of `tools/list` pages followed
+const MAX_LIST_PAGES: usize = 100;
+// Lifetime of one GET event stream before it is reopened
+const LISTEN_TIMEOUT_MS: u64 = 24 * 60 * 60 * 1000;
+
+static MCP_CLIENT: Lazy<McpClientProvider> = Lazy::new(McpClientProvider::new);
+
+/// Returns the global client for upstream MCP servers.
+pub fn get_mcp_client() -> &'static McpClientProvider {
+    &MCP_CLIENT
+}
+
+/// Transport settings of the requests sent to one upstream MCP server.
+#[derive(Debug, Clone, Default)]
+pub struct McpCallOptions {
+    // Extra headers sent with every request, e.g. upstream auth
+    pub headers: HashMap<String, String>,
+    pub tls_profile: Option<String>,
+    pub call_policy: Option<HttpCallPolicy>,
+    pub response_limits: Option<ResponseLimits>,
+}
+
+impl McpCallOptions {
+    // servers keep one session per client, so differing auth needs its own
+    fn session_key(&self, url: &str) -> String {
+        let mut headers: Vec<_> = self.headers.iter().collect();
+        headers.sort();
+        format!(
+            "{}\u{0}{}\u{0}{:?}",
+            url,
+            self.tls_profile.as_deref().unwrap_or_default(),
+            headers
+        )
+    }
+}
+
+/// An initialized session with an upstream server.
+#[derive(Debug, Clone)]
+struct McpSession {
+    // `Mcp-Session-Id` assigned by the server, if it tracks sessions
+    id: Option<String>,
+    // Protocol version the server answered `initialize` with
+    protocol_version: String,
+}
+
+/// A Streamable HTTP MCP client.
+///
+/// Sessions are opened with `initialize` on first use and kept per server URL
+/// and headers; concurrent first calls wait for the same `initialize`. A
+/// server that forgot the session (404) gets a fresh one and the request is
+/// sent again once.
+pub struct McpClientProvider {
+    sessions: DashMap<String, Arc<OnceCell<McpSession>>>,
+    next_id: AtomicU64,
+}
+
+impl McpClientProvider {
+    fn new() -> Self {
+        Self {
+            sessions: DashMap::new(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Calls `tools/call` and returns the upstream `CallToolResult` as JSON.
+    pub async fn call_tool(
+        &self,
+        url: &str,
+        tool: &str,
+        arguments: &HashMap<String, Value>,
+        options: &McpCallOptions,
+    ) -> Result<Value> {
+        let params = json!({ "name": tool, "arguments": arguments });
+        self.request(url, "tools/call", params, options).await
+    }
+
+    /// Lists every tool of the server, following `nextCursor`.
+    pub async fn list_tools(&self, url: &str, options: &McpCallOptions) -> Result<Vec<Value>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request(url, "tools/list", params, options).await?;
+            if let Some(page) = result.get("tools").and_then(Value::as_array) {
+                tools.extend(page.iter().cloned());
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+        Err(McpCallError::Protocol(format!(
+            "tools/list exceeded {} pages",
+            MAX_LIST_PAGES
+        ))
+        .into())
+    }
+
+    /// Opens the server's GET event stream and hands the method of every
+    /// notification on it to `on_notification`, until the stream ends.
+    ///
+    /// Servers without a GET stream answer 405, which surfaces as an
+    /// `HttpCallError::Status`.
+    pub async fn listen<F>(
+        &self,
+        url: &str,
+        options: &McpCallOptions,
+        mut on_notification: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let key = options.session_key(url);
+        let session = self.session(&key, url, options).await?;
+        let mut headers = options.headers.clone();
+        headers.insert("Accept".to_string(), "text/event-stream".to_string());
+        if let Some(id) = &session.id {
+            headers.insert(SESSION_HEADER.to_string(), id.clone());
+        }
+        headers.insert(
+            PROTOCOL_VERSION_HEADER.to_string(),
+            session.protocol_version.clone(),
+        );
+        // the stream stays open far longer than any request
+        let call_policy = HttpCallPolicy {
+            timeout_ms: Some(LISTEN_TIMEOUT_MS),
+            max_retries: Some(0),
+            ..options.call_policy.clone().unwrap_or_default()
+        };
+        let request = HttpRequestOptions::<Value> {
+            method: "GET".to_string(),
+            headers: Some(headers),
+            tls_profile: options.tls_profile.clone(),
+            call_policy: Some(call_policy),
+            ..Default::default()
+        };
+        let mut resp = match get_http_client()?.request_stream(url, request).await {
+            Err(e) if is_session_expired(&e) => {
+                self.forget_session(&key, &session);
+                return Err(e);
+            }
+            result => result?,
+        };
+
+        let mut buffer = String::new();
+        while let Some(chunk) = resp.chunk().await? {
+            buffer.push_str(&String::from_utf8_lossy(&chunk).replace("\r\n", "\n"));
+            while let Some(end) = buffer.find("\n\n") {
+                let event: String = buffer.drain(..end + 2).collect();
+                for data in sse_data(&event) {
+                    let Ok(message) = serde_json::from_str::<Value>(&data) else {
+                        continue;
+                    };
+                    if message.get("id").is_none() {
+                        if let Some(method) = message.get("method").and_then(Value::as_str) {
+                            on_notification(method);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends one JSON-RPC request within the server's session and returns its
+    /// `result`.
+    pub async fn request(
+        &self,
+        url: &str,
+        method: &str,
+        params: Value,
+        options: &McpCallOptions,
+    ) -> Result<Value> {
+        let key = options.session_key(url);
+        let session = self.session(&key, url, options).await?;
+        match self.send_request(url, Some(&session), method, &params, options).await {
+            Err(e) if session.id.is_some() && is_session_expired(&e) => {
+                info!("Upstream MCP session expired, reinitializing: {}", url);
+                self.forget_session(&key, &session);
+                let session = self.session(&key, url, options).await?;
+                self.send_request(url, Some(&session), method, &params, options)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    async fn session(&self, key: &str, url: &str, options: &McpCallOptions) -> Result<McpSession> {
+        let cell = self.sessions.entry(key.to_string()).or_default().clone();
+        // a failed initialize leaves the cell empty for the next call to retry
+        cell.get_or_try_init(|| self.initialize(url, options))
+            .await
+            .cloned()
+    }
+
+    /// Drops an expired session, unless a concurrent call already replaced it.
+    fn forget_session(&self, key: &str, expired: &McpSession) {
+        self.sessions.remove_if(key, |_, cell| {
+            cell.get().is_some_and(|session| session.id == expired.id)
+        });
+    }
+
+    async fn initialize(&self, url: &str, options: &McpCallOptions) -> Result<McpSession> {
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": SERVER_NAME, "version": SERVER_VERSION },
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": "initialize", "params": params });
+        let (_, headers, body) = self.post(url, None, &message, options).await?;
+        let result = find_response(&headers, &body, id)?;
+        let session = McpSession {
+            id: headers
+                .get(SESSION_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            protocol_version: result
+                .get("protocolVersion")
+                .and_then(Value::as_str)
+                .unwrap_or(PROTOCOL_VERSION)
+                .to_string(),
+        };
+        debug!(
+            "Initialized upstream MCP session {:?} ({}): {}",
+            session.id, session.protocol_version, url
+        );
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        self.post(url, Some(&session), &initialized, options).await?;
+        Ok(session)
+    }
+
+    async fn send_request(
+        &self,
+        url: &str,
+        session: Option<&McpSession>,
+        method: &str,
+        params: &Value,
+        options: &McpCallOptions,
+    ) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let (_, headers, body) = self.post(url, session, &message, options).await?;
+        find_response(&headers, &body, id)
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        session: Option<&McpSession>,
+        message: &Value,
+        options: &McpCallOptions,
+    ) -> Result<(StatusCode, HeaderMap, String)> {
+        let mut headers = options.headers.clone();
+        headers.insert(
+            "Accept".to_string(),
+            "application/json, text/event-stream".to_string(),
+        );
+        if let Some(session) = session {
+            if let Some(id) = &session.id {
+                headers.insert(SESSION_HEADER.to_string(), id.clone());
+            }
+            headers.insert(
+                PROTOCOL_VERSION_HEADER.to_string(),
+                session.protocol_version.clone(),
+            );
+        }
+        let request = HttpRequestOptions {
+            method: "POST".to_string(),
+            headers: Some(headers),
+            body: Some(message.clone()),
+            tls_profile: options.tls_profile.clone(),
+            call_policy: options.call_policy.clone(),
+            response_limits: options.response_limits.clone(),
+            ..Default::default()
+        };
+        get_http_client()?
+            .request_with_headers::<Value, String>(url, request)
+            .await
+    }
+}
+
+fn is_session_expired(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<HttpCallError>(),
+        Some(HttpCallError::Status { status, .. }) if *status == StatusCode::NOT_FOUND
+    )
+}
+
+/// Picks the JSON-RPC response with `id` out of a JSON body (single message
+/// or batch) or an SSE stream, skipping the server's notifications.
+fn find_response(headers: &HeaderMap, body: &str, id: u64) -> Result<Value> {
+    let is_sse = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    let messages: Vec<Value> = if is_sse {
+        sse_data(body)
+            .iter()
+            .filter_map(|data| serde_json::from_str(data).ok())
+            .collect()
+    } else {
+        match serde_json::from_str(body) {
+            Ok(Value::Array(batch)) => batch,
+            Ok(message) => vec![message],
+            Err(e) => return Err(McpCallError::Protocol(e.to_string()).into()),
+        }
+    };
+
+    let response = messages
+        .into_iter()
+        .find(|m| m.get("id").and_then(Value::as_u64) == Some(id))
+        .ok_or_else(|| McpCallError::Protocol(format!("no response to request {}", id)))?;
+    if let Some(error) = response.get("error") {
+        return Err(McpCallError::Rpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or_default(),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string(),
+        }
+        .into());
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+/// The `data` of each event in an SSE stream.
+fn sse_data(body: &str) -> Vec<String> {
+    let mut events = Vec::new();
+    let mut data: Vec<&str> = Vec::new();
+    for line in body.lines() {
+        if line.is_empty() {
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+                data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    if !data.is_empty() {
+        events.push(data.join("\n"));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+
+    use super::*;
+    use crate::test_util::{init_test_config, serve};
+
+    /// An MCP server answering `initialize` slowly and `ping` right away.
+    async fn server(initializes: Arc<AtomicUsize>) -> String {
+        let router = Router::new().route(
+            "/mcp",
+            post(move |Json(message): Json<Value>| {
+                let initializes = initializes.clone();
+                async move {
+                    let Some(id) = message.get("id").cloned() else {
+                        return StatusCode::ACCEPTED.into_response();
+                    };
+                    if message["method"] == "initialize" {
+                        initializes.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    }
+                    let result = json!({ "protocolVersion": PROTOCOL_VERSION });
+                    (
+                        [(SESSION_HEADER, "s-1")],
+                        Json(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+                    )
+                        .into_response()
+                }
+            }),
+        );
+        format!("http://{}/mcp", serve(router).await)
+    }
+
+    #[tokio::test]
+    async fn concurrent_first_calls_share_one_initialize() {
+        init_test_config();
+        let initializes = Arc::new(AtomicUsize::new(0));
+        let url = server(initializes.clone()).await;
+        let client = Arc::new(McpClientProvider::new());
+
+        let mut calls = tokio::task::JoinSet::new();
+        for _ in 0..8 {
+            let (client, url) = (client.clone(), url.clone());
+            calls.spawn(async move {
+                client
+                    .request(&url, "ping", json!({}), &McpCallOptions::default())
+                    .await
+            });
+        }
+        while let Some(result) = calls.join_next().await {
+            assert!(result.unwrap().is_ok());
+        }
+        assert_eq!(initializes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn a_replaced_session_is_not_forgotten() {
+        let client = McpClientProvider::new();
+        let session = |id: &str| McpSession {
+            id: Some(id.to_string()),
+            protocol_version: PROTOCOL_VERSION.to_string(),
+        };
+        let cell = Arc::new(OnceCell::new_with(Some(session("new"))));
+        client.sessions.insert("key".to_string(), cell);
+
+        client.forget_session("key", &session("old"));
+        assert!(client.sessions.contains_key("key"));
+        client.forget_session("key", &session("new"));
+        assert!(!client.sessions.contains_key("key"));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod error;
+pub mod mcp_client_provider;
@@ -46,7 +46,8 @@ pub fn get_mysql_pool() -> Arc<MySqlPool> {
 }
 
 pub fn init_http_client() -> Result<()> {
-    let client = Arc::new(HttpClientProvider::new()?);
+    let config = get_app_config()?;
+    let client = Arc::new(HttpClientProvider::from_config(&config)?);
     HTTP_CLIENT
         .set(client)
         .map_err(|_| anyhow!("HTTP client already initialized"))?;
@@ -55,10 +56,11 @@ pub fn init_http_client() -> Result<()> {
 pub fn get_http_client() -> Result<Arc<HttpClientProvider>> {
     HTTP_CLIENT
         .get_or_try_init(|| {
-            let client = HttpClientProvider::new()?;
+            let config = get_app_config()?;
+            let client = HttpClientProvider::from_config(&config)?;
             Ok(Arc::new(client))
         })
-        .map(|arc| arc.clone())
+        .cloned()
 }
 
 pub fn init_app_config() -> Result<()> {
@@ -69,11 +71,19 @@ pub fn init_app_config() -> Result<()> {
     Ok(())
 }
 
+/// Installs an already loaded config, e.g. one built in tests.
+pub fn set_app_config(config: AppConfig) -> Result<()> {
+    CONFIG
+        .set(Arc::new(config))
+        .map_err(|_| anyhow!("AppConfig already initialized"))?;
+    Ok(())
+}
+
 pub fn get_app_config() -> Result<Arc<AppConfig>> {
     CONFIG
         .get_or_try_init(|| {
             let config = AppConfig::load_from_env()?;
             Ok(Arc::new(config))
         })
-        .map(|arc| arc.clone())
+        .cloned()
 }
@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+use tracing::info;
+
+static RATE_LIMITERS: Lazy<RateLimiterRegistry> = Lazy::new(RateLimiterRegistry::new);
+
+/// Returns the global limiter registry.
+pub fn get_rate_limiters() -> &'static RateLimiterRegistry {
+    &RATE_LIMITERS
+}
+
+/// One set of limits: in-flight calls and a token bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LimitConfig {
+    // Concurrent upstream calls allowed
+    pub max_in_flight: Option<usize>,
+    // Token bucket refill rate; calls beyond it wait or are rejected
+    pub rate_per_sec: Option<f64>,
+    // Bucket size, i.e. how many calls may burst at once (defaults to 1s of rate)
+    pub burst: Option<f64>,
+}
+
+impl LimitConfig {
+    fn validate(&self) -> Result<()> {
+        if self.max_in_flight == Some(0) {
+            return Err(anyhow!("max_in_flight must be greater than zero"));
+        }
+        if self.rate_per_sec.is_some_and(|r| r <= 0.0) || self.burst.is_some_and(|b| b < 1.0) {
+            return Err(anyhow!("rate_per_sec must be positive and burst at least 1"));
+        }
+        Ok(())
+    }
+
+    /// The stricter of two limits, field by field. A rate's burst defaults to
+    /// one second of that rate, as in `Limiter::new`.
+    fn strictest(&self, other: &LimitConfig) -> LimitConfig {
+        fn min<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+                (a, b) => a.or(b),
+            }
+        }
+        let burst = |limits: &LimitConfig| {
+            limits
+                .rate_per_sec
+                .map(|rate| limits.burst.unwrap_or(rate.max(1.0)))
+        };
+        LimitConfig {
+            max_in_flight: min(self.max_in_flight, other.max_in_flight),
+            rate_per_sec: min(self.rate_per_sec, other.rate_per_sec),
+            burst: min(burst(self), burst(other)),
+        }
+    }
+}
+
+/// Outbound limits of a TDS.
+///
+/// `tool` limits this tool alone; `domain` is one limiter shared by every tool
+/// calling the same upstream domain. When those tools declare different
+/// `domain` limits, the strictest of each limit applies to all of them. Without
+/// `queue_timeout_ms` a call over the limit is rejected at once, otherwise it
+/// waits up to that long for a slot.
+///
+/// ```json
+/// {
+///   "tool": { "max_in_flight": 4, "rate_per_sec": 5, "burst": 10 },
+///   "domain": { "max_in_flight": 16 },
+///   "queue_timeout_ms": 2000
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub tool: Option<LimitConfig>,
+    pub domain: Option<LimitConfig>,
+    pub queue_timeout_ms: Option<u64>,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(tool) = &self.tool {
+            tool.validate().map_err(|e| anyhow!("tool: {}", e))?;
+        }
+        if let Some(domain) = &self.domain {
+            domain.validate().map_err(|e| anyhow!("domain: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitError {
+    #[error("Too many concurrent calls to {0} (limit {1}), call rejected")]
+    Concurrency(String, usize),
+
+    #[error("Rate limit of {0} exceeded ({1}/s), call rejected")]
+    Rate(String, f64),
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Takes a token, or returns how long until one is available.
+    fn try_take(&mut self) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Limiter {
+    // "tool `x`" or "upstream `y`", used in error messages
+    label: String,
+    config: LimitConfig,
+    semaphore: Option<Arc<Semaphore>>,
+    bucket: Option<Mutex<TokenBucket>>,
+}
+
+impl Limiter {
+    pub fn new(label: &str, config: LimitConfig) -> Self {
+        let semaphore = config.max_in_flight.map(|n| Arc::new(Semaphore::new(n)));
+        let bucket = config.rate_per_sec.map(|rate| {
+            let capacity = config.burst.unwrap_or(rate.max(1.0));
+            Mutex::new(TokenBucket {
+                capacity,
+                tokens: capacity,
+                refill_per_sec: rate,
+                last_refill: Instant::now(),
+            })
+        });
+        Self {
+            label: label.to_string(),
+            config,
+            semaphore,
+            bucket,
+        }
+    }
+
+    /// Waits until `deadline` (or not at all) for an in-flight slot and a
+    /// rate token. The returned permit holds the slot until dropped.
+    pub async fn acquire(
+        &self,
+        deadline: Option<Instant>,
+    ) -> std::result::Result<Option<OwnedSemaphorePermit>, RateLimitError> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => {
+                let limit = self.config.max_in_flight.unwrap_or_default();
+                let rejected = || RateLimitError::Concurrency(self.label.clone(), limit);
+                let permit = match deadline {
+                    // the semaphore is never closed, so acquiring only fails on timeout
+                    Some(deadline) => {
+                        tokio::time::timeout_at(deadline, semaphore.clone().acquire_owned())
+                            .await
+                            .ok()
+                            .and_then(|permit| permit.ok())
+                    }
+                    None => semaphore.clone().try_acquire_owned().ok(),
+                };
+                Some(permit.ok_or_else(rejected)?)
+            }
+            None => None,
+        };
+
+        if let Some(bucket) = &self.bucket {
+            loop {
+                let wait = match bucket.lock().unwrap().try_take() {
+                    Ok(()) => break,
+                    Err(wait) => wait,
+                };
+                if deadline.is_none_or(|deadline| Instant::now() + wait > deadline) {
+                    let rate = self.config.rate_per_sec.unwrap_or_default();
+                    return Err(RateLimitError::Rate(self.label.clone(), rate));
+                }
+                tokio::time::sleep(wait).await;
+            }
+        }
+        Ok(permit)
+    }
+}
+
+/// Limiters keyed by tool id and by upstream domain, created on first call.
+pub struct RateLimiterRegistry {
+    limiters: DashMap<String, Arc<Limiter>>,
+    // upstream domain -> tool id -> the `domain` limits the tool declares
+    domain_limits: DashMap<String, HashMap<String, LimitConfig>>,
+}
+
+impl RateLimiterRegistry {
+    fn new() -> Self {
+        Self {
+            limiters: DashMap::new(),
+            domain_limits: DashMap::new(),
+        }
+    }
+
+    /// Records the `domain` limits a tool declares for its upstream, replacing
+    /// what it declared before; called when its TDS is loaded or changed.
+    pub fn declare_tool(&self, tool_id: &str, domain: &str, config: Option<&RateLimitConfig>) {
+        self.forget_tool(tool_id);
+        if let Some(limits) = config.and_then(|config| config.domain.as_ref()) {
+            self.domain_limits
+                .entry(domain.to_string())
+                .or_default()
+                .insert(tool_id.to_string(), limits.clone());
+        }
+    }
+
+    /// Drops the `domain` limits a deleted tool declared.
+    pub fn forget_tool(&self, tool_id: &str) {
+        self.domain_limits.retain(|_, declared| {
+            declared.remove(tool_id);
+            !declared.is_empty()
+        });
+    }
+
+    /// The strictest `domain` limits declared for `domain`, counting the
+    /// caller's own. A caller that was never declared (e.g. a workflow step
+    /// calling another domain) is declared now, so the result only changes
+    /// when declarations do.
+    fn domain_limits(&self, tool_id: &str, domain: &str, own: &LimitConfig) -> LimitConfig {
+        let mut declared = self.domain_limits.entry(domain.to_string()).or_default();
+        if declared.get(tool_id) != Some(own) {
+            declared.insert(tool_id.to_string(), own.clone());
+        }
+        declared
+            .values()
+            .fold(own.clone(), |strictest, limits| strictest.strictest(limits))
+    }
+
+    /// Returns the limiter for `key`, rebuilding it when `config` changed.
+    pub fn get(&self, key: &str, label: &str, config: &LimitConfig) -> Arc<Limiter> {
+        if let Some(limiter) = self.limiters.get(key).map(|v| v.value().clone()) {
+            if &limiter.config == config {
+                return limiter;
+            }
+            info!("Limits for {} changed, rebuilding limiter", label);
+        }
+        let limiter = Arc::new(Limiter::new(label, config.clone()));
+        self.limiters.insert(key.to_string(), limiter.clone());
+        limiter
+    }
+
+    /// Acquires the tool and domain limits of a TDS, in that order.
+    pub async fn acquire(
+        &self,
+        tool_id: &str,
+        domain: &str,
+        config: &RateLimitConfig,
+    ) -> std::result::Result<Vec<OwnedSemaphorePermit>, RateLimitError> {
+        let deadline = config
+            .queue_timeout_ms
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        let mut permits = Vec::new();
+        if let Some(tool) = &config.tool {
+            let limiter = self.get(
+                &format!("tool:{}", tool_id),
+                &format!("tool `{}`", tool_id),
+                tool,
+            );
+            permits.extend(limiter.acquire(deadline).await?);
+        }
+        if let Some(own) = &config.domain {
+            let limits = self.domain_limits(tool_id, domain, own);
+            let limiter = self.get(
+                &format!("domain:{}", domain),
+                &format!("upstream `{}`", domain),
+                &limits,
+            );
+            permits.extend(limiter.acquire(deadline).await?);
+        }
+        Ok(permits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_in_flight: usize, rate_per_sec: Option<f64>) -> RateLimitConfig {
+        RateLimitConfig {
+            tool: None,
+            domain: Some(LimitConfig {
+                max_in_flight: Some(max_in_flight),
+                rate_per_sec,
+                burst: None,
+            }),
+            queue_timeout_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn the_strictest_domain_limits_apply_to_every_tool() {
+        for (first, second) in [("a", "b"), ("b", "a")] {
+            let registry = RateLimiterRegistry::new();
+            let config = |tool: &str| match tool {
+                "a" => limits(1, None),
+                _ => limits(2, None),
+            };
+            registry.declare_tool(first, "api.test", Some(&config(first)));
+            registry.declare_tool(second, "api.test", Some(&config(second)));
+            let _held = registry.acquire("b", "api.test", &config("b")).await.unwrap();
+            // `b` allows two, but `a` declares one for the same domain
+            assert!(matches!(
+                registry.acquire("b", "api.test", &config("b")).await,
+                Err(RateLimitError::Concurrency(_, 1))
+            ));
+            assert!(matches!(
+                registry.acquire("a", "api.test", &config("a")).await,
+                Err(RateLimitError::Concurrency(_, 1))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn forgotten_tools_no_longer_tighten_the_domain() {
+        let registry = RateLimiterRegistry::new();
+        registry.declare_tool("a", "api.test", Some(&limits(1, None)));
+        registry.declare_tool("b", "api.test", Some(&limits(2, None)));
+        registry.forget_tool("a");
+        let _held = registry.acquire("b", "api.test", &limits(2, None)).await.unwrap();
+        assert!(registry.acquire("b", "api.test", &limits(2, None)).await.is_ok());
+    }
+
+    #[test]
+    fn the_strictest_limits_are_taken_field_by_field() {
+        let a = LimitConfig {
+            max_in_flight: Some(4),
+            rate_per_sec: Some(10.0),
+            burst: None,
+        };
+        let b = LimitConfig {
+            max_in_flight: None,
+            rate_per_sec: Some(20.0),
+            burst: Some(2.0),
+        };
+        assert_eq!(
+            a.strictest(&b),
+            LimitConfig {
+                max_in_flight: Some(4),
+                rate_per_sec: Some(10.0),
+                burst: Some(2.0),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn tools_agreeing_on_domain_limits_share_them() {
+        let registry = RateLimiterRegistry::new();
+        let _held = registry.acquire("a", "api.test", &limits(1, None)).await.unwrap();
+        assert!(registry.acquire("b", "api.test", &limits(1, None)).await.is_err());
+        assert!(registry.acquire("c", "other.test", &limits(1, None)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn the_token_bucket_rejects_bursts_without_a_queue() {
+        let registry = RateLimiterRegistry::new();
+        let config = limits(10, Some(1.0));
+        registry.acquire("a", "api.test", &config).await.unwrap();
+        assert!(matches!(
+            registry.acquire("a", "api.test", &config).await,
+            Err(RateLimitError::Rate(..))
+        ));
+    }
+}
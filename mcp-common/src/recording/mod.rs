@@ -0,0 +1 @@
+pub mod recorder;
\ No newline at end of file
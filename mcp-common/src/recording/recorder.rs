@@ -0,0 +1,419 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+use crate::{
+    http_client::{
+        error::HttpCallError,
+        model::{HttpRequestOptions, LimitedText},
+    },
+    provider::global_provider::get_app_config,
+};
+
+const REDACTED: &str = "[REDACTED]";
+
+static RECORDER: OnceCell<Recorder> = OnceCell::new();
+
+/// Returns the global recorder, built from `[recording]` on first use.
+pub fn get_recorder() -> Result<&'static Recorder> {
+    RECORDER.get_or_try_init(|| {
+        let config = get_app_config()?;
+        Ok(Recorder::new(config.recording.clone()))
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingMode {
+    // upstream calls go out as usual
+    #[default]
+    Off,
+    // upstream calls go out and are written to the cassettes
+    Record,
+    // upstream calls are answered from the cassettes, never sent
+    Replay,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub mode: RecordingMode,
+    // Directory of the cassettes, one JSON file per TDS
+    pub cassette_dir: String,
+    // Request headers whose values are never written
+    pub redact_headers: Vec<String>,
+    // JSON fields (request and response bodies) and query parameters whose
+    // values are never written
+    pub redact_fields: Vec<String>,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            mode: RecordingMode::Off,
+            cassette_dir: "cassettes".to_string(),
+            redact_headers: ["authorization", "cookie", "proxy-authorization", "x-api-key"]
+                .map(String::from)
+                .to_vec(),
+            redact_fields: [
+                "password",
+                "secret",
+                "token",
+                "access_token",
+                "refresh_token",
+                "api_key",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// A rendered upstream request, redacted.
+///
+/// Replay matches on method, URL and body; headers are kept for reference
+/// only, as they often carry per-call values such as signatures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+impl RecordedRequest {
+    fn matches(&self, other: &RecordedRequest) -> bool {
+        self.method.eq_ignore_ascii_case(&other.method)
+            && self.url == other.url
+            && self.body == other.body
+    }
+}
+
+/// An upstream answer: a response or an error status, redacted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub body: String,
+    #[serde(default)]
+    pub truncated: bool,
+    // pagination note of the call, if any
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl RecordedResponse {
+    /// The recorded answer in the shape of a live call's result.
+    pub fn to_call_result(&self) -> Result<(StatusCode, LimitedText, Option<String>)> {
+        let status = StatusCode::from_u16(self.status)?;
+        if status.is_client_error() || status.is_server_error() {
+            return Err(HttpCallError::Status {
+                status,
+                body: self.body.clone(),
+            }
+            .into());
+        }
+        Ok((
+            status,
+            LimitedText {
+                text: self.body.clone(),
+                truncated: self.truncated,
+            },
+            self.note.clone(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+    recorded_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cassette {
+    tool_id: String,
+    interactions: Vec<Interaction>,
+}
+
+/// Records upstream HTTP traffic of tools to cassette files and replays it.
+///
+/// Only http and graphql calls (including the inline http steps of
+/// workflows) are recorded. In replay mode the HTTP client refuses every
+/// request and gRPC, SQL and MCP tools are refused, so nothing reaches the
+/// network.
+///
+/// Cassettes are JSON files named after the TDS id and a hash of it, loaded
+/// on first use and rewritten whole through a temporary file; a new
+/// recording of the same request replaces the old one. Listed headers, JSON
+/// fields and query parameters are redacted before anything is written, and
+/// replayed requests are redacted the same way before they are matched.
+pub struct Recorder {
+    config: RecordingConfig,
+    cassettes: DashMap<String, Arc<Mutex<Cassette>>>,
+}
+
+impl Recorder {
+    fn new(config: RecordingConfig) -> Self {
+        if config.mode != RecordingMode::Off {
+            info!(
+                "Recording mode {:?}, cassettes in {}",
+                config.mode, config.cassette_dir
+            );
+        }
+        Self {
+            config,
+            cassettes: DashMap::new(),
+        }
+    }
+
+    pub fn mode(&self) -> RecordingMode {
+        self.config.mode
+    }
+
+    /// The redacted form of a request about to be sent to `url`.
+    pub fn request(&self, url: &str, options: &HttpRequestOptions<Value>) -> RecordedRequest {
+        let headers = options
+            .headers
+            .iter()
+            .flatten()
+            .map(|(name, value)| {
+                let redact = self
+                    .config
+                    .redact_headers
+                    .iter()
+                    .any(|header| header.eq_ignore_ascii_case(name));
+                let value = if redact { REDACTED } else { value.as_str() };
+                (name.to_lowercase(), value.to_string())
+            })
+            .collect();
+        RecordedRequest {
+            method: options.method.to_uppercase(),
+            url: self.redact_url(url),
+            headers,
+            body: options.body.as_ref().map(|body| self.redact_json(body.clone())),
+        }
+    }
+
+    /// Writes a call's outcome to the tool's cassette. Failures other than
+    /// error statuses (timeouts, transport errors) are not recorded.
+    pub async fn record(
+        &self,
+        tool_id: &str,
+        request: RecordedRequest,
+        call_result: &Result<(StatusCode, LimitedText, Option<String>)>,
+    ) -> Result<()> {
+        let response = match call_result {
+            Ok((status, body, note)) => RecordedResponse {
+                status: status.as_u16(),
+                body: self.redact_body(&body.text),
+                truncated: body.truncated,
+                note: note.clone(),
+            },
+            Err(e) => match e.downcast_ref::<HttpCallError>() {
+                Some(HttpCallError::Status { status, body }) => RecordedResponse {
+                    status: status.as_u16(),
+                    body: self.redact_body(body),
+                    truncated: false,
+                    note: None,
+                },
+                _ => return Ok(()),
+            },
+        };
+        let cassette = self.cassette(tool_id).await?;
+        let mut cassette = cassette.lock().await;
+        cassette
+            .interactions
+            .retain(|interaction| !interaction.request.matches(&request));
+        cassette.interactions.push(Interaction {
+            request,
+            response,
+            recorded_at: Utc::now().to_rfc3339(),
+        });
+        let path = self.cassette_path(tool_id);
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // a crash mid-write must not leave a truncated cassette behind
+        let temp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&temp_path, serde_json::to_vec_pretty(&*cassette)?)
+            .await
+            .with_context(|| format!("Failed to write cassette {}", temp_path.display()))?;
+        tokio::fs::rename(&temp_path, &path)
+            .await
+            .with_context(|| format!("Failed to write cassette {}", path.display()))?;
+        debug!("Recorded upstream call of {} to {}", tool_id, path.display());
+        Ok(())
+    }
+
+    /// The recorded answer to a request, if the tool's cassette has one.
+    pub async fn replay(
+        &self,
+        tool_id: &str,
+        request: &RecordedRequest,
+    ) -> Result<Option<RecordedResponse>> {
+        let cassette = self.cassette(tool_id).await?;
+        let cassette = cassette.lock().await;
+        Ok(cassette
+            .interactions
+            .iter()
+            .rev()
+            .find(|interaction| interaction.request.matches(request))
+            .map(|interaction| interaction.response.clone()))
+    }
+
+    async fn cassette(&self, tool_id: &str) -> Result<Arc<Mutex<Cassette>>> {
+        if let Some(cassette) = self.cassettes.get(tool_id) {
+            return Ok(cassette.value().clone());
+        }
+        let path = self.cassette_path(tool_id);
+        let cassette = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow!("Invalid cassette {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Cassette {
+                tool_id: tool_id.to_string(),
+                interactions: Vec::new(),
+            },
+            Err(e) => return Err(anyhow!("Failed to read cassette {}: {}", path.display(), e)),
+        };
+        // a concurrent first use may have loaded it already
+        Ok(self
+            .cassettes
+            .entry(tool_id.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(cassette)))
+            .value()
+            .clone())
+    }
+
+    /// `<cassette_dir>/<readable id>-<hash>.json`: the hash keeps ids that
+    /// read alike once sanitized (`a/b`, `a_b`) apart.
+    fn cassette_path(&self, tool_id: &str) -> PathBuf {
+        let readable = tool_id
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect::<String>();
+        let hash = hex::encode(&Sha256::digest(tool_id.as_bytes())[..6]);
+        PathBuf::from(&self.config.cassette_dir).join(format!("{}-{}.json", readable, hash))
+    }
+
+    fn is_redacted_field(&self, name: &str) -> bool {
+        self.config
+            .redact_fields
+            .iter()
+            .any(|field| field.eq_ignore_ascii_case(name))
+    }
+
+    /// Redacts the query of a URL and sorts its parameters, as tools
+    /// render them in no fixed order.
+    fn redact_url(&self, url: &str) -> String {
+        let Ok(mut parsed) = Url::parse(url) else {
+            return url.to_string();
+        };
+        if parsed.query().is_none() {
+            return url.to_string();
+        }
+        let mut pairs = parsed
+            .query_pairs()
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(pairs.iter().map(|(name, value)| {
+            let value = if self.is_redacted_field(name) { REDACTED } else { value.as_str() };
+            (name.as_str(), value)
+        }));
+        parsed.to_string()
+    }
+
+    fn redact_json(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(name, value)| match self.is_redacted_field(&name) {
+                        true => (name, Value::String(REDACTED.to_string())),
+                        false => (name, self.redact_json(value)),
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.redact_json(item)).collect())
+            }
+            other => other,
+        }
+    }
+
+    /// Redacts a JSON body; other bodies, and JSON without redacted fields,
+    /// are kept as they are.
+    fn redact_body(&self, body: &str) -> String {
+        let Ok(json) = serde_json::from_str::<Value>(body) else {
+            return body.to_string();
+        };
+        let redacted = self.redact_json(json.clone());
+        match redacted == json {
+            true => body.to_string(),
+            false => redacted.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    fn recorder(dir: &str) -> Recorder {
+        Recorder::new(RecordingConfig {
+            mode: RecordingMode::Record,
+            cassette_dir: test_dir().join(dir).display().to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn ids_that_sanitize_alike_get_their_own_cassettes() {
+        let recorder = recorder("cassettes-names");
+        assert_ne!(recorder.cassette_path("a/b"), recorder.cassette_path("a_b"));
+        assert_eq!(recorder.cassette_path("a/b"), recorder.cassette_path("a/b"));
+    }
+
+    #[tokio::test]
+    async fn recordings_are_written_whole_and_replayed_from_disk() {
+        let writer = recorder("cassettes-atomic");
+        let options = HttpRequestOptions::<Value>::default();
+        let request = writer.request("http://upstream/items?b=2&a=1", &options);
+        let body = LimitedText {
+            text: "ok".to_string(),
+            truncated: false,
+        };
+        writer
+            .record("tools/items", request.clone(), &Ok((StatusCode::OK, body, None)))
+            .await
+            .unwrap();
+
+        let path = writer.cassette_path("tools/items");
+        assert!(path.exists());
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // a fresh recorder loads the cassette from the file
+        let response = recorder("cassettes-atomic")
+            .replay("tools/items", &request)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((response.status, response.body.as_str()), (200, "ok"));
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use rhai::{
+    packages::{Package, StandardPackage},
+    serde::{from_dynamic, to_dynamic},
+    Dynamic, Engine, EvalAltResult, Module, Scope, Shared, AST,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::constants::constants::script_consts::{
+    DEFAULT_SCRIPT_MAX_OPERATIONS, DEFAULT_SCRIPT_TIMEOUT_MS, MAX_SCRIPT_OPERATIONS,
+    MAX_SCRIPT_TIMEOUT_MS, SCRIPT_MAX_CALL_LEVELS, SCRIPT_MAX_COLLECTION_SIZE,
+    SCRIPT_MAX_STRING_SIZE,
+};
+
+static SCRIPT_ENGINE: Lazy<ScriptEngine> = Lazy::new(ScriptEngine::new);
+
+// Rhai's standard library, built once and shared by every engine
+static STANDARD_PACKAGE: Lazy<Shared<Module>> =
+    Lazy::new(|| StandardPackage::new().as_shared_module());
+
+/// Returns the global registry of compiled tool scripts.
+pub fn get_script_engine() -> &'static ScriptEngine {
+    &SCRIPT_ENGINE
+}
+
+/// Rhai hooks of a TDS.
+///
+/// `pre_request` (http backends only) sees `args` and the rendered `request`
+/// (`method`, `url`, `headers`, `body`); it may edit `request` in place or
+/// return a new one, but not move it to another scheme, host or port.
+/// `post_response` runs before `response_transform` and sees `args`,
+/// `status`, the raw `body`, its parse as `json` (`()` if not JSON) and
+/// `is_error`; it may return the new result text (a map or array is sent as
+/// JSON), reassign `body`, or assign a bool to `is_error`. An error status
+/// stays an error unless the script clears `is_error`.
+///
+/// Besides the standard library, scripts may call `sha256_hex`,
+/// `hmac_sha256_hex`, `hmac_sha256_base64`, `base64_encode`, `base64_decode`,
+/// `json_parse`, `json_encode`, `now_unix` and `now_unix_ms`. They have no
+/// I/O, run on the blocking pool, and each run is cut off after
+/// `max_operations` (at most 10000000) or `timeout_ms` (at most 1000).
+///
+/// ```json
+/// {
+///   "pre_request": "let ts = now_unix().to_string(); request.headers[\"X-Ts\"] = ts; request.headers[\"X-Sig\"] = hmac_sha256_hex(\"secret\", ts + request.url);",
+///   "post_response": "if status == 404 { is_error = false; \"not found\" } else { json.data }",
+///   "max_operations": 50000,
+///   "timeout_ms": 20
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolScripts {
+    #[serde(default)]
+    pub pre_request: Option<String>,
+    #[serde(default)]
+    pub post_response: Option<String>,
+    // Operations one script run may take, default 100000
+    #[serde(default)]
+    pub max_operations: Option<u64>,
+    // Wall-clock time one script run may take, default 100ms
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl ToolScripts {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_operations == Some(0) || self.timeout_ms == Some(0) {
+            return Err(anyhow!("max_operations and timeout_ms must be greater than zero"));
+        }
+        if self.max_operations.is_some_and(|ops| ops > MAX_SCRIPT_OPERATIONS) {
+            return Err(anyhow!("max_operations must be at most {}", MAX_SCRIPT_OPERATIONS));
+        }
+        if self.timeout_ms.is_some_and(|ms| ms > MAX_SCRIPT_TIMEOUT_MS) {
+            return Err(anyhow!("timeout_ms must be at most {}", MAX_SCRIPT_TIMEOUT_MS));
+        }
+        CompiledScripts::compile(self).map(|_| ())
+    }
+
+    // clamped again here, for scripts that reach the engine unvalidated
+    fn max_operations(&self) -> u64 {
+        self.max_operations
+            .unwrap_or(DEFAULT_SCRIPT_MAX_OPERATIONS)
+            .min(MAX_SCRIPT_OPERATIONS)
+    }
+
+    fn timeout(&self) -> Duration {
+        let timeout_ms = self.timeout_ms.unwrap_or(DEFAULT_SCRIPT_TIMEOUT_MS);
+        Duration::from_millis(timeout_ms.min(MAX_SCRIPT_TIMEOUT_MS))
+    }
+}
+
+/// The HTTP request a `pre_request` script works on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+/// What a `post_response` script made of the upstream response.
+#[derive(Debug, Clone)]
+pub struct ScriptResponse {
+    pub text: String,
+    pub is_error: bool,
+}
+
+/// The parsed scripts of one TDS, along with the sources they came from.
+struct CompiledScripts {
+    source: ToolScripts,
+    pre_request: Option<AST>,
+    post_response: Option<AST>,
+}
+
+impl CompiledScripts {
+    fn compile(scripts: &ToolScripts) -> Result<Self> {
+        let engine = new_engine(scripts);
+        let compile = |name: &str, script: &Option<String>| {
+            script
+                .as_ref()
+                .map(|script| engine.compile(script))
+                .transpose()
+                .map_err(|e| anyhow!("{} script does not compile: {}", name, e))
+        };
+        Ok(Self {
+            pre_request: compile("pre_request", &scripts.pre_request)?,
+            post_response: compile("post_response", &scripts.post_response)?,
+            source: scripts.clone(),
+        })
+    }
+}
+
+/// Compiled tool scripts by TDS id.
+///
+/// Scripts are compiled when their TDS enters the `McpCache`; a run with
+/// sources that differ from the cached ones compiles them afresh.
+pub struct ScriptEngine {
+    compiled: DashMap<String, Arc<CompiledScripts>>,
+}
+
+impl ScriptEngine {
+    fn new() -> Self {
+        Self {
+            compiled: DashMap::new(),
+        }
+    }
+
+    /// Compiles and caches the scripts of a TDS.
+    pub fn compile(&self, tds_id: &str, scripts: &ToolScripts) -> Result<()> {
+        let compiled = CompiledScripts::compile(scripts)?;
+        self.compiled.insert(tds_id.to_string(), Arc::new(compiled));
+        debug!("Compiled scripts of TDS: {}", tds_id);
+        Ok(())
+    }
+
+    pub fn remove(&self, tds_id: &str) {
+        self.compiled.remove(tds_id);
+    }
+
+    fn compiled(&self, tds_id: &str, scripts: &ToolScripts) -> Result<Arc<CompiledScripts>> {
+        let cached = self.compiled.get(tds_id).map(|c| c.value().clone());
+        if let Some(compiled) = cached.filter(|c| &c.source == scripts) {
+            return Ok(compiled);
+        }
+        self.compile(tds_id, scripts)?;
+        self.compiled
+            .get(tds_id)
+            .map(|c| c.value().clone())
+            .ok_or_else(|| anyhow!("scripts of TDS `{}` vanished", tds_id))
+    }
+
+    /// Runs `pre_request`, returning the request to send.
+    pub async fn pre_request(
+        &self,
+        tds_id: &str,
+        scripts: &ToolScripts,
+        args: &HashMap<String, Value>,
+        request: ScriptRequest,
+    ) -> Result<ScriptRequest> {
+        let compiled = self.compiled(tds_id, scripts)?;
+        if compiled.pre_request.is_none() {
+            return Ok(request);
+        }
+        let args = args.clone();
+        run_blocking(move || compiled.run_pre_request(&args, request))
+            .await
+            .map_err(pre_request_failed)
+    }
+
+    /// Runs `post_response` on an upstream response.
+    pub async fn post_response(
+        &self,
+        tds_id: &str,
+        scripts: &ToolScripts,
+        args: &HashMap<String, Value>,
+        status: u16,
+        body: String,
+        is_error: bool,
+    ) -> Result<ScriptResponse> {
+        let compiled = self.compiled(tds_id, scripts)?;
+        if compiled.post_response.is_none() {
+            return Ok(ScriptResponse {
+                text: body,
+                is_error,
+            });
+        }
+        let args = args.clone();
+        run_blocking(move || compiled.run_post_response(&args, status, body, is_error))
+            .await
+            .map_err(post_response_failed)
+    }
+}
+
+impl CompiledScripts {
+    fn run_pre_request(
+        &self,
+        args: &HashMap<String, Value>,
+        request: ScriptRequest,
+    ) -> Result<ScriptRequest> {
+        let Some(ast) = &self.pre_request else {
+            return Ok(request);
+        };
+        let origin = Url::parse(&request.url)
+            .map(|url| url.origin())
+            .map_err(|e| anyhow!("invalid request url `{}`: {}", request.url, e))?;
+        let mut scope = Scope::new();
+        scope.push("args", to_dynamic(args)?);
+        scope.push("request", to_dynamic(&request)?);
+
+        let returned = run(&self.source, &mut scope, ast)?;
+        let request = match returned.is_map() {
+            true => returned,
+            false => scope.get_value::<Dynamic>("request").unwrap_or_default(),
+        };
+        let request: ScriptRequest = from_dynamic(&request)?;
+
+        // signing may touch the path and query, never where the request goes
+        let url = Url::parse(&request.url)
+            .map_err(|e| anyhow!("invalid request url `{}`: {}", request.url, e))?;
+        if url.origin() != origin {
+            return Err(anyhow!(
+                "request url `{}` leaves the origin {}",
+                request.url,
+                origin.ascii_serialization()
+            ));
+        }
+        Ok(request)
+    }
+
+    fn run_post_response(
+        &self,
+        args: &HashMap<String, Value>,
+        status: u16,
+        body: String,
+        is_error: bool,
+    ) -> Result<ScriptResponse> {
+        let Some(ast) = &self.post_response else {
+            return Ok(ScriptResponse {
+                text: body,
+                is_error,
+            });
+        };
+        let json = serde_json::from_str::<Value>(&body)
+            .ok()
+            .map(to_dynamic)
+            .transpose()?
+            .unwrap_or_default();
+        let mut scope = Scope::new();
+        scope.push("args", to_dynamic(args)?);
+        scope.push("status", status as i64);
+        scope.push("json", json);
+        scope.push("body", body);
+        scope.push("is_error", is_error);
+
+        let returned = run(&self.source, &mut scope, ast)?;
+        let text = if returned.is_unit() {
+            scope.get_value::<String>("body").unwrap_or_default()
+        } else if returned.is_string() {
+            returned.into_string().unwrap_or_default()
+        } else {
+            from_dynamic::<Value>(&returned)?.to_string()
+        };
+        // anything but a bool assigned to `is_error` leaves the status verdict
+        let is_error = match scope.get_value::<Dynamic>("is_error") {
+            Some(value) if value.is_bool() => value.as_bool().unwrap_or(is_error),
+            _ => is_error,
+        };
+        Ok(ScriptResponse { text, is_error })
+    }
+}
+
+/// Runs a script on the blocking pool, off the async runtime workers.
+async fn run_blocking<T: Send + 'static>(
+    script: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(script)
+        .await
+        .map_err(|e| anyhow!("script run was lost: {}", e))?
+}
+
+fn run(scripts: &ToolScripts, scope: &mut Scope, ast: &AST) -> Result<Dynamic> {
+    let timeout = scripts.timeout();
+    let deadline = Instant::now() + timeout;
+    let mut engine = new_engine(scripts);
+    engine.on_progress(move |_| (Instant::now() >= deadline).then_some(Dynamic::UNIT));
+    engine
+        .eval_ast_with_scope::<Dynamic>(scope, ast)
+        .map_err(|e| match *e {
+            EvalAltResult::ErrorTerminated(..) => {
+                anyhow!("timed out after {}ms", timeout.as_millis())
+            }
+            e => anyhow!("{}", e),
+        })
+}
+
+/// A sandboxed engine: the standard library plus the helper functions, no
+/// `eval`, and bounded operations, call depth and value sizes.
+fn new_engine(scripts: &ToolScripts) -> Engine {
+    let mut engine = Engine::new_raw();
+    engine
+        .register_global_module(STANDARD_PACKAGE.clone())
+        .set_max_operations(scripts.max_operations())
+        .set_max_call_levels(SCRIPT_MAX_CALL_LEVELS)
+        .set_max_string_size(SCRIPT_MAX_STRING_SIZE)
+        .set_max_array_size(SCRIPT_MAX_COLLECTION_SIZE)
+        .set_max_map_size(SCRIPT_MAX_COLLECTION_SIZE)
+        .disable_symbol("eval");
+    register_helpers(&mut engine);
+    engine
+}
+
+fn register_helpers(engine: &mut Engine) {
+    engine
+        .register_fn("sha256_hex", |data: &str| {
+            hex::encode(Sha256::digest(data.as_bytes()))
+        })
+        .register_fn("hmac_sha256_hex", |key: &str, data: &str| {
+            hex::encode(hmac_sha256(key, data))
+        })
+        .register_fn("hmac_sha256_base64", |key: &str, data: &str| {
+            STANDARD.encode(hmac_sha256(key, data))
+        })
+        .register_fn("base64_encode", |data: &str| STANDARD.encode(data))
+        .register_fn("base64_decode", |data: &str| {
+            STANDARD
+                .decode(data)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .map_err(|e| Box::<EvalAltResult>::from(e.to_string()))
+        })
+        .register_fn("json_parse", |text: &str| {
+            serde_json::from_str::<Value>(text)
+                .map_err(|e| e.to_string().into())
+                .and_then(to_dynamic)
+        })
+        .register_fn("json_encode", |value: Dynamic| {
+            from_dynamic::<Value>(&value).map(|value| value.to_string())
+        })
+        .register_fn("now_unix", || unix_time().as_secs() as i64)
+        .register_fn("now_unix_ms", || unix_time().as_millis() as i64);
+}
+
+fn hmac_sha256(key: &str, data: &str) -> Vec<u8> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+fn pre_request_failed(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("pre_request script failed: {}", e)
+}
+
+fn post_response_failed(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("post_response script failed: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(pre_request: Option<&str>, post_response: Option<&str>) -> ToolScripts {
+        ToolScripts {
+            pre_request: pre_request.map(str::to_string),
+            post_response: post_response.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn request(url: &str) -> ScriptRequest {
+        ScriptRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: HashMap::new(),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn limits_are_capped_at_validation() {
+        let mut limits = scripts(Some("1"), None);
+        limits.max_operations = Some(MAX_SCRIPT_OPERATIONS + 1);
+        assert!(limits.validate().is_err());
+        limits.max_operations = Some(MAX_SCRIPT_OPERATIONS);
+        limits.timeout_ms = Some(MAX_SCRIPT_TIMEOUT_MS + 1);
+        assert!(limits.validate().is_err());
+        limits.timeout_ms = Some(MAX_SCRIPT_TIMEOUT_MS);
+        assert!(limits.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn pre_request_cannot_leave_the_origin() {
+        let engine = ScriptEngine::new();
+        let args = HashMap::new();
+        let signing = scripts(
+            Some(r#"request.url += "&sig=" + sha256_hex(request.url);"#),
+            None,
+        );
+        let signed = engine
+            .pre_request("sign", &signing, &args, request("https://api.test/v1?a=1"))
+            .await
+            .unwrap();
+        assert!(signed.url.starts_with("https://api.test/v1?a=1&sig="));
+
+        for url in ["https://evil.test/v1", "http://api.test/v1", "https://api.test:8443/v1"] {
+            let moving = scripts(Some(&format!("request.url = \"{}\";", url)), None);
+            let err = engine
+                .pre_request("move", &moving, &args, request("https://api.test/v1"))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("leaves the origin"), "{}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn error_statuses_stay_errors_unless_the_script_clears_them() {
+        let engine = ScriptEngine::new();
+        let args = HashMap::new();
+        let run = |script: &'static str| {
+            let scripts = scripts(None, Some(script));
+            let engine = &engine;
+            let args = &args;
+            async move {
+                engine
+                    .post_response("tds", &scripts, args, 500, "{}".to_string(), true)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let reshaped = run(r#""upstream failed""#).await;
+        assert_eq!(reshaped.text, "upstream failed");
+        assert!(reshaped.is_error);
+        assert!(run(r#"is_error = "no"; body"#).await.is_error);
+        assert!(!run("is_error = false; body").await.is_error);
+    }
+
+    #[tokio::test]
+    async fn scripts_run_off_the_runtime_worker() {
+        let engine = ScriptEngine::new();
+        let mut spinning = scripts(None, Some("loop { }"));
+        spinning.timeout_ms = Some(200);
+        spinning.max_operations = Some(MAX_SCRIPT_OPERATIONS);
+        let args = HashMap::new();
+        let started = Instant::now();
+
+        // the single-threaded test runtime keeps ticking while the script spins
+        let (result, ticked) = tokio::join!(
+            engine.post_response("spin", &spinning, &args, 200, String::new(), false),
+            async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                started.elapsed()
+            }
+        );
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        assert!(ticked < Duration::from_millis(150), "{:?}", ticked);
+    }
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// SQL tool failures the tool layer reports back as `isError` results.
+#[derive(Debug, Error)]
+pub enum SqlCallError {
+    #[error("Unknown sql pool `{0}`")]
+    UnknownPool(String),
+
+    #[error("Query timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Query failed: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl SqlCallError {
+    /// Whether the failure points at an unhealthy database rather than a bad query.
+    pub fn is_upstream_failure(&self) -> bool {
+        match self {
+            SqlCallError::Timeout(_) => true,
+            SqlCallError::Database(e) => matches!(
+                e,
+                sqlx::Error::Io(_)
+                    | sqlx::Error::Tls(_)
+                    | sqlx::Error::PoolTimedOut
+                    | sqlx::Error::PoolClosed
+                    | sqlx::Error::WorkerCrashed
+            ),
+            SqlCallError::UnknownPool(_) => false,
+        }
+    }
+}
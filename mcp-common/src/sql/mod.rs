@@ -0,0 +1,3 @@
+pub mod error;
+pub mod row;
+pub mod sql_pool_provider;
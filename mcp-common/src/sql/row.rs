@@ -0,0 +1,91 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde_json::{json, Map, Value};
+use sqlx::{
+    mysql::MySqlRow, sqlite::SqliteRow, Column, Decode, MySql, Row, Type, TypeInfo, ValueRef,
+};
+
+/// Converts a MySQL row into a JSON object keyed by column name.
+///
+/// Integers and floats stay numbers, DECIMAL stays a string so no precision
+/// is lost, temporal types use ISO 8601, JSON columns are embedded as JSON and
+/// binary columns are base64.
+pub fn mysql_row_to_json(row: &MySqlRow) -> Map<String, Value> {
+    row.columns()
+        .iter()
+        .map(|column| {
+            let i = column.ordinal();
+            let value = match row.try_get_raw(i) {
+                Ok(raw) if raw.is_null() => Value::Null,
+                Ok(_) => mysql_value(row, i, column.type_info().name()),
+                Err(_) => Value::Null,
+            };
+            (column.name().to_string(), value)
+        })
+        .collect()
+}
+
+fn mysql_value(row: &MySqlRow, i: usize, type_name: &str) -> Value {
+    let value = match type_name {
+        "BOOLEAN" => decode::<bool>(row, i).map(|v| json!(v)),
+        name if name.ends_with("UNSIGNED") => decode::<u64>(row, i).map(|v| json!(v)),
+        "TINYINT" | "SMALLINT" | "INT" | "MEDIUMINT" | "BIGINT" | "YEAR" => {
+            decode::<i64>(row, i).map(|v| json!(v))
+        }
+        "FLOAT" => decode::<f32>(row, i).map(|v| json!(v)),
+        "DOUBLE" => decode::<f64>(row, i).map(|v| json!(v)),
+        "DATETIME" | "TIMESTAMP" => decode::<NaiveDateTime>(row, i)
+            .map(|v| json!(v.format("%Y-%m-%dT%H:%M:%S%.f").to_string())),
+        "DATE" => decode::<NaiveDate>(row, i).map(|v| json!(v.to_string())),
+        "TIME" => decode::<NaiveTime>(row, i).map(|v| json!(v.to_string())),
+        "JSON" => decode::<String>(row, i)
+            .and_then(|text| serde_json::from_str(&text).ok().or(Some(Value::String(text)))),
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BIT"
+        | "GEOMETRY" => decode::<Vec<u8>>(row, i).map(|v| json!(STANDARD.encode(v))),
+        _ => decode::<String>(row, i).map(Value::String),
+    };
+    // whatever did not decode as its declared type is passed on as text
+    value
+        .or_else(|| {
+            decode::<Vec<u8>>(row, i).map(|bytes| json!(String::from_utf8_lossy(&bytes)))
+        })
+        .unwrap_or(Value::Null)
+}
+
+/// Decodes without the type compatibility check, so e.g. DECIMAL (sent as
+/// text) can be read as a string.
+fn decode<'r, T>(row: &'r MySqlRow, i: usize) -> Option<T>
+where
+    T: Decode<'r, MySql> + Type<MySql>,
+{
+    row.try_get_unchecked::<T, _>(i).ok()
+}
+
+/// Converts a SQLite row into a JSON object keyed by column name.
+///
+/// SQLite values carry their own storage class, which decides the JSON type;
+/// columns declared BOOLEAN come back as `true`/`false`.
+pub fn sqlite_row_to_json(row: &SqliteRow) -> Map<String, Value> {
+    row.columns()
+        .iter()
+        .map(|column| {
+            let i = column.ordinal();
+            let storage = match row.try_get_raw(i) {
+                Ok(raw) if !raw.is_null() => raw.type_info().name().to_string(),
+                _ => "NULL".to_string(),
+            };
+            let value = match (column.type_info().name(), storage.as_str()) {
+                (_, "NULL") => Some(Value::Null),
+                ("BOOLEAN", "INTEGER") => row.try_get::<bool, _>(i).ok().map(|v| json!(v)),
+                (_, "INTEGER") => row.try_get::<i64, _>(i).ok().map(|v| json!(v)),
+                (_, "REAL") => row.try_get::<f64, _>(i).ok().map(|v| json!(v)),
+                (_, "BLOB") => row
+                    .try_get::<Vec<u8>, _>(i)
+                    .ok()
+                    .map(|v| json!(STANDARD.encode(v))),
+                _ => row.try_get_unchecked::<String, _>(i).ok().map(Value::String),
+            };
+            (column.name().to_string(), value.unwrap_or(Value::Null))
+        })
+        .collect()
+}
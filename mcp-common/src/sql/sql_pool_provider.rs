@@ -0,0 +1,273 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value};
+use sqlx::{
+    mysql::{MySqlConnection, MySqlPool, MySqlPoolOptions},
+    pool::PoolConnection,
+    query::Query,
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+    Connection, Database, Encode, Executor, MySql, Sqlite, Type,
+};
+use tokio_stream::{Stream, StreamExt};
+use tracing::info;
+
+use crate::{
+    config::config::SqlPoolConfig,
+    provider::global_provider::get_app_config,
+    sql::{
+        error::SqlCallError,
+        row::{mysql_row_to_json, sqlite_row_to_json},
+    },
+};
+
+static SQL_POOLS: Lazy<SqlPoolProvider> = Lazy::new(SqlPoolProvider::new);
+
+/// Returns the global registry of `[sql.pools]` connection pools.
+pub fn get_sql_pools() -> &'static SqlPoolProvider {
+    &SQL_POOLS
+}
+
+#[derive(Debug, Clone)]
+enum SqlPool {
+    MySql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
+/// One statement to run, already rewritten to positional `?` parameters.
+#[derive(Debug, Clone)]
+pub struct SqlQuery<'a> {
+    pub sql: &'a str,
+    pub params: Vec<Value>,
+    // Whether to fetch rows (SELECT and friends) or report rows affected
+    pub returns_rows: bool,
+    pub read_only: bool,
+    pub max_rows: usize,
+    // Covers the wait for a connection as well as the query itself
+    pub timeout: Duration,
+}
+
+/// What a statement produced.
+#[derive(Debug, Clone)]
+pub enum SqlOutcome {
+    Rows {
+        rows: Vec<Map<String, Value>>,
+        // more rows than `max_rows` were available
+        truncated: bool,
+    },
+    Affected(u64),
+}
+
+impl SqlOutcome {
+    pub fn to_json(&self) -> Value {
+        match self {
+            SqlOutcome::Rows { rows, .. } => json!(rows),
+            SqlOutcome::Affected(n) => json!({ "rows_affected": n }),
+        }
+    }
+}
+
+/// Connection pools by name, opened lazily on first use.
+pub struct SqlPoolProvider {
+    pools: DashMap<String, SqlPool>,
+}
+
+impl SqlPoolProvider {
+    fn new() -> Self {
+        Self {
+            pools: DashMap::new(),
+        }
+    }
+
+    fn pool(&self, name: &str) -> Result<SqlPool, SqlCallError> {
+        if let Some(pool) = self.pools.get(name) {
+            return Ok(pool.clone());
+        }
+        let config = get_app_config().map_err(|_| SqlCallError::UnknownPool(name.to_string()))?;
+        let pool_config = config
+            .sql
+            .pools
+            .get(name)
+            .ok_or_else(|| SqlCallError::UnknownPool(name.to_string()))?;
+        let pool = Self::connect_lazy(pool_config)?;
+        info!("Opened sql pool: {}", name);
+        self.pools.insert(name.to_string(), pool.clone());
+        Ok(pool)
+    }
+
+    fn connect_lazy(config: &SqlPoolConfig) -> Result<SqlPool, sqlx::Error> {
+        if config.url.starts_with("sqlite:") {
+            let options = SqliteConnectOptions::from_str(&config.url)?.read_only(config.read_only);
+            let mut pool = SqlitePoolOptions::new();
+            if let Some(max) = config.max_connections {
+                pool = pool.max_connections(max);
+            }
+            Ok(SqlPool::Sqlite(pool.connect_lazy_with(options)))
+        } else {
+            let mut pool = MySqlPoolOptions::new();
+            if let Some(max) = config.max_connections {
+                pool = pool.max_connections(max);
+            }
+            Ok(SqlPool::MySql(pool.connect_lazy(&config.url)?))
+        }
+    }
+
+    /// Runs one statement on the named pool within the query's time limit.
+    pub async fn execute(
+        &self,
+        pool_name: &str,
+        query: &SqlQuery<'_>,
+    ) -> Result<SqlOutcome, SqlCallError> {
+        let pool = self.pool(pool_name)?;
+        let run = async {
+            match pool {
+                SqlPool::MySql(pool) => run_mysql(&pool, query).await,
+                SqlPool::Sqlite(pool) => run_sqlite(&pool, query).await,
+            }
+        };
+        tokio::time::timeout(query.timeout, run)
+            .await
+            .map_err(|_| SqlCallError::Timeout(query.timeout))?
+            .map_err(SqlCallError::from)
+    }
+}
+
+async fn run_mysql(pool: &MySqlPool, query: &SqlQuery<'_>) -> Result<SqlOutcome, sqlx::Error> {
+    let mut conn = SessionConnection {
+        conn: pool.acquire().await?,
+        dirty: false,
+    };
+    if query.returns_rows {
+        // stops the SELECT on the server too, not only our wait for it
+        conn.dirty = true;
+        let max_execution_time = format!(
+            "SET SESSION max_execution_time = {}",
+            query.timeout.as_millis()
+        );
+        conn.conn.execute(max_execution_time.as_str()).await?;
+    }
+    let outcome = run_mysql_statement(&mut conn.conn, query).await;
+    if conn.dirty {
+        conn.conn
+            .execute("SET SESSION max_execution_time = DEFAULT")
+            .await?;
+        conn.dirty = false;
+    }
+    outcome
+}
+
+async fn run_mysql_statement(
+    conn: &mut MySqlConnection,
+    query: &SqlQuery<'_>,
+) -> Result<SqlOutcome, sqlx::Error> {
+    // the database itself refuses writes, whatever the statement hides
+    let mut tx = if query.read_only {
+        conn.begin_with("START TRANSACTION READ ONLY").await?
+    } else {
+        conn.begin().await?
+    };
+    let statement = bind_all(sqlx::query::<MySql>(query.sql), &query.params);
+    let outcome = if query.returns_rows {
+        collect_rows(tx.fetch(statement), query.max_rows, mysql_row_to_json).await?
+    } else {
+        SqlOutcome::Affected(tx.execute(statement).await?.rows_affected())
+    };
+    tx.commit().await?;
+    Ok(outcome)
+}
+
+/// A pooled connection whose session settings were changed for one call.
+/// Dropped before they are reset (an error, or the call timing out), it is
+/// closed instead of going back to the pool with them.
+struct SessionConnection {
+    conn: PoolConnection<MySql>,
+    dirty: bool,
+}
+
+impl Drop for SessionConnection {
+    fn drop(&mut self) {
+        if self.dirty {
+            self.conn.close_on_drop();
+        }
+    }
+}
+
+async fn run_sqlite(pool: &SqlitePool, query: &SqlQuery<'_>) -> Result<SqlOutcome, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    // set on every call since pooled connections are shared between tools
+    let pragma = if query.read_only {
+        "PRAGMA query_only = ON"
+    } else {
+        "PRAGMA query_only = OFF"
+    };
+    conn.execute(pragma).await?;
+    let statement = bind_all(sqlx::query::<Sqlite>(query.sql), &query.params);
+    if query.returns_rows {
+        collect_rows(conn.fetch(statement), query.max_rows, sqlite_row_to_json).await
+    } else {
+        Ok(SqlOutcome::Affected(conn.execute(statement).await?.rows_affected()))
+    }
+}
+
+/// Reads at most `max_rows` rows, peeking one further to tell whether the
+/// result was cut.
+async fn collect_rows<R>(
+    mut stream: impl Stream<Item = Result<R, sqlx::Error>> + Unpin,
+    max_rows: usize,
+    to_json: fn(&R) -> Map<String, Value>,
+) -> Result<SqlOutcome, sqlx::Error> {
+    let mut rows = Vec::new();
+    while let Some(row) = stream.next().await {
+        let row = row?;
+        if rows.len() == max_rows {
+            return Ok(SqlOutcome::Rows {
+                rows,
+                truncated: true,
+            });
+        }
+        rows.push(to_json(&row));
+    }
+    Ok(SqlOutcome::Rows {
+        rows,
+        truncated: false,
+    })
+}
+
+/// Binds JSON arguments as native parameters: numbers, booleans and strings
+/// as themselves, null as NULL, arrays and objects as JSON text.
+fn bind_all<'q, DB>(
+    mut query: Query<'q, DB, <DB as Database>::Arguments<'q>>,
+    params: &[Value],
+) -> Query<'q, DB, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    i64: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    bool: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+    Option<String>: Encode<'q, DB> + Type<DB>,
+{
+    for param in params {
+        query = match param {
+            Value::Null => query.bind(None::<String>),
+            Value::Bool(b) => query.bind(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => query.bind(i),
+                None => query.bind(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => query.bind(s.clone()),
+            other => query.bind(other.to_string()),
+        };
+    }
+    query
+}
+
+/// Checks a `[sql.pools]` entry without connecting.
+pub fn validate_pool_config(name: &str, config: &SqlPoolConfig) -> Result<()> {
+    if !config.url.starts_with("mysql:") && !config.url.starts_with("sqlite:") {
+        return Err(anyhow!("sql pool `{}` must be a mysql:// or sqlite: url", name));
+    }
+    Ok(())
+}
@@ -19,7 +19,7 @@ pub fn get_broadcast_tx(capacity: Option<usize>) -> Result<Arc<Sender<BroadcastM
             let (tx, _) = broadcast::channel::<BroadcastMsg>(capacity.unwrap_or(1024));
             Ok(Arc::new(tx))
         })
-        .map(|arc| arc.clone())
+        .cloned()
 }
 
 pub fn get_global_broadcast_tx() -> Result<Arc<Sender<BroadcastMsg>>> {
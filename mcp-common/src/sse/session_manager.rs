@@ -11,14 +11,14 @@ use once_cell::sync::OnceCell;
 ///
 /// ## Initialization
 ///
-/// ```rust
+/// ```rust,ignore
 /// // Initialize the global session manager with capacity 1000 and TTL 30 minutes
 /// init_session_manager(1000, Duration::from_secs(1800));
 /// ```
 ///
 /// ## Usage
 ///
-/// ```rust
+/// ```rust,ignore
 /// #[tokio::main]
 /// async fn main() -> Result<()> {
 ///     // Create a session
@@ -48,7 +48,6 @@ use once_cell::sync::OnceCell;
 ///     Ok(())
 /// }
 /// ```
-
 pub fn get_session_manager() -> Result<&'static StreamableSessionManager> {
     STREAM_SESSION_MANAGER
         .get()
@@ -107,11 +106,12 @@ impl StreamableSessionManager {
 
     pub async fn get_inner(&self, session_id: &str) -> Option<InnerSession> {
         let now = Instant::now();
-        self.cache.get(session_id).await.map(|mut inner| {
-            inner.last_active = now;
-            let _ = self.cache.insert(session_id.to_string(), inner.clone());
-            inner
-        })
+        let mut inner = self.cache.get(session_id).await?;
+        inner.last_active = now;
+        self.cache
+            .insert(session_id.to_string(), inner.clone())
+            .await;
+        Some(inner)
     }
 
     pub async fn get(&self, session_id: &str) -> Option<StreamableSession> {
@@ -0,0 +1,116 @@
+//! Helpers shared by the unit tests of the workspace crates, enabled in other
+//! crates by the `test-util` feature.
+
+use std::{net::SocketAddr, path::PathBuf, sync::Once};
+
+use axum::Router;
+use tokio::net::TcpListener;
+
+use crate::{config::config::AppConfig, provider::global_provider::set_app_config};
+
+static INIT: Once = Once::new();
+
+/// Scratch directory of the current test process.
+pub fn test_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dynmcp-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("Failed to create test dir");
+    dir
+}
+
+/// Installs the config every test of the process shares: no data source,
+/// default HTTP client settings and a read-write and a read-only SQLite pool.
+pub fn init_test_config() {
+    INIT.call_once(|| {
+        let db = test_dir().join("test.db");
+        let toml = format!(
+            r#"
+[app]
+host = "127.0.0.1"
+port = 0
+data_source = "etcd"
+api_key = "test"
+
+[log]
+log_level = "info"
+log_dir = "logs"
+log_name = "test"
+
+[data_source]
+
+[sql.pools.local]
+url = "sqlite://{db}?mode=rwc"
+
+[sql.pools.ro]
+url = "sqlite://{db}?mode=rwc"
+read_only = true
+"#,
+            db = db.display()
+        );
+        set_app_config(AppConfig::from_toml(&toml).expect("Invalid test config"))
+            .expect("Test config already set");
+    });
+}
+
+/// Serves `router` on an ephemeral port of 127.0.0.1.
+pub async fn serve(router: Router) -> SocketAddr {
+    serve_on("127.0.0.1:0", router).await
+}
+
+/// Serves `router` on `addr`, e.g. "127.0.0.2:0".
+pub async fn serve_on(addr: &str, router: Router) -> SocketAddr {
+    let listener = TcpListener::bind(addr).await.expect("Failed to bind");
+    let addr = listener.local_addr().expect("No local address");
+    tokio::spawn(async move { axum::serve(listener, router).await.expect("Server failed") });
+    addr
+}
+
+/// A FileDescriptorSet with service `test.Echo`: `Get` (marked
+/// `NO_SIDE_EFFECTS`) and `Create`, both taking and returning
+/// `test.Msg { string text = 1; }`.
+pub fn echo_descriptor_set() -> Vec<u8> {
+    use prost::Message;
+    use prost_reflect::prost_types::{
+        field_descriptor_proto::{Label, Type},
+        method_options::IdempotencyLevel,
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        MethodDescriptorProto, MethodOptions, ServiceDescriptorProto,
+    };
+
+    let method = |name: &str, idempotency: Option<IdempotencyLevel>| MethodDescriptorProto {
+        name: Some(name.to_string()),
+        input_type: Some(".test.Msg".to_string()),
+        output_type: Some(".test.Msg".to_string()),
+        options: idempotency.map(|level| MethodOptions {
+            idempotency_level: Some(level as i32),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let file = FileDescriptorProto {
+        name: Some("test/echo.proto".to_string()),
+        package: Some("test".to_string()),
+        syntax: Some("proto3".to_string()),
+        message_type: vec![DescriptorProto {
+            name: Some("Msg".to_string()),
+            field: vec![FieldDescriptorProto {
+                name: Some("text".to_string()),
+                json_name: Some("text".to_string()),
+                number: Some(1),
+                label: Some(Label::Optional as i32),
+                r#type: Some(Type::String as i32),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+        service: vec![ServiceDescriptorProto {
+            name: Some("Echo".to_string()),
+            method: vec![
+                method("Get", Some(IdempotencyLevel::NoSideEffects)),
+                method("Create", None),
+            ],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    FileDescriptorSet { file: vec![file] }.encode_to_vec()
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::transform::template;
+
+/// Builds the upstream JSON body from flat tool arguments.
+///
+/// The template is rendered with minijinja and must produce JSON, unless the
+/// TDS uses the `xml` or `text` request encoding, where the output is sent as-is. It sees the
+/// tool arguments as `args` (and by their own names), the TDS `constants`, and
+/// the calling IDS's metadata as `ids`. Values are inserted with `tojson`:
+///
+/// ```json
+/// {
+///   "template": "{\"user\": {\"email\": {{ email | tojson }}}{% if tags %}, \"labels\": [{% for t in tags %}{{ t | tojson }}{% if not loop.last %},{% endif %}{% endfor %}]{% endif %}, \"source\": {{ constants.source | tojson }}}",
+///   "constants": { "source": "dynmcp" }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BodyTemplate {
+    pub template: String,
+    #[serde(default)]
+    pub constants: HashMap<String, Value>,
+}
+
+impl BodyTemplate {
+    pub fn validate(&self) -> Result<()> {
+        template::check(&self.template)
+    }
+
+    pub fn render(&self, args: &HashMap<String, Value>, ids_metadata: &Value) -> Result<Value> {
+        let rendered = self.render_text(args, ids_metadata)?;
+        serde_json::from_str(&rendered)
+            .map_err(|e| anyhow!("Body template did not render valid JSON: {}", e))
+    }
+
+    /// Renders the template as-is, for XML and plain-text request encodings.
+    pub fn render_text(&self, args: &HashMap<String, Value>, ids_metadata: &Value) -> Result<String> {
+        let mut ctx: Map<String, Value> = args.clone().into_iter().collect();
+        ctx.insert("args".into(), json!(args));
+        ctx.insert("constants".into(), json!(self.constants));
+        ctx.insert("ids".into(), ids_metadata.clone());
+
+        template::render(&self.template, &ctx)
+    }
+}
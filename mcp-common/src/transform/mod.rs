@@ -0,0 +1,3 @@
+pub mod body_template;
+pub mod response_transform;
+pub mod template;
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serde_json_path::JsonPath;
+
+use crate::transform::template;
+
+/// Post-processing applied to an upstream response before it is handed to the model.
+///
+/// Steps run in field order: `select`, `rename`, `max_array_items`, then
+/// `template`. The first three only apply to JSON bodies; a template also sees
+/// the raw body as `body`, so it works for non-JSON upstreams too.
+///
+/// ```json
+/// {
+///   "select": { "title": "$.data.title", "tags": "$.data.tags[*].name" },
+///   "rename": { "title": "name" },
+///   "max_array_items": 10,
+///   "template": "{{ name }} ({{ tags | join(', ') }})"
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseTransform {
+    // Output field -> JSONPath query. A query matching one node yields that
+    // node, no match yields null and several matches yield an array.
+    #[serde(default)]
+    pub select: Option<HashMap<String, String>>,
+    // Object key renames, applied at every depth
+    #[serde(default)]
+    pub rename: HashMap<String, String>,
+    // Arrays longer than this are cut down, at every depth
+    pub max_array_items: Option<usize>,
+    // Jinja-style template rendering the final text; the JSON result is
+    // available as `result` and, when it is an object, by its top-level keys
+    pub template: Option<String>,
+    // `select` parsed once, by `validate` or on first use
+    #[serde(skip)]
+    compiled_select: OnceCell<Vec<(String, JsonPath)>>,
+}
+
+impl ResponseTransform {
+    pub fn validate(&self) -> Result<()> {
+        self.compiled_select()?;
+        if let Some(tpl) = &self.template {
+            template::check(tpl)?;
+        }
+        Ok(())
+    }
+
+    fn compiled_select(&self) -> Result<&[(String, JsonPath)]> {
+        let compiled = self.compiled_select.get_or_try_init(|| {
+            self.select
+                .iter()
+                .flatten()
+                .map(|(field, path)| {
+                    JsonPath::parse(path)
+                        .map(|path| (field.clone(), path))
+                        .map_err(|e| anyhow!("Invalid JSONPath for `{}`: {}", field, e))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        Ok(compiled)
+    }
+
+    /// Transforms a raw upstream body into the text returned to the model,
+    /// along with whether `max_array_items` cut any array short.
+    pub fn apply(&self, body: &str) -> Result<(String, bool)> {
+        let parsed = serde_json::from_str::<Value>(body).ok();
+
+        let mut truncated = false;
+        let result = match parsed {
+            Some(mut value) => {
+                if self.select.is_some() {
+                    value = select_fields(self.compiled_select()?, &value);
+                }
+                if !self.rename.is_empty() {
+                    rename_keys(&mut value, &self.rename);
+                }
+                if let Some(max) = self.max_array_items {
+                    truncated = truncate_arrays(&mut value, max);
+                }
+                Some(value)
+            }
+            None if self.template.is_none() => return Ok((body.to_string(), false)),
+            None => None,
+        };
+
+        match &self.template {
+            Some(tpl) => {
+                let mut ctx = match &result {
+                    Some(Value::Object(map)) => map.clone(),
+                    _ => Map::new(),
+                };
+                ctx.insert("result".into(), result.unwrap_or(Value::Null));
+                ctx.insert("body".into(), json!(body));
+                Ok((template::render(tpl, &ctx)?, truncated))
+            }
+            None => Ok((serde_json::to_string(&result)?, truncated)),
+        }
+    }
+}
+
+fn select_fields(select: &[(String, JsonPath)], value: &Value) -> Value {
+    let mut out = Map::new();
+    for (field, path) in select {
+        let nodes = path.query(value);
+        let selected = match nodes.at_most_one() {
+            Ok(node) => node.cloned().unwrap_or(Value::Null),
+            Err(_) => Value::Array(nodes.all().into_iter().cloned().collect()),
+        };
+        out.insert(field.clone(), selected);
+    }
+    Value::Object(out)
+}
+
+fn rename_keys(value: &mut Value, rename: &HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            let renamed = std::mem::take(map)
+                .into_iter()
+                .map(|(k, mut v)| {
+                    rename_keys(&mut v, rename);
+                    (rename.get(&k).cloned().unwrap_or(k), v)
+                })
+                .collect();
+            *map = renamed;
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| rename_keys(v, rename)),
+        _ => {}
+    }
+}
+
+/// Cuts every array down to `max` items; returns whether any was longer.
+fn truncate_arrays(value: &mut Value, max: usize) -> bool {
+    let mut cut = false;
+    match value {
+        Value::Array(items) => {
+            cut = items.len() > max;
+            items.truncate(max);
+            for item in items {
+                cut |= truncate_arrays(item, max);
+            }
+        }
+        Value::Object(map) => {
+            for v in map.values_mut() {
+                cut |= truncate_arrays(v, max);
+            }
+        }
+        _ => {}
+    }
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(json: Value) -> ResponseTransform {
+        let transform: ResponseTransform = serde_json::from_value(json).unwrap();
+        transform.validate().unwrap();
+        transform
+    }
+
+    #[test]
+    fn select_paths_are_parsed_once_at_validation() {
+        let transform = transform(json!({ "select": { "id": "$.data.id" } }));
+        assert!(transform.compiled_select.get().is_some());
+        let (text, truncated) = transform.apply(r#"{"data":{"id":7,"x":1}}"#).unwrap();
+        assert_eq!(text, r#"{"id":7}"#);
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn invalid_select_paths_fail_validation() {
+        let transform: ResponseTransform =
+            serde_json::from_value(json!({ "select": { "id": "data.id" } })).unwrap();
+        assert!(transform.validate().is_err());
+    }
+
+    #[test]
+    fn cut_arrays_are_reported() {
+        let transform = transform(json!({ "max_array_items": 2, "rename": { "a": "b" } }));
+        let (text, truncated) = transform.apply(r#"{"a":[1,2,3]}"#).unwrap();
+        assert_eq!(text, r#"{"b":[1,2]}"#);
+        assert!(truncated);
+
+        let (_, truncated) = transform.apply(r#"{"a":[1,2],"c":{"d":[[1,2,3]]}}"#).unwrap();
+        assert!(truncated, "nested arrays count too");
+        let (_, truncated) = transform.apply(r#"{"a":[1,2]}"#).unwrap();
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn non_json_bodies_pass_through_without_a_template() {
+        let transform = transform(json!({ "max_array_items": 1 }));
+        assert_eq!(transform.apply("plain text").unwrap(), ("plain text".to_string(), false));
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Result};
+use minijinja::{Environment, Error, ErrorKind, Value};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+
+// Everything but the unreserved characters of RFC 3986
+const URL_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Renders a Jinja-style template (minijinja) against a serializable context.
+///
+/// Templates are plain text: no HTML auto-escaping is applied.
+pub fn render<S: Serialize>(template: &str, ctx: &S) -> Result<String> {
+    Environment::new()
+        .render_str(template, Value::from_serialize(ctx))
+        .map_err(|e| anyhow!("Template render failed: {}", e))
+}
+
+/// Checks that a template parses, without rendering it.
+pub fn check(template: &str) -> Result<()> {
+    Environment::new()
+        .template_from_str(template)
+        .map(|_| ())
+        .map_err(|e| anyhow!("Invalid template: {}", e))
+}
+
+/// Renders a URL template, percent-encoding every interpolated value so an
+/// argument stays within its path segment or query value. The scheme and host
+/// are literal (see `check_url`), so the URL cannot be pointed elsewhere.
+pub fn render_url<S: Serialize>(template: &str, ctx: &S) -> Result<String> {
+    let origin = url_origin(template)?;
+    let mut env = Environment::new();
+    env.set_formatter(|out, _state, value| {
+        if value.is_undefined() {
+            return Ok(());
+        }
+        let value = value.to_string();
+        // dot segments are resolved away even when encoded
+        if value == "." || value == ".." {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("`{}` is not a valid url value", value),
+            ));
+        }
+        write!(out, "{}", utf8_percent_encode(&value, URL_VALUE)).map_err(Error::from)
+    });
+    let url = env
+        .render_str(template, Value::from_serialize(ctx))
+        .map_err(|e| anyhow!("Template render failed: {}", e))?;
+    debug_assert!(url.starts_with(origin));
+    Ok(url)
+}
+
+/// Checks a URL template: it parses, and its scheme and host are literal
+/// http(s) so only the path and query are templated.
+pub fn check_url(template: &str) -> Result<()> {
+    url_origin(template)?;
+    check(template)
+}
+
+/// The literal `scheme://host[:port]` a URL template starts with.
+fn url_origin(template: &str) -> Result<&str> {
+    let rest = template
+        .strip_prefix("https://")
+        .or_else(|| template.strip_prefix("http://"))
+        .ok_or_else(|| anyhow!("url must start with http:// or https://"))?;
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let origin = &template[..template.len() - rest.len() + end];
+    if origin.contains("{{") || origin.contains("{%") || origin.contains("{#") {
+        return Err(anyhow!("only the path and query of a url can be templated"));
+    }
+    Ok(origin)
+}
+
+/// Evaluates a Jinja expression (e.g. `steps.user.result.id`) to JSON.
+pub fn eval<S: Serialize>(expr: &str, ctx: &S) -> Result<serde_json::Value> {
+    let value = Environment::new()
+        .compile_expression(expr)
+        .and_then(|e| e.eval(Value::from_serialize(ctx)))
+        .map_err(|e| anyhow!("Expression `{}` failed: {}", expr, e))?;
+    if value.is_undefined() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::to_value(&value).map_err(|e| anyhow!("Expression `{}` failed: {}", expr, e))
+}
+
+/// Evaluates a Jinja expression as a condition, with Jinja truthiness.
+pub fn eval_condition<S: Serialize>(expr: &str, ctx: &S) -> Result<bool> {
+    Environment::new()
+        .compile_expression(expr)
+        .and_then(|e| e.eval(Value::from_serialize(ctx)))
+        .map(|value| value.is_true())
+        .map_err(|e| anyhow!("Condition `{}` failed: {}", expr, e))
+}
+
+/// Checks that an expression parses, without evaluating it.
+pub fn check_expression(expr: &str) -> Result<()> {
+    Environment::new()
+        .compile_expression(expr)
+        .map(|_| ())
+        .map_err(|e| anyhow!("Invalid expression `{}`: {}", expr, e))
+}
+
+/// Renders every string inside a JSON value as a template.
+///
+/// A string that is a single `{{ ... }}` takes the expression's value with
+/// its JSON type, so numbers, objects and arrays pass through unchanged.
+pub fn render_json<S: Serialize>(value: &serde_json::Value, ctx: &S) -> Result<serde_json::Value> {
+    match value {
+        serde_json::Value::String(text) => match sole_expression(text) {
+            Some(expr) => eval(expr, ctx),
+            None => render(text, ctx).map(serde_json::Value::String),
+        },
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| render_json(item, ctx))
+            .collect::<Result<_>>()
+            .map(serde_json::Value::Array),
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| render_json(v, ctx).map(|v| (k.clone(), v)))
+            .collect::<Result<_>>()
+            .map(serde_json::Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+/// Checks every string inside a JSON value the way `render_json` uses it.
+pub fn check_json(value: &serde_json::Value) -> Result<()> {
+    match value {
+        serde_json::Value::String(text) => match sole_expression(text) {
+            Some(expr) => check_expression(expr),
+            None => check(text),
+        },
+        serde_json::Value::Array(items) => items.iter().try_for_each(check_json),
+        serde_json::Value::Object(map) => map.values().try_for_each(check_json),
+        _ => Ok(()),
+    }
+}
+
+fn sole_expression(text: &str) -> Option<&str> {
+    let inner = text.trim().strip_prefix("{{")?.strip_suffix("}}")?;
+    (!inner.contains("{{") && !inner.contains("}}")).then_some(inner.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn url_values_are_encoded() {
+        let ctx = json!({ "args": { "id": "a b/../c?d=1", "q": "x&y" } });
+        let url = render_url("https://api.test/users/{{ args.id }}?q={{ args.q }}", &ctx).unwrap();
+        assert_eq!(url, "https://api.test/users/a%20b%2F..%2Fc%3Fd%3D1?q=x%26y");
+    }
+
+    #[test]
+    fn url_hosts_cannot_be_templated() {
+        for url in [
+            "https://{{ args.host }}/users",
+            "https://api.test{{ args.suffix }}/users",
+            "http://api.test:{{ args.port }}",
+            "{{ args.url }}",
+        ] {
+            assert!(check_url(url).is_err(), "{}", url);
+        }
+        assert!(check_url("http://api.test:8080/{{ args.id }}").is_ok());
+    }
+
+    #[test]
+    fn dot_segments_are_refused() {
+        let ctx = json!({ "args": { "id": ".." } });
+        assert!(render_url("https://api.test/users/{{ args.id }}/admin", &ctx).is_err());
+    }
+}
@@ -7,7 +7,7 @@ impl<'a> HeaderExtractor<'a> {
     /// Creates a new HeaderExtractor instance
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let headers = HeaderMap::new();
     /// let extractor = HeaderExtractor::new(&headers);
     /// ```
@@ -18,7 +18,7 @@ impl<'a> HeaderExtractor<'a> {
     /// Extracts header value as string
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let session_id = extractor.get_str("Mcp-Session-Id");
     /// // Returns: Some("abc123") or None
     /// ```
@@ -32,7 +32,7 @@ impl<'a> HeaderExtractor<'a> {
     /// Extracts header value as numeric type
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let timeout: Option<u64> = extractor.get_number("Timeout");
     /// let port: Option<i32> = extractor.get_number("Port");
     /// ```
@@ -46,7 +46,7 @@ impl<'a> HeaderExtractor<'a> {
     /// Extracts header value as boolean
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let debug: Option<bool> = extractor.get_bool("X-Debug");
     /// let enable_cache: Option<bool> = extractor.get_bool("Enable-Cache");
     /// ```
@@ -57,7 +57,7 @@ impl<'a> HeaderExtractor<'a> {
     /// Extracts header value as JSON deserialized type
     ///
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// #[derive(Deserialize)]
     /// struct Config { timeout: u64, debug: bool }
     ///
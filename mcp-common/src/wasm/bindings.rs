@@ -0,0 +1,34 @@
+// Host side of the plugin interface, generated from the WIT world below.
+wasmtime::component::bindgen!({
+    inline: "
+        package dynmcp:plugin@0.1.0;
+
+        interface host {
+            record http-request {
+                method: string,
+                url: string,
+                headers: list<tuple<string, string>>,
+                body: option<string>,
+            }
+
+            record http-response {
+                status: u16,
+                headers: list<tuple<string, string>>,
+                body: string,
+            }
+
+            // Error statuses come back as responses; `err` means the request
+            // was refused or never completed
+            http-call: func(request: http-request) -> result<http-response, string>;
+        }
+
+        world tool {
+            import host;
+
+            // Tool arguments as a JSON object in, result text or error out
+            export call: func(arguments: string) -> result<string, string>;
+        }
+    ",
+    world: "tool",
+    async: true,
+});
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+/// WASM plugin failures the tool layer reports back as `isError` results.
+#[derive(Debug, Error)]
+pub enum WasmCallError {
+    #[error("Unknown wasm plugin `{0}`")]
+    UnknownPlugin(String),
+
+    #[error("Wasm plugin ran out of fuel ({0})")]
+    OutOfFuel(u64),
+
+    #[error("Wasm plugin timed out after {0}ms")]
+    Timeout(u128),
+
+    #[error("Wasm plugin trapped: {0}")]
+    Trap(String),
+
+    // The `err` returned by the plugin itself
+    #[error("{0}")]
+    Plugin(String),
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use reqwest::Url;
+use serde_json::Value;
+use tracing::debug;
+use wasmtime::{component::ResourceTable, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::{IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+use crate::{
+    backend::wasm::WasmBackend,
+    http_client::{
+        encoding::RequestEncoding,
+        error::HttpCallError,
+        model::{HttpCallPolicy, HttpRequestOptions, LimitedText, ResponseLimits},
+    },
+    provider::global_provider::get_http_client,
+    wasm::bindings::dynmcp::plugin::host::{Host, HttpRequest, HttpResponse},
+    xds::tds::TDS,
+};
+
+/// The store data of one plugin call.
+///
+/// WASI is there for the runtime support components are built with: no
+/// arguments, environment, files, sockets or stdio are granted.
+pub(crate) struct PluginState {
+    wasi: WasiCtx,
+    table: ResourceTable,
+    pub(crate) limits: StoreLimits,
+    backend: WasmBackend,
+    tls_profile: Option<String>,
+    call_policy: Option<HttpCallPolicy>,
+    response_limits: Option<ResponseLimits>,
+}
+
+impl PluginState {
+    pub(crate) fn new(tds: &TDS, backend: &WasmBackend) -> Self {
+        Self {
+            wasi: WasiCtxBuilder::new().build(),
+            table: ResourceTable::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(backend.max_memory_bytes())
+                .trap_on_grow_failure(true)
+                .build(),
+            backend: backend.clone(),
+            tls_profile: tds.tds_ext_info.tls_profile.clone(),
+            call_policy: tds.tds_ext_info.call_policy.clone(),
+            response_limits: tds.tds_ext_info.response_limits.clone(),
+        }
+    }
+}
+
+impl IoView for PluginState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for PluginState {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl Host for PluginState {
+    async fn http_call(&mut self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let url = Url::parse(&request.url).map_err(|e| format!("invalid url: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("only http(s) urls can be called".to_string());
+        }
+        let host = url.host_str().unwrap_or_default();
+        if !self.backend.allows_host(host) {
+            return Err(format!("host `{}` is not in allowed_hosts", host));
+        }
+        debug!("mcp_protocol[tool/call] wasm http call: {} {}", request.method, url);
+
+        let options = HttpRequestOptions::<Value> {
+            method: request.method,
+            headers: Some(request.headers.into_iter().collect::<HashMap<_, _>>()),
+            body: request.body.map(Value::String),
+            tls_profile: self.tls_profile.clone(),
+            call_policy: self.call_policy.clone(),
+            encoding: RequestEncoding::Text,
+            response_limits: self.response_limits.clone(),
+            // each hop must pass `allowed_hosts`, so the plugin follows redirects itself
+            follow_redirects: false,
+        };
+        let http_client = get_http_client().map_err(|e| e.to_string())?;
+        match http_client
+            .request_with_headers::<Value, LimitedText>(url.as_str(), options)
+            .await
+        {
+            Ok((status, headers, body)) => Ok(HttpResponse {
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        value.to_str().ok().map(|v| (name.to_string(), v.to_string()))
+                    })
+                    .collect(),
+                body: body.text,
+            }),
+            Err(e) => match e.downcast_ref::<HttpCallError>() {
+                // the plugin decides what an error status means
+                Some(HttpCallError::Status { status, body }) => Ok(HttpResponse {
+                    status: status.as_u16(),
+                    headers: Vec::new(),
+                    body: body.clone(),
+                }),
+                _ => Err(e.to_string()),
+            },
+        }
+    }
+}
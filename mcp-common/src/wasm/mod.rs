@@ -0,0 +1,4 @@
+mod bindings;
+pub mod error;
+mod host;
+pub mod wasm_runtime;
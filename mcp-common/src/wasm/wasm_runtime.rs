@@ -0,0 +1,392 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use tracing::info;
+use wasmtime::{
+    component::{Component, Linker},
+    Config, Engine, Store, Trap,
+};
+
+use crate::{
+    backend::wasm::WasmBackend,
+    constants::constants::wasm_consts::{WASM_EPOCH_TICK_MS, WASM_FUEL_YIELD_INTERVAL},
+    wasm::{
+        bindings::{Tool, ToolPre},
+        error::WasmCallError,
+        host::PluginState,
+    },
+    xds::{pds::PDS, tds::TDS},
+};
+
+static WASM_RUNTIME: OnceCell<WasmRuntime> = OnceCell::new();
+
+/// Returns the global WASM plugin runtime.
+pub fn get_wasm_runtime() -> Result<&'static WasmRuntime> {
+    WASM_RUNTIME.get_or_try_init(WasmRuntime::new)
+}
+
+/// A compiled plugin, ready to be instantiated.
+struct LoadedPlugin {
+    sha256: String,
+    pre: ToolPre<PluginState>,
+}
+
+/// Compiled WASM plugins by PDS id.
+///
+/// A plugin is compiled when its PDS enters the `McpCache` and swapped out
+/// when the PDS changes; calls already running finish on the version they
+/// started with. Every call gets a fresh instance with its own fuel, memory
+/// and wall-clock limits; the clock is the engine epoch, advanced by a
+/// background thread every `WASM_EPOCH_TICK_MS`. Compiling runs on the
+/// blocking pool.
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<PluginState>,
+    plugins: DashMap<String, Arc<LoadedPlugin>>,
+}
+
+impl WasmRuntime {
+    fn new() -> Result<Self> {
+        let mut config = Config::new();
+        config
+            .async_support(true)
+            .consume_fuel(true)
+            .epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        let ticker = engine.weak();
+        thread::Builder::new()
+            .name("wasm-epoch".to_string())
+            .spawn(move || {
+                while let Some(engine) = ticker.upgrade() {
+                    engine.increment_epoch();
+                    drop(engine);
+                    thread::sleep(Duration::from_millis(WASM_EPOCH_TICK_MS));
+                }
+            })?;
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::add_to_linker_async(&mut linker)?;
+        Tool::add_to_linker(&mut linker, |state: &mut PluginState| state)?;
+        Ok(Self {
+            engine,
+            linker,
+            plugins: DashMap::new(),
+        })
+    }
+
+    /// Compiles a plugin and swaps it in, unless this version is loaded already.
+    pub async fn load(&self, pds: &PDS) -> Result<()> {
+        let loaded = self
+            .plugins
+            .get(&pds.id)
+            .is_some_and(|plugin| plugin.sha256 == pds.sha256);
+        if loaded {
+            return Ok(());
+        }
+        let pre = self.compile(pds).await?;
+        self.plugins.insert(
+            pds.id.clone(),
+            Arc::new(LoadedPlugin {
+                sha256: pds.sha256.clone(),
+                pre,
+            }),
+        );
+        info!("Loaded wasm plugin {} ({})", pds.id, pds.sha256);
+        Ok(())
+    }
+
+    /// Compiles a plugin without loading it, to reject uploads that would
+    /// not run.
+    pub async fn check(&self, pds: &PDS) -> Result<()> {
+        self.compile(pds).await.map(|_| ())
+    }
+
+    async fn compile(&self, pds: &PDS) -> Result<ToolPre<PluginState>> {
+        pds.validate()?;
+        let wasm = pds.decode()?;
+        let id = pds.id.clone();
+        let engine = self.engine.clone();
+        let linker = self.linker.clone();
+        // cranelift takes a while on larger components
+        tokio::task::spawn_blocking(move || {
+            let component = Component::new(&engine, wasm)
+                .map_err(|e| anyhow!("Failed to compile wasm plugin `{}`: {}", id, e))?;
+            linker
+                .instantiate_pre(&component)
+                .and_then(ToolPre::new)
+                .map_err(|e| anyhow!("Wasm plugin `{}` does not fit the tool world: {}", id, e))
+        })
+        .await
+        .map_err(|e| anyhow!("Compiling wasm plugin `{}` was lost: {}", pds.id, e))?
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.plugins.remove(id);
+    }
+
+    /// Runs the plugin of a `wasm` backend with the tool arguments as JSON.
+    pub async fn call(
+        &self,
+        tds: &TDS,
+        backend: &WasmBackend,
+        arguments: &str,
+    ) -> Result<String, WasmCallError> {
+        let plugin = self
+            .plugins
+            .get(&backend.plugin)
+            .map(|plugin| plugin.value().clone())
+            .ok_or_else(|| WasmCallError::UnknownPlugin(backend.plugin.clone()))?;
+
+        let fuel = backend.fuel();
+        let timeout = backend.timeout();
+        let trap = |e: anyhow::Error| match e.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => WasmCallError::OutOfFuel(fuel),
+            Some(Trap::Interrupt) => WasmCallError::Timeout(timeout.as_millis()),
+            _ => WasmCallError::Trap(e.root_cause().to_string()),
+        };
+        let mut store = Store::new(&self.engine, PluginState::new(tds, backend));
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(fuel).map_err(trap)?;
+        // long computations leave room for other tasks on the worker
+        store
+            .fuel_async_yield_interval(Some(WASM_FUEL_YIELD_INTERVAL))
+            .map_err(trap)?;
+        // traps at the first epoch check past the deadline, rounded up to a tick
+        let ticks = timeout.as_millis().div_ceil(WASM_EPOCH_TICK_MS as u128) as u64;
+        store.set_epoch_deadline(ticks);
+        store.epoch_deadline_trap();
+
+        let tool = plugin.pre.instantiate_async(&mut store).await.map_err(trap)?;
+        tool.call_call(&mut store, arguments)
+            .await
+            .map_err(trap)?
+            .map_err(WasmCallError::Plugin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            OnceLock,
+        },
+        time::Instant,
+    };
+
+    use axum::{http::header::LOCATION, http::StatusCode, routing::get, Router};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        constants::constants::wasm_consts::MAX_WASM_MODULE_BYTES,
+        test_util::{init_test_config, serve},
+    };
+
+    // `call` returns its arguments
+    const ECHO: &str = r#"
+(component
+  (core module $M
+    (memory (export "memory") 1)
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32) (i32.const 256))
+    (func (export "call") (param $ptr i32) (param $len i32) (result i32)
+      (i32.store8 (i32.const 0) (i32.const 0))
+      (i32.store (i32.const 4) (local.get $ptr))
+      (i32.store (i32.const 8) (local.get $len))
+      (i32.const 0)))
+  (core instance $m (instantiate $M))
+  (alias core export $m "memory" (core memory $memory))
+  (alias core export $m "realloc" (core func $realloc))
+  (func (export "call") (param "arguments" string) (result (result string (error string)))
+    (canon lift (core func $m "call") (memory $memory) (realloc $realloc))))
+"#;
+
+    // `call` spins until something stops it
+    const SPIN: &str = r#"
+(component
+  (core module $M
+    (memory (export "memory") 1)
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32) (i32.const 256))
+    (func (export "call") (param i32 i32) (result i32)
+      (loop $spin (br $spin))
+      (unreachable)))
+  (core instance $m (instantiate $M))
+  (alias core export $m "memory" (core memory $memory))
+  (alias core export $m "realloc" (core func $realloc))
+  (func (export "call") (param "arguments" string) (result (result string (error string)))
+    (canon lift (core func $m "call") (memory $memory) (realloc $realloc))))
+"#;
+
+    // `call` GETs the url it is given and returns the response body, or the
+    // `http-call` error
+    const FETCH: &str = r#"
+(component
+  (import "dynmcp:plugin/host@0.1.0" (instance $host
+    (type $request (record
+      (field "method" string)
+      (field "url" string)
+      (field "headers" (list (tuple string string)))
+      (field "body" (option string))))
+    (export "http-request" (type $http-request (eq $request)))
+    (type $response (record
+      (field "status" u16)
+      (field "headers" (list (tuple string string)))
+      (field "body" string)))
+    (export "http-response" (type $http-response (eq $response)))
+    (export "http-call"
+      (func (param "request" $http-request) (result (result $http-response (error string)))))))
+  (core module $Libc
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 4096))
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $ptr) (local.get 3)))
+      (local.get $ptr)))
+  (core instance $libc (instantiate $Libc))
+  (alias core export $libc "memory" (core memory $memory))
+  (alias core export $libc "realloc" (core func $realloc))
+  (alias export $host "http-call" (func $http-call))
+  (core func $http-call
+    (canon lower (func $http-call) (memory $memory) (realloc $realloc)))
+  (core module $Main
+    (import "libc" "memory" (memory 1))
+    (import "host" "http-call"
+      (func $http-call (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32)))
+    (data (i32.const 0) "GET")
+    (func (export "call") (param $url i32) (param $len i32) (result i32)
+      (call $http-call
+        (i32.const 0) (i32.const 3)
+        (local.get $url) (local.get $len)
+        (i32.const 0) (i32.const 0)
+        (i32.const 0) (i32.const 0) (i32.const 0)
+        (i32.const 1024))
+      ;; ok(response): move its body where ok(string) keeps it
+      (if (i32.eqz (i32.load8_u (i32.const 1024)))
+        (then
+          (i32.store (i32.const 1028) (i32.load (i32.const 1040)))
+          (i32.store (i32.const 1032) (i32.load (i32.const 1044)))))
+      (i32.const 1024)))
+  (core instance $main (instantiate $Main
+    (with "libc" (instance $libc))
+    (with "host" (instance (export "http-call" (func $http-call))))))
+  (func (export "call") (param "arguments" string) (result (result string (error string)))
+    (canon lift (core func $main "call") (memory $memory) (realloc $realloc))))
+"#;
+
+    fn tds() -> TDS {
+        serde_json::from_value(json!({
+            "id": "test/plugin",
+            "name": "plugin",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": {
+                "domain": "",
+                "method": "POST",
+                "path": "",
+                "required_params": {},
+                "ext_info": {}
+            }
+        }))
+        .unwrap()
+    }
+
+    async fn plugin(id: &str, wat: &str, backend: Value) -> WasmBackend {
+        let runtime = get_wasm_runtime().unwrap();
+        runtime
+            .load(&PDS::new(id.to_string(), id.to_string(), wat.as_bytes()))
+            .await
+            .unwrap();
+        let mut backend = backend;
+        backend["plugin"] = json!(id);
+        serde_json::from_value(backend).unwrap()
+    }
+
+    #[tokio::test]
+    async fn calls_return_what_the_guest_returns() {
+        init_test_config();
+        let backend = plugin("echo", ECHO, json!({})).await;
+        let output = get_wasm_runtime()
+            .unwrap()
+            .call(&tds(), &backend, r#"{"city":"Paris"}"#)
+            .await
+            .unwrap();
+        assert_eq!(output, r#"{"city":"Paris"}"#);
+    }
+
+    #[tokio::test]
+    async fn calls_are_cut_off_at_the_wall_clock_limit() {
+        init_test_config();
+        let backend = plugin(
+            "spin",
+            SPIN,
+            json!({ "fuel": u64::MAX / 2, "timeout_ms": 100 }),
+        )
+        .await;
+        let started = Instant::now();
+        let err = get_wasm_runtime()
+            .unwrap()
+            .call(&tds(), &backend, "{}")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WasmCallError::Timeout(100)), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn http_calls_do_not_follow_redirects_past_allowed_hosts() {
+        init_test_config();
+        static SECRET_HITS: AtomicUsize = AtomicUsize::new(0);
+        static PORT: OnceLock<u16> = OnceLock::new();
+        let addr = serve(
+            Router::new()
+                .route(
+                    "/moved",
+                    get(|| async {
+                        let location = format!("http://localhost:{}/secret", PORT.get().unwrap());
+                        (StatusCode::FOUND, [(LOCATION, location)], "moved")
+                    }),
+                )
+                .route(
+                    "/secret",
+                    get(|| async {
+                        SECRET_HITS.fetch_add(1, Ordering::SeqCst);
+                        "secret"
+                    }),
+                ),
+        )
+        .await;
+        let port = *PORT.get_or_init(|| addr.port());
+        let backend = plugin("fetch", FETCH, json!({ "allowed_hosts": ["127.0.0.1"] })).await;
+        let runtime = get_wasm_runtime().unwrap();
+
+        let moved = runtime
+            .call(&tds(), &backend, &format!("http://{}/moved", addr))
+            .await
+            .unwrap();
+        assert_eq!(moved, "moved");
+        assert_eq!(SECRET_HITS.load(Ordering::SeqCst), 0);
+
+        let denied = runtime
+            .call(&tds(), &backend, &format!("http://localhost:{}/secret", port))
+            .await
+            .unwrap_err();
+        assert!(denied.to_string().contains("allowed_hosts"), "{}", denied);
+        assert_eq!(SECRET_HITS.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn oversized_components_are_refused() {
+        let pds = PDS::new(
+            "big".to_string(),
+            "big".to_string(),
+            &vec![0; MAX_WASM_MODULE_BYTES + 1],
+        );
+        let err = get_wasm_runtime().unwrap().check(&pds).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds"), "{}", err);
+    }
+}
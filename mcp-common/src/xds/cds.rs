@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::constants::cluster_consts::{
+        DEFAULT_HEALTHY_THRESHOLD, DEFAULT_HEALTH_CHECK_INTERVAL_MS,
+        DEFAULT_HEALTH_CHECK_TIMEOUT_MS, DEFAULT_UNHEALTHY_THRESHOLD,
+    },
+    provider::global_provider::{get_app_config, get_http_client},
+};
+
+/// A cluster of interchangeable upstream endpoints (Cluster Discovery
+/// Service). A TDS names it in `tds_ext_info.cluster` instead of setting a
+/// `domain`, and every call goes to one endpoint picked by `lb_policy`.
+///
+/// With a `health_check`, endpoints are probed in the background and taken
+/// out of rotation while unhealthy; when none is healthy, all of them are
+/// used again rather than failing every call. Endpoints whose circuit
+/// breaker is open are passed over the same way.
+///
+/// ```json
+/// {
+///   "name": "orders",
+///   "endpoints": [
+///     { "url": "https://orders-a.internal", "weight": 3 },
+///     { "url": "https://orders-b.internal" }
+///   ],
+///   "lb_policy": { "type": "consistent_hash", "argument": "customer_id" },
+///   "health_check": { "path": "/healthz", "interval_ms": 5000 }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CDS {
+    // The unique ID of the cluster
+    pub id: String,
+    // The name TDS entries reference the cluster by
+    pub name: String,
+    pub endpoints: Vec<ClusterEndpoint>,
+    #[serde(default)]
+    pub lb_policy: LbPolicy,
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterEndpoint {
+    // Base URL used in place of `domain`, e.g. "https://orders-a.internal"
+    pub url: String,
+    // Share of the calls relative to the other endpoints
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// How an endpoint is picked for a call.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LbPolicy {
+    // Weighted rotation
+    #[default]
+    RoundRobin,
+    // The endpoint with the fewest calls in flight for its weight
+    LeastRequests,
+    // The same endpoint for the same value of a tool argument; calls without
+    // the argument rotate
+    ConsistentHash { argument: String },
+}
+
+/// An active HTTP health check: `GET <endpoint url><path>`, healthy on a 2xx.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub path: String,
+    #[serde(default)]
+    pub interval_ms: Option<u64>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    // Failed probes in a row that take an endpoint out of rotation
+    #[serde(default)]
+    pub unhealthy_threshold: Option<u32>,
+    // Passed probes in a row that bring it back
+    #[serde(default)]
+    pub healthy_threshold: Option<u32>,
+    // TLS profile from `[http_client.tls_profiles]` used for the probes
+    #[serde(default)]
+    pub tls_profile: Option<String>,
+}
+
+impl CDS {
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(anyhow!("CDS validation failed: id is empty"));
+        }
+        if self.name.is_empty() {
+            return Err(anyhow!("CDS validation failed: name is empty"));
+        }
+        if self.endpoints.is_empty() {
+            return Err(anyhow!("CDS validation failed: cluster has no endpoints"));
+        }
+        let mut urls = HashSet::new();
+        for endpoint in &self.endpoints {
+            if !endpoint.url.starts_with("http://") && !endpoint.url.starts_with("https://") {
+                return Err(anyhow!(
+                    "CDS validation failed: endpoint url `{}` must be http(s)",
+                    endpoint.url
+                ));
+            }
+            if !urls.insert(endpoint.url.trim_end_matches('/')) {
+                return Err(anyhow!(
+                    "CDS validation failed: duplicate endpoint `{}`",
+                    endpoint.url
+                ));
+            }
+            get_http_client()?
+                .check_egress(&endpoint.url)
+                .map_err(|e| anyhow!("CDS validation failed: {}", e))?;
+            if endpoint.weight == 0 {
+                return Err(anyhow!(
+                    "CDS validation failed: weight of `{}` must be greater than zero",
+                    endpoint.url
+                ));
+            }
+        }
+        if let LbPolicy::ConsistentHash { argument } = &self.lb_policy {
+            if argument.is_empty() {
+                return Err(anyhow!(
+                    "CDS validation failed: consistent_hash needs an argument"
+                ));
+            }
+        }
+        if let Some(health_check) = &self.health_check {
+            health_check
+                .validate()
+                .map_err(|e| anyhow!("CDS validation failed: health_check: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+impl HealthCheck {
+    fn validate(&self) -> Result<()> {
+        if !self.path.starts_with('/') {
+            return Err(anyhow!("path must start with `/`"));
+        }
+        if self.interval_ms == Some(0)
+            || self.timeout_ms == Some(0)
+            || self.unhealthy_threshold == Some(0)
+            || self.healthy_threshold == Some(0)
+        {
+            return Err(anyhow!(
+                "interval_ms, timeout_ms and thresholds must be greater than zero"
+            ));
+        }
+        if let Some(profile) = &self.tls_profile {
+            if !get_app_config()?.http_client.tls_profiles.contains_key(profile) {
+                return Err(anyhow!("unknown tls_profile `{}`", profile));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn interval_ms(&self) -> u64 {
+        self.interval_ms.unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL_MS)
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms.unwrap_or(DEFAULT_HEALTH_CHECK_TIMEOUT_MS)
+    }
+
+    pub fn unhealthy_threshold(&self) -> u32 {
+        self.unhealthy_threshold.unwrap_or(DEFAULT_UNHEALTHY_THRESHOLD)
+    }
+
+    pub fn healthy_threshold(&self) -> u32 {
+        self.healthy_threshold.unwrap_or(DEFAULT_HEALTHY_THRESHOLD)
+    }
+}
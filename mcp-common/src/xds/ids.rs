@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 pub struct IDSMetadata {
     // legacy | streamable-stateless | streamable-stateful
     pub proto_type: String,
+    // Answers tool calls from the TDS mocks instead of the real upstreams
+    #[serde(default)]
+    pub mock_mode: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    backend::{backend::ToolBackend, mcp::McpBackend},
+    constants::constants::mcp_cache_consts::{ETCD_MDS_PREFIX, ETCD_TDS_PREFIX},
+    provider::global_provider::{get_app_config, get_http_client},
+    xds::tds::{TDSx, TDS},
+};
+
+/// An upstream MCP server whose tools are mirrored as TDS entries.
+///
+/// The server's `tools/list` is written through the DataSource as one TDS
+/// per tool, with an `mcp` backend calling back into it. Mirrored entries
+/// are regular tools: IDS instances attach them by id, which is
+/// `<MDS id>.<upstream tool name>` (under the TDS prefix on etcd).
+///
+/// ```json
+/// {
+///   "name": "jira",
+///   "url": "https://jira-mcp.internal/mcp",
+///   "headers": { "Authorization": "Bearer ..." },
+///   "tool_prefix": "jira_",
+///   "include": ["search_*", "get_issue"],
+///   "exclude": ["*_admin"],
+///   "resync_interval_ms": 300000
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MDS {
+    // The unique ID of the upstream server
+    pub id: String,
+    pub name: String,
+    // Streamable HTTP endpoint of the server
+    pub url: String,
+    // Headers sent with every request to the server, e.g. its auth
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // TLS profile from `[http_client.tls_profiles]` used for the server
+    #[serde(default)]
+    pub tls_profile: Option<String>,
+    // Prepended to the upstream tool names, e.g. "jira_"
+    #[serde(default)]
+    pub tool_prefix: String,
+    // Upstream tool names to mirror (`*` wildcards); empty mirrors all
+    #[serde(default)]
+    pub include: Vec<String>,
+    // Upstream tool names never mirrored, applied after `include`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    // Periodic resync; without it tools resync only on `list_changed`
+    #[serde(default)]
+    pub resync_interval_ms: Option<u64>,
+}
+
+impl MDS {
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(anyhow!("MDS validation failed: id is empty"));
+        }
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(anyhow!("MDS validation failed: url must be http(s)"));
+        }
+        get_http_client()?
+            .check_egress(&self.url)
+            .map_err(|e| anyhow!("MDS validation failed: {}", e))?;
+        if self.resync_interval_ms == Some(0) {
+            return Err(anyhow!(
+                "MDS validation failed: resync_interval_ms must be greater than zero"
+            ));
+        }
+        if let Some(profile) = &self.tls_profile {
+            if !get_app_config()?.http_client.tls_profiles.contains_key(profile) {
+                return Err(anyhow!(
+                    "MDS validation failed: unknown tls_profile `{}`",
+                    profile
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether an upstream tool passes the include and exclude filters.
+    pub fn mirrors(&self, tool: &str) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|p| wildcard_match(p, tool));
+        included && !self.exclude.iter().any(|p| wildcard_match(p, tool))
+    }
+
+    /// The TDS id of a mirrored upstream tool.
+    pub fn tool_id(&self, tool: &str) -> String {
+        // etcd only watches TDS keys under their prefix
+        match self.id.strip_prefix(ETCD_MDS_PREFIX) {
+            Some(rest) => format!("{}{}.{}", ETCD_TDS_PREFIX, rest, tool),
+            None => format!("{}.{}", self.id, tool),
+        }
+    }
+
+    /// Builds the TDS mirroring one entry of the server's `tools/list`.
+    pub fn mirror_tool(&self, tool: &Value) -> Result<TDS> {
+        let name = tool
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("upstream tool without a name"))?;
+        let input_schema = match tool.get("inputSchema") {
+            Some(Value::Object(schema)) => schema.clone().into_iter().collect(),
+            _ => HashMap::new(),
+        };
+        Ok(TDS {
+            id: self.tool_id(name),
+            name: format!("{}{}", self.tool_prefix, name),
+            description: tool
+                .get("description")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            input_schema,
+            tds_ext_info: TDSx {
+                domain: self.url.clone(),
+                method: "POST".to_string(),
+                tls_profile: self.tls_profile.clone(),
+                backend: ToolBackend::Mcp(McpBackend {
+                    tool: name.to_string(),
+                    headers: self.headers.clone(),
+                }),
+                mirrored_from: Some(self.id.clone()),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no `*` at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -1,2 +1,5 @@
+pub mod cds;
 pub mod ids;
-pub mod tds;
\ No newline at end of file
+pub mod mds;
+pub mod pds;
+pub mod tds;
@@ -0,0 +1,71 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::constants::constants::wasm_consts::MAX_WASM_MODULE_BYTES;
+
+/// A WASM tool plugin (Plugin Discovery Service): a WASI component run by
+/// TDS entries with a `wasm` backend, which name it by id.
+///
+/// The component is stored base64 encoded along with its SHA-256, so
+/// instances only recompile it when the digest changes. On MySQL the
+/// `xds_json` column must be MEDIUMTEXT or larger to hold it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PDS {
+    // The unique ID of the plugin
+    pub id: String,
+    pub name: String,
+    // The component binary (or text format), base64 encoded
+    pub wasm: String,
+    // Hex SHA-256 of the decoded component
+    pub sha256: String,
+}
+
+impl PDS {
+    /// Wraps an uploaded component.
+    pub fn new(id: String, name: String, wasm: &[u8]) -> Self {
+        Self {
+            id,
+            name,
+            wasm: STANDARD.encode(wasm),
+            sha256: hex::encode(Sha256::digest(wasm)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(anyhow!("PDS validation failed: id is empty"));
+        }
+        let wasm = self.decode()?;
+        if wasm.len() > MAX_WASM_MODULE_BYTES {
+            return Err(anyhow!(
+                "PDS validation failed: component exceeds {} bytes",
+                MAX_WASM_MODULE_BYTES
+            ));
+        }
+        if hex::encode(Sha256::digest(&wasm)) != self.sha256 {
+            return Err(anyhow!("PDS validation failed: sha256 does not match the component"));
+        }
+        Ok(())
+    }
+
+    /// The component bytes.
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        STANDARD
+            .decode(&self.wasm)
+            .map_err(|e| anyhow!("PDS validation failed: wasm is not base64: {}", e))
+    }
+
+    /// The plugin without its component, for listings.
+    pub fn summary(&self) -> Value {
+        let padding = self.wasm.bytes().rev().take_while(|b| *b == b'=').count();
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "sha256": self.sha256,
+            "size": (self.wasm.len() / 4 * 3).saturating_sub(padding),
+        })
+    }
+}
@@ -3,10 +3,29 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::{
+    backend::{backend::ToolBackend, mock::MockBackend},
+    cache::result_cache::ResultCacheConfig,
+    http_client::{
+        encoding::RequestEncoding,
+        model::{HttpCallPolicy, ResponseLimits},
+        pagination::Pagination,
+    },
+    grpc_client::grpc_client_provider::get_grpc_client,
+    provider::global_provider::{get_app_config, get_http_client},
+    rate_limit::rate_limiter::RateLimitConfig,
+    script::script_engine::ToolScripts,
+    transform::{body_template::BodyTemplate, response_transform::ResponseTransform},
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TDSx {
-    // The base domain of the API, e.g. "api.example.com"
+    // The base domain of the API, e.g. "api.example.com"; may be left empty with `cluster`
+    #[serde(default)]
     pub domain: String,
+    // Name of the CDS cluster whose endpoints replace `domain`, picked per call
+    #[serde(default)]
+    pub cluster: Option<String>,
     // The HTTP method, e.g. "GET" or "POST" d
     pub method: String,
     // The API path, e.g. "/v1/emails/:email_id"
@@ -15,6 +34,56 @@ pub struct TDSx {
     pub required_params: HashMap<String, Value>,
     // ext information about the API, such as authentication details
     pub ext_info: HashMap<String, Value>,
+    // TLS profile from `[http_client.tls_profiles]` used for this upstream, e.g. "mesh"
+    #[serde(default)]
+    pub tls_profile: Option<String>,
+    // Timeout and retry overrides for this tool's upstream calls
+    #[serde(default)]
+    pub call_policy: Option<HttpCallPolicy>,
+    // Field selection, renaming, truncation and templating of the upstream response
+    #[serde(default)]
+    pub response_transform: Option<ResponseTransform>,
+    // Builds the request body from flat tool arguments instead of `arguments.body`
+    #[serde(default)]
+    pub body_template: Option<BodyTemplate>,
+    // Wire encoding of the body: json (default), form, multipart, xml or text
+    #[serde(default)]
+    pub request_encoding: RequestEncoding,
+    // Overrides the Content-Type implied by `request_encoding`, e.g. "text/xml"
+    #[serde(default)]
+    pub content_type: Option<String>,
+    // Response size caps for this tool, overriding `[http_client.response_limits]`
+    #[serde(default)]
+    pub response_limits: Option<ResponseLimits>,
+    // Follows list pages (Link header, cursor, page or offset) and concatenates the items
+    #[serde(default)]
+    pub pagination: Option<Pagination>,
+    // Opt-in caching of successful results, for read-only lookups
+    #[serde(default)]
+    pub result_cache: Option<ResultCacheConfig>,
+    // In-flight and token-bucket limits for this tool and its upstream domain
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    // Upstream kind: REST over `domain` + `method` + `path` (default), GraphQL, gRPC, SQL, MCP, a workflow, a WASM plugin, a native tool or canned mock responses
+    #[serde(default)]
+    pub backend: ToolBackend,
+    // Rhai hooks run on the request before it is sent and on the response
+    #[serde(default)]
+    pub scripts: Option<ToolScripts>,
+    // Id of the MDS this entry is mirrored from; such entries are managed by the sync
+    #[serde(default)]
+    pub mirrored_from: Option<String>,
+    // Canned responses served instead of the backend while the calling IDS is in mock mode
+    #[serde(default)]
+    pub mock: Option<MockBackend>,
+}
+
+impl TDSx {
+    /// The upstream the `domain` rate limits count against: the cluster when
+    /// there is one, so its endpoints share them.
+    pub fn rate_limit_upstream(&self) -> &str {
+        self.cluster.as_deref().unwrap_or(&self.domain)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,9 +103,209 @@ pub struct TDS {
 impl TDS {
     pub fn validate(&self) -> Result<()> {
         if self.id.is_empty() {
-            Err(anyhow!("TDS validation failed: id is empty"))
-        } else {
-            Ok(())
+            return Err(anyhow!("TDS validation failed: id is empty"));
+        }
+        self.tds_ext_info
+            .backend
+            .validate()
+            .map_err(|e| anyhow!("TDS validation failed: backend: {}", e))?;
+        if self.tds_ext_info.pagination.is_some()
+            && !matches!(self.tds_ext_info.backend, ToolBackend::Http)
+        {
+            return Err(anyhow!(
+                "TDS validation failed: pagination is only supported for http backends"
+            ));
+        }
+        // a GraphQL request is always the JSON document built from the operation
+        if matches!(self.tds_ext_info.backend, ToolBackend::Graphql(_))
+            && (self.tds_ext_info.body_template.is_some()
+                || self.tds_ext_info.request_encoding != RequestEncoding::Json
+                || self.tds_ext_info.content_type.is_some())
+        {
+            return Err(anyhow!(
+                "TDS validation failed: body_template, request_encoding and content_type are not supported for graphql backends"
+            ));
+        }
+        if self.tds_ext_info.content_type.is_some()
+            && self.tds_ext_info.request_encoding == RequestEncoding::Multipart
+        {
+            return Err(anyhow!(
+                "TDS validation failed: content_type cannot be set for multipart bodies, the boundary comes from the encoding"
+            ));
+        }
+        if self.tds_ext_info.response_transform.is_some()
+            && matches!(self.tds_ext_info.backend, ToolBackend::Mcp(_))
+        {
+            return Err(anyhow!(
+                "TDS validation failed: response_transform is not supported for mcp backends"
+            ));
+        }
+        if let Some(cluster) = &self.tds_ext_info.cluster {
+            if cluster.is_empty() {
+                return Err(anyhow!("TDS validation failed: cluster is empty"));
+            }
+            if !matches!(
+                self.tds_ext_info.backend,
+                ToolBackend::Http
+                    | ToolBackend::Graphql(_)
+                    | ToolBackend::Grpc(_)
+                    | ToolBackend::Mcp(_)
+            ) {
+                return Err(anyhow!(
+                    "TDS validation failed: cluster is only supported for http, graphql, grpc and mcp backends"
+                ));
+            }
+        }
+        if !self.tds_ext_info.domain.is_empty()
+            && matches!(
+                self.tds_ext_info.backend,
+                ToolBackend::Http
+                    | ToolBackend::Graphql(_)
+                    | ToolBackend::Grpc(_)
+                    | ToolBackend::Mcp(_)
+            )
+        {
+            get_http_client()?
+                .check_egress(&self.tds_ext_info.domain)
+                .map_err(|e| anyhow!("TDS validation failed: {}", e))?;
+        }
+        if !self.tds_ext_info.domain.is_empty()
+            && matches!(self.tds_ext_info.backend, ToolBackend::Grpc(_))
+        {
+            get_grpc_client()
+                .check_endpoint(&self.tds_ext_info.domain)
+                .map_err(|e| anyhow!("TDS validation failed: {}", e))?;
+        }
+        if self.tds_ext_info.tls_profile.is_some()
+            && matches!(
+                self.tds_ext_info.backend,
+                ToolBackend::Grpc(_) | ToolBackend::Sql(_)
+            )
+        {
+            return Err(anyhow!(
+                "TDS validation failed: tls_profile is not supported for grpc and sql backends"
+            ));
+        }
+        if let Some(profile) = &self.tds_ext_info.tls_profile {
+            if !get_app_config()?.http_client.tls_profiles.contains_key(profile) {
+                return Err(anyhow!(
+                    "TDS validation failed: unknown tls_profile `{}`",
+                    profile
+                ));
+            }
+        }
+        if let Some(transform) = &self.tds_ext_info.response_transform {
+            transform
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: response_transform: {}", e))?;
+        }
+        if let Some(limits) = &self.tds_ext_info.response_limits {
+            limits
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: {}", e))?;
+        }
+        if let Some(pagination) = &self.tds_ext_info.pagination {
+            pagination
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: pagination: {}", e))?;
+        }
+        if let Some(result_cache) = &self.tds_ext_info.result_cache {
+            result_cache
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: result_cache: {}", e))?;
+        }
+        if let Some(rate_limit) = &self.tds_ext_info.rate_limit {
+            rate_limit
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: rate_limit: {}", e))?;
+        }
+        if let Some(scripts) = &self.tds_ext_info.scripts {
+            if scripts.pre_request.is_some()
+                && !matches!(self.tds_ext_info.backend, ToolBackend::Http)
+            {
+                return Err(anyhow!(
+                    "TDS validation failed: pre_request scripts are only supported for http backends"
+                ));
+            }
+            if scripts.post_response.is_some()
+                && matches!(self.tds_ext_info.backend, ToolBackend::Mcp(_))
+            {
+                return Err(anyhow!(
+                    "TDS validation failed: post_response scripts are not supported for mcp backends"
+                ));
+            }
+            scripts
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: scripts: {}", e))?;
+        }
+        if let Some(mock) = &self.tds_ext_info.mock {
+            mock.validate()
+                .map_err(|e| anyhow!("TDS validation failed: mock: {}", e))?;
+        }
+        if let Some(body_template) = &self.tds_ext_info.body_template {
+            body_template
+                .validate()
+                .map_err(|e| anyhow!("TDS validation failed: body_template: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::init_test_config;
+
+    fn tds(ext: Value) -> TDS {
+        let mut tds_ext_info = json!({
+            "domain": "http://127.0.0.1:8080",
+            "method": "POST",
+            "path": "/upload",
+            "required_params": {},
+            "ext_info": {}
+        });
+        tds_ext_info
+            .as_object_mut()
+            .unwrap()
+            .extend(ext.as_object().unwrap().clone());
+        serde_json::from_value(json!({
+            "id": "test/tool",
+            "name": "tool",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": tds_ext_info
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn multipart_bodies_keep_their_own_content_type() {
+        init_test_config();
+        assert!(tds(json!({ "request_encoding": "multipart" })).validate().is_ok());
+        let err = tds(json!({ "request_encoding": "multipart", "content_type": "text/plain" }))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("content_type"));
+        assert!(tds(json!({ "request_encoding": "xml", "content_type": "text/xml" }))
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn graphql_tools_build_their_own_body() {
+        init_test_config();
+        let graphql = json!({ "type": "graphql", "query": "query { me { id } }" });
+        assert!(tds(json!({ "backend": graphql })).validate().is_ok());
+        for ext in [
+            json!({ "body_template": { "template": "{}" } }),
+            json!({ "request_encoding": "form" }),
+            json!({ "content_type": "application/graphql" }),
+        ] {
+            let mut ext = ext;
+            ext["backend"] = graphql.clone();
+            assert!(tds(ext.clone()).validate().is_err(), "{}", ext);
         }
     }
 }
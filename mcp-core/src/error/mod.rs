@@ -1 +1,2 @@
-pub mod dyn_execute_error;
\ No newline at end of file
+pub mod dyn_execute_error;
+pub mod native_tool_error;
\ No newline at end of file
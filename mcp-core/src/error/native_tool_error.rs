@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+/// Failures of a native tool call, both reported as `isError` results.
+#[derive(Debug, Error)]
+pub enum NativeToolError {
+    #[error("Invalid arguments: {0}")]
+    Arguments(serde_json::Error),
+
+    #[error("{0}")]
+    Failed(anyhow::Error),
+}
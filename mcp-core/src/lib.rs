@@ -1,3 +1,23 @@
+// lets `#[mcp_tool]` expansions name `::mcp_core` from inside this crate too
+extern crate self as mcp_core;
+
 pub mod mcp;
 pub mod model;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod tool;
+
+/// What `#[mcp_tool]` expansions refer to, so the macro works in crates
+/// that do not depend on `ctor`, `schemars` or `serde` themselves. Not a
+/// public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use ::ctor;
+    pub use ::schemars;
+    pub use ::serde;
+    pub use ::serde_json;
+
+    pub use crate::{
+        error::native_tool_error::NativeToolError,
+        tool::native_tool::{register_native_tool, NativeTool},
+    };
+}
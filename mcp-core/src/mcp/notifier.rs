@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Relays the chunks of a streaming upstream response to the MCP client
+/// while the tool call runs.
+///
+/// Chunks go out as `notifications/progress` when the request carried a
+/// `progressToken`, else as `notifications/message` log entries. The final
+/// result still comes as the call's response.
+pub struct ToolNotifier {
+    tx: UnboundedSender<Value>,
+    progress_token: Option<Value>,
+    progress: AtomicU64,
+}
+
+impl ToolNotifier {
+    pub fn new(tx: UnboundedSender<Value>, progress_token: Option<Value>) -> Self {
+        Self {
+            tx,
+            progress_token,
+            progress: AtomicU64::new(0),
+        }
+    }
+
+    /// Sends one chunk of the output of `tool`.
+    pub fn chunk(&self, tool: &str, text: &str) {
+        let notification = match &self.progress_token {
+            Some(token) => {
+                let progress = self.progress.fetch_add(1, Ordering::Relaxed) + 1;
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": { "progressToken": token, "progress": progress, "message": text },
+                })
+            }
+            None => json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": { "level": "info", "logger": tool, "data": text },
+            }),
+        };
+        // a client that went away just misses the rest
+        let _ = self.tx.send(notification);
+    }
+}
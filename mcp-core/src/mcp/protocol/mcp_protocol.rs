@@ -9,7 +9,7 @@ use once_cell::sync::Lazy;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
-use crate::error::dyn_execute_error::DynExecuteError;
+use crate::{error::dyn_execute_error::DynExecuteError, mcp::notifier::ToolNotifier};
 
 static REGISTRY: Lazy<DashMap<String, Arc<dyn DynMCProtocol>>> = Lazy::new(DashMap::new);
 
@@ -22,14 +22,17 @@ pub struct Responsex {
     // initialize phase
     pub initialize_session_id: Option<String>,
 }
-impl Responsex {
-    pub fn default() -> Self {
+impl Default for Responsex {
+    fn default() -> Self {
         Responsex {
             http_status: 200,
             protocol_method: None,
             initialize_session_id: None,
         }
     }
+}
+
+impl Responsex {
     pub fn accepted() -> Self {
         Responsex {
             http_status: 202,
@@ -47,6 +50,10 @@ impl Responsex {
 pub struct Requestx<'a> {
     pub mcp_cache: &'a McpCache,
     pub ids_id: &'a str,
+    // `Mcp-Session-Id` of the request, absent before initialize
+    pub session_id: Option<&'a str>,
+    // set when the response can carry notifications ahead of the result
+    pub notifier: Option<&'a ToolNotifier>,
 }
 
 pub struct DynExecuteResult {
@@ -1,6 +1,6 @@
 use mcp_common::xds::tds::TDS;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -111,16 +111,45 @@ pub struct ToolCallParams {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolCallResult {
-    #[serde(rename = "isError")]
+    #[serde(rename = "isError", default)]
     pub is_error: bool,
     pub content: Vec<ToolContent>,
+    #[serde(
+        rename = "structuredContent",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub structured_content: Option<Value>,
+}
+
+impl ToolCallResult {
+    pub fn text(text: impl Into<String>, is_error: bool) -> Self {
+        ToolCallResult {
+            is_error,
+            content: vec![ToolContent {
+                content_type: "text".into(),
+                text: Some(text.into()),
+                extra: Map::new(),
+            }],
+            structured_content: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::text(message, true)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolContent {
     #[serde(rename = "type")]
     pub content_type: String,
-    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    // The other fields of non-text content, e.g. `data` and `mimeType` of an
+    // image or `resource` of an embedded resource, relayed from upstream MCP tools
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -14,7 +14,7 @@ use std::str::FromStr;
 /// ```
 /// use anyhow::Result;
 /// use std::str::FromStr;
-/// use your_crate::ProtocolMethod;
+/// use mcp_core::model::spec::protocol_method::ProtocolMethod;
 ///
 /// fn main() -> Result<()> {
 ///     // Enum to &str
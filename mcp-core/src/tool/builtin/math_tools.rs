@@ -0,0 +1,65 @@
+use anyhow::{anyhow, Result};
+use mcp_macro::mcp_tool;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    Sqrt,
+    Abs,
+    Round,
+}
+
+/// Applies an arithmetic operation to `a` (and `b` for binary operations).
+///
+/// Binary: add, subtract, multiply, divide, modulo, power. Unary: sqrt, abs,
+/// round (`b` decimal places, 0 by default).
+#[mcp_tool]
+async fn calculate(operation: Operation, a: f64, b: Option<f64>) -> Result<f64> {
+    let b_required = || b.ok_or_else(|| anyhow!("`b` is required for this operation"));
+    let result = match operation {
+        Operation::Add => a + b_required()?,
+        Operation::Subtract => a - b_required()?,
+        Operation::Multiply => a * b_required()?,
+        Operation::Divide | Operation::Modulo if b_required()? == 0.0 => {
+            return Err(anyhow!("division by zero"))
+        }
+        Operation::Divide => a / b_required()?,
+        Operation::Modulo => a % b_required()?,
+        Operation::Power => a.powf(b_required()?),
+        Operation::Sqrt if a < 0.0 => return Err(anyhow!("sqrt of a negative number")),
+        Operation::Sqrt => a.sqrt(),
+        Operation::Abs => a.abs(),
+        Operation::Round => {
+            let factor = 10f64.powi(b.unwrap_or(0.0) as i32);
+            (a * factor).round() / factor
+        }
+    };
+    if !result.is_finite() {
+        return Err(anyhow!("result is not a finite number"));
+    }
+    Ok(result)
+}
+
+/// Sums up a list of numbers and returns their count, sum, mean, min and max.
+#[mcp_tool]
+async fn summarize_numbers(numbers: Vec<f64>) -> Result<serde_json::Value> {
+    if numbers.is_empty() {
+        return Err(anyhow!("numbers is empty"));
+    }
+    let sum = numbers.iter().sum::<f64>();
+    Ok(serde_json::json!({
+        "count": numbers.len(),
+        "sum": sum,
+        "mean": sum / numbers.len() as f64,
+        "min": numbers.iter().copied().fold(f64::INFINITY, f64::min),
+        "max": numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    }))
+}
@@ -0,0 +1,4 @@
+//! Utility tools served in-process, without an upstream.
+mod math_tools;
+mod time_tools;
+mod uuid_tools;
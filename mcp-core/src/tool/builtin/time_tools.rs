@@ -0,0 +1,38 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use mcp_macro::mcp_tool;
+use serde_json::{json, Value};
+
+/// Returns the current date and time as RFC 3339 and Unix timestamps.
+///
+/// `utc_offset` (e.g. "+08:00") sets the offset of the RFC 3339 time, UTC by default.
+#[mcp_tool]
+async fn current_time(utc_offset: Option<String>) -> Result<Value> {
+    let offset = parse_offset(utc_offset.as_deref())?;
+    let now = Utc::now().with_timezone(&offset);
+    Ok(json!({
+        "rfc3339": now.to_rfc3339(),
+        "unix": now.timestamp(),
+        "unix_ms": now.timestamp_millis(),
+    }))
+}
+
+/// Converts a Unix timestamp in seconds to an RFC 3339 date and time.
+///
+/// `utc_offset` (e.g. "-05:00") sets the offset of the result, UTC by default.
+#[mcp_tool]
+async fn format_timestamp(unix: i64, utc_offset: Option<String>) -> Result<String> {
+    let offset = parse_offset(utc_offset.as_deref())?;
+    let time = DateTime::from_timestamp(unix, 0)
+        .ok_or_else(|| anyhow!("timestamp {} is out of range", unix))?;
+    Ok(time.with_timezone(&offset).to_rfc3339())
+}
+
+fn parse_offset(offset: Option<&str>) -> Result<FixedOffset> {
+    match offset {
+        None | Some("Z") | Some("UTC") => Ok(Utc.fix()),
+        Some(offset) => offset
+            .parse()
+            .map_err(|_| anyhow!("invalid utc_offset `{}`, expected e.g. +08:00", offset)),
+    }
+}
@@ -0,0 +1,15 @@
+use anyhow::{anyhow, Result};
+use mcp_macro::mcp_tool;
+use uuid::Uuid;
+
+const MAX_UUIDS: u32 = 100;
+
+/// Generates random (version 4) UUIDs, one by default and at most 100.
+#[mcp_tool]
+async fn generate_uuid(count: Option<u32>) -> Result<Vec<String>> {
+    let count = count.unwrap_or(1);
+    if count == 0 || count > MAX_UUIDS {
+        return Err(anyhow!("count must be between 1 and {}", MAX_UUIDS));
+    }
+    Ok((0..count).map(|_| Uuid::new_v4().to_string()).collect())
+}
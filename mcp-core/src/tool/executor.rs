@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use mcp_common::{
+    backend::backend::ToolBackend,
+    cache::result_cache::{get_result_cache, ToolResultCache},
+    cluster::cluster_manager::get_cluster_manager,
+    rate_limit::rate_limiter::get_rate_limiters,
+    recording::recorder::{get_recorder, RecordingMode},
+    xds::tds::TDS,
+};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use crate::{
+    mcp::protocol::mcp_protocol::Requestx, model::spec::protocol::ToolCallResult,
+    tool::{
+        fan_out_tool, graphql_tool, grpc_tool, http_tool, mcp_tool, mock_tool, native_tool,
+        sql_tool, wasm_tool, workflow_tool,
+    },
+};
+
+/// Runs a tool against its upstream and returns the result for the model.
+///
+/// Upstream problems (timeouts, error statuses, open circuits, bad templates)
+/// come back as `isError` results; `Err` is reserved for failures of dynmcp
+/// itself, which surface as JSON-RPC errors.
+pub async fn execute_tool(
+    tds: &TDS,
+    args: &HashMap<String, Value>,
+    reqx: &Requestx<'_>,
+) -> Result<ToolCallResult> {
+    // mock answers skip the result cache and limits meant for the real upstream
+    if mock_mode(reqx) {
+        debug!("mcp_protocol[tool/call] mock mode: {}", tds.name);
+        return mock_tool::execute_in_mock_mode(tds, args).await;
+    }
+    let cache_key = tds.tds_ext_info.result_cache.as_ref().and_then(|config| {
+        ToolResultCache::key(config, args, reqx.ids_id, reqx.session_id)
+            .map(|key| (config, key))
+    });
+    let Some((config, key)) = cache_key else {
+        return execute_limited(tds, args, reqx).await;
+    };
+
+    let cache = get_result_cache();
+    if let Some(cached) = cache.get(&tds.id, &key).await {
+        info!("mcp_protocol[tool/call] result cache hit: {}", tds.name);
+        return Ok(serde_json::from_value(cached)?);
+    }
+    debug!("mcp_protocol[tool/call] result cache miss: {}", tds.name);
+
+    let generation = cache.generation(&tds.id);
+    let result = execute_limited(tds, args, reqx).await?;
+    // only successful results are worth replaying
+    if !result.is_error {
+        cache
+            .insert(&tds.id, config, key, serde_json::to_value(&result)?, generation)
+            .await;
+    }
+    Ok(result)
+}
+
+/// Runs the tool once its concurrency and rate limits allow it, against an
+/// endpoint of its cluster when it names one.
+async fn execute_limited(
+    tds: &TDS,
+    args: &HashMap<String, Value>,
+    reqx: &Requestx<'_>,
+) -> Result<ToolCallResult> {
+    let tds_ext_info = &tds.tds_ext_info;
+    // held until the call completes
+    let _permits = match &tds_ext_info.rate_limit {
+        Some(config) => {
+            match get_rate_limiters()
+                .acquire(&tds.id, tds_ext_info.rate_limit_upstream(), config)
+                .await
+            {
+                Ok(permits) => permits,
+                Err(e) => {
+                    warn!("mcp_protocol[tool/call] {}: {}", tds.name, e);
+                    return Ok(ToolCallResult::error(e.to_string()));
+                }
+            }
+        }
+        None => Vec::new(),
+    };
+    let resolved;
+    // held until the call completes, counting the call against its endpoint
+    let (tds, _pick) = match &tds_ext_info.cluster {
+        Some(cluster) => {
+            let pick = match get_cluster_manager().pick(cluster, args) {
+                Ok(pick) => pick,
+                Err(e) => {
+                    warn!("mcp_protocol[tool/call] {}: {}", tds.name, e);
+                    return Ok(ToolCallResult::error(e.to_string()));
+                }
+            };
+            debug!(
+                "mcp_protocol[tool/call] {}: cluster {} endpoint {}",
+                tds.name,
+                cluster,
+                pick.url()
+            );
+            let mut tds = tds.clone();
+            tds.tds_ext_info.domain = pick.url().to_string();
+            resolved = tds;
+            (&resolved, Some(pick))
+        }
+        None => (tds, None),
+    };
+    let tds_ext_info = &tds.tds_ext_info;
+    if !is_replayed(&tds_ext_info.backend) && get_recorder()?.mode() == RecordingMode::Replay {
+        warn!("mcp_protocol[tool/call] {} is not replayed", tds.name);
+        return Ok(ToolCallResult::error(format!(
+            "Tool `{}` is not replayed: only http and graphql upstream calls are recorded",
+            tds.name
+        )));
+    }
+    match &tds_ext_info.backend {
+        ToolBackend::Http => http_tool::execute(tds, args, reqx).await,
+        ToolBackend::Graphql(graphql) => graphql_tool::execute(tds, graphql, args).await,
+        ToolBackend::Grpc(grpc) => grpc_tool::execute(tds, grpc, args).await,
+        ToolBackend::Sql(sql) => sql_tool::execute(tds, sql, args).await,
+        ToolBackend::Mcp(mcp) => mcp_tool::execute(tds, mcp, args).await,
+        ToolBackend::Workflow(workflow) => {
+            workflow_tool::execute(tds, workflow, args, reqx).await
+        }
+        ToolBackend::FanOut(fan_out) => fan_out_tool::execute(tds, fan_out, args, reqx).await,
+        ToolBackend::Wasm(wasm) => wasm_tool::execute(tds, wasm, args).await,
+        ToolBackend::Native(native) => native_tool::execute(tds, native, args).await,
+        ToolBackend::Mock(mock) => mock_tool::execute(tds, mock, args).await,
+    }
+}
+
+/// Whether a backend runs in replay mode: http and graphql calls are
+/// answered from the cassettes, workflows, fan-outs and WASM plugins run with
+/// their upstream calls replayed or refused, and native and mock tools make
+/// none. gRPC, SQL and MCP backends would reach their upstream directly.
+fn is_replayed(backend: &ToolBackend) -> bool {
+    !matches!(
+        backend,
+        ToolBackend::Grpc(_) | ToolBackend::Sql(_) | ToolBackend::Mcp(_)
+    )
+}
+
+/// Metadata of the IDS the call came through, as JSON (`null` if unavailable).
+pub fn ids_metadata(reqx: &Requestx<'_>) -> Value {
+    reqx.mcp_cache
+        .get_ids(reqx.ids_id)
+        .and_then(|ids| serde_json::from_str(&ids.metadata).ok())
+        .unwrap_or(Value::Null)
+}
+
+/// Whether the IDS the call came through is in mock mode.
+fn mock_mode(reqx: &Requestx<'_>) -> bool {
+    reqx.mcp_cache.is_mock_mode(reqx.ids_id)
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
+use mcp_common::{
+    backend::{
+        backend::ToolBackend,
+        fan_out::{FanOutBackend, FanOutMember, FanOutMerge},
+    },
+    provider::global_provider::get_http_client,
+    transform::template,
+    xds::tds::TDS,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    mcp::protocol::mcp_protocol::Requestx,
+    model::spec::protocol::ToolCallResult,
+    tool::{
+        executor::{execute_tool, ids_metadata},
+        upstream::{self, UpstreamResponse},
+        workflow_tool::parse_result,
+    },
+};
+
+/// What a member that succeeded returned.
+struct MemberOutput {
+    text: String,
+    // parsed JSON (or structured content) when there is any, else the text
+    result: Value,
+}
+
+/// Executes a fan-out tool: calls every member concurrently and merges what
+/// they return. Failed members are noted after the merged result.
+pub async fn execute(
+    tds: &TDS,
+    fan_out: &FanOutBackend,
+    args: &HashMap<String, Value>,
+    reqx: &Requestx<'_>,
+) -> Result<ToolCallResult> {
+    let ctx = &json!({ "args": args, "ids": ids_metadata(reqx) });
+    let branches = fan_out.members.iter().map(|member| async move {
+        let outcome = run_member(fan_out, member, args, ctx, reqx).await;
+        if let Err(message) = &outcome {
+            warn!(
+                "mcp_protocol[tool/call] fan-out {} member {} failed: {}",
+                tds.name, member.id, message
+            );
+        }
+        (member, outcome)
+    });
+
+    let mut outputs = Vec::new();
+    let mut failures = Vec::new();
+    match fan_out.merge {
+        FanOutMerge::FirstSuccess => {
+            // dropping the stream cancels the members still running
+            let mut running = branches.collect::<FuturesUnordered<_>>();
+            while let Some((member, outcome)) = running.next().await {
+                match outcome {
+                    Ok(output) => {
+                        outputs.push((member, output));
+                        break;
+                    }
+                    Err(message) => failures.push((member, message)),
+                }
+            }
+        }
+        FanOutMerge::Concat | FanOutMerge::Keyed => {
+            for (member, outcome) in join_all(branches).await {
+                match outcome {
+                    Ok(output) => outputs.push((member, output)),
+                    Err(message) => failures.push((member, message)),
+                }
+            }
+        }
+    }
+
+    if outputs.is_empty() {
+        let failures = failures
+            .iter()
+            .map(|(member, message)| format!("`{}`: {}", member.id, message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        return Ok(ToolCallResult::error(format!(
+            "All fan-out members failed: {}",
+            failures
+        )));
+    }
+    debug!(
+        "mcp_protocol[tool/call] fan-out {}: {} succeeded, {} failed",
+        tds.name,
+        outputs.len(),
+        failures.len()
+    );
+
+    let body = match fan_out.merge {
+        FanOutMerge::Concat => {
+            let mut items = Vec::new();
+            for (_, output) in outputs {
+                match output.result {
+                    Value::Array(results) => items.extend(results),
+                    result => items.push(result),
+                }
+            }
+            Value::Array(items).to_string()
+        }
+        FanOutMerge::Keyed => Value::Object(
+            outputs
+                .into_iter()
+                .map(|(member, output)| (member.id.clone(), output.result))
+                .collect::<Map<_, _>>(),
+        )
+        .to_string(),
+        FanOutMerge::FirstSuccess => outputs
+            .into_iter()
+            .next()
+            .map(|(_, output)| output.text)
+            .unwrap_or_default(),
+    };
+    let notes = failures
+        .iter()
+        .map(|(member, message)| format!("[member `{}` failed: {}]", member.id, message))
+        .collect();
+    let limits = get_http_client()?.response_limits(tds.tds_ext_info.response_limits.as_ref());
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status: StatusCode::OK,
+            body,
+            notes,
+            limits,
+        },
+    )
+    .await)
+}
+
+/// Calls a member's TDS within its timeout. Every failure, dynmcp's own
+/// included, is the member's and comes back as a message.
+async fn run_member(
+    fan_out: &FanOutBackend,
+    member: &FanOutMember,
+    args: &HashMap<String, Value>,
+    ctx: &Value,
+    reqx: &Requestx<'_>,
+) -> std::result::Result<MemberOutput, String> {
+    let Some(target) = reqx.mcp_cache.get_tds(&member.tool_id) else {
+        return Err(format!("unknown tool `{}`", member.tool_id));
+    };
+    // workflows may call fan-outs, so neither may be called from here
+    if matches!(
+        target.tds_ext_info.backend,
+        ToolBackend::FanOut(_) | ToolBackend::Workflow(_)
+    ) {
+        return Err(format!(
+            "tool `{}` is a fan-out or workflow, which do not nest in fan-outs",
+            member.tool_id
+        ));
+    }
+    let arguments = match &member.arguments {
+        Some(arguments) => match template::render_json(&Value::Object(arguments.clone()), ctx) {
+            Ok(Value::Object(arguments)) => arguments.into_iter().collect(),
+            Ok(_) => HashMap::new(),
+            Err(e) => return Err(e.to_string()),
+        },
+        None => args.clone(),
+    };
+
+    let timeout = fan_out.timeout(member);
+    let result = match tokio::time::timeout(
+        timeout,
+        Box::pin(execute_tool(&target, &arguments, reqx)),
+    )
+    .await
+    {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(_) => return Err(format!("timed out after {} ms", timeout.as_millis())),
+    };
+    let text = result
+        .content
+        .iter()
+        .filter_map(|content| content.text.as_deref())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if result.is_error {
+        return Err(text);
+    }
+    let result = match result.structured_content {
+        Some(structured) => structured,
+        None => parse_result(&text)?,
+    };
+    Ok(MemberOutput { text, result })
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use mcp_common::{
+    backend::graphql::{unwrap_response, GraphqlBackend},
+    http_client::model::HttpRequestOptions,
+    xds::tds::TDS,
+};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{model::spec::protocol::ToolCallResult, tool::upstream};
+
+/// Executes a GraphQL-backed tool: the stored operation is POSTed to
+/// domain + path with variables from the tool arguments. The answer is one
+/// JSON document unwrapped as a whole, so it is never relayed in chunks.
+pub async fn execute(
+    tds: &TDS,
+    graphql: &GraphqlBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let tds_ext_info = &tds.tds_ext_info;
+
+    // 1. build request
+    let url = format!("{}{}", tds_ext_info.domain, tds_ext_info.path);
+    let body = graphql.request_body(args)?;
+    debug!("mcp_protocol[tool/call] graphql url: {}", url);
+    debug!("mcp_protocol[tool/call] graphql body: {:?}", body);
+
+    let options = HttpRequestOptions::<Value> {
+        method: "POST".to_string(),
+        body: Some(body),
+        tls_profile: tds_ext_info.tls_profile.clone(),
+        call_policy: tds_ext_info.call_policy.clone(),
+        response_limits: tds_ext_info.response_limits.clone(),
+        ..Default::default()
+    };
+
+    // 2. call API
+    let mut response = match upstream::send(tds, &url, options, None).await? {
+        Ok(response) => response,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    // 3. unwrap `data`; GraphQL reports failures in `errors`, often with a 200
+    match unwrap_response(&response.body) {
+        Ok(data) => response.body = data.to_string(),
+        Err(message) => {
+            warn!("mcp_protocol[tool/call] {}", message);
+            let mut message = message;
+            for note in &response.notes {
+                message = format!("{} {}", message, note);
+            }
+            return Ok(ToolCallResult::error(message));
+        }
+    }
+
+    // 4. tool call result
+    Ok(upstream::finish(tds, args, response).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use mcp_common::{backend::backend::ToolBackend, test_util::{init_test_config, serve}};
+    use serde_json::json;
+
+    use super::*;
+
+    fn tds(domain: &str) -> (TDS, GraphqlBackend) {
+        let tds: TDS = serde_json::from_value(json!({
+            "id": "test/graphql",
+            "name": "graphql",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": {
+                "domain": domain,
+                "method": "POST",
+                "path": "/graphql",
+                "required_params": {},
+                "ext_info": {},
+                "backend": {
+                    "type": "graphql",
+                    "query": "query User($id: ID!) { user(id: $id) { id name } }",
+                    "variables": { "id": "user_id" }
+                }
+            }
+        }))
+        .unwrap();
+        let ToolBackend::Graphql(graphql) = tds.tds_ext_info.backend.clone() else {
+            panic!("not a graphql backend");
+        };
+        (tds, graphql)
+    }
+
+    #[tokio::test]
+    async fn data_is_the_result_and_errors_are_tool_errors() {
+        init_test_config();
+        // answers with the user asked for, or with an error when there is no id
+        let router = Router::new().route(
+            "/graphql",
+            post(|Json(body): Json<Value>| async move {
+                match body["variables"]["id"].as_str() {
+                    Some(id) => Json(json!({ "data": { "user": { "id": id, "name": "Ada" } } })),
+                    None => Json(json!({
+                        "data": null,
+                        "errors": [{ "message": "id is required", "path": ["user"] }]
+                    })),
+                }
+            }),
+        );
+        let (tds, graphql) = tds(&format!("http://{}", serve(router).await));
+
+        let args = HashMap::from([("user_id".to_string(), json!("7"))]);
+        let result = execute(&tds, &graphql, &args).await.unwrap();
+        assert!(!result.is_error);
+        assert_eq!(
+            result.content[0].text.as_deref(),
+            Some(r#"{"user":{"id":"7","name":"Ada"}}"#)
+        );
+
+        let result = execute(&tds, &graphql, &HashMap::new()).await.unwrap();
+        assert!(result.is_error);
+        assert_eq!(
+            result.content[0].text.as_deref(),
+            Some("GraphQL errors: id is required (at user)")
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use mcp_common::{
+    backend::grpc::{message_to_json, GrpcBackend},
+    grpc_client::grpc_client_provider::{get_grpc_client, GrpcCallOptions},
+    provider::global_provider::get_http_client,
+    xds::tds::TDS,
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    model::spec::protocol::ToolCallResult,
+    tool::upstream::{self, call_with_breaker, UpstreamResponse},
+};
+
+/// Executes a gRPC-backed tool: the arguments are transcoded into the request
+/// message of a unary method on the `domain` endpoint.
+pub async fn execute(
+    tds: &TDS,
+    grpc: &GrpcBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let tds_ext_info = &tds.tds_ext_info;
+
+    // 1. build request message
+    let method = grpc.method_descriptor()?;
+    let message = match grpc.request_message(args) {
+        Ok(message) => message,
+        Err(e) => return Ok(ToolCallResult::error(e.to_string())),
+    };
+    debug!(
+        "mcp_protocol[tool/call] grpc call: {} {}",
+        tds_ext_info.domain, grpc.method
+    );
+
+    // timeouts, retries and size caps follow the same settings as HTTP tools
+    let http_client = get_http_client()?;
+    let limits = http_client.response_limits(tds_ext_info.response_limits.as_ref());
+    let options = GrpcCallOptions {
+        metadata: grpc.metadata.clone(),
+        call_policy: http_client.call_policy(tds_ext_info.call_policy.as_ref()),
+        max_response_bytes: limits.max_response_bytes(),
+    };
+
+    // 2. call API, through the upstream's circuit breaker
+    let request = get_grpc_client().unary(&tds_ext_info.domain, &method, message, &options);
+    let result = match call_with_breaker(&tds_ext_info.domain, request, |e| {
+        e.is_upstream_failure()
+    })
+    .await?
+    {
+        Ok(result) => result,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    // 3. tool call result
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("mcp_protocol[tool/call] upstream call failed: {}", e);
+            return Ok(ToolCallResult::error(e.to_string()));
+        }
+    };
+    let body = message_to_json(&response)?.to_string();
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status: StatusCode::OK,
+            body,
+            notes: Vec::new(),
+            limits,
+        },
+    )
+    .await)
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use mcp_common::{
+    http_client::model::HttpRequestOptions,
+    script::script_engine::{get_script_engine, ScriptRequest},
+    xds::tds::TDS,
+};
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    mcp::protocol::mcp_protocol::Requestx,
+    model::spec::protocol::ToolCallResult,
+    tool::{executor::ids_metadata, upstream},
+};
+
+fn extract_required_args(
+    required_params: &HashMap<String, Value>,
+    args_value: Option<&Value>,
+) -> Result<HashMap<String, Value>> {
+    let args_map = match args_value {
+        Some(Value::Object(map)) => map,
+        Some(_) => {
+            return Err(anyhow!("Expected object for arguments"));
+        }
+        None => {
+            return Err(anyhow!("Missing arguments"));
+        }
+    };
+    let mut extracted = HashMap::new();
+    for key in required_params.keys() {
+        if let Some(value) = args_map.get(key) {
+            extracted.insert(key.clone(), value.clone());
+        } else {
+            return Err(anyhow!("Missing required parameter: {}", key));
+        }
+    }
+    Ok(extracted)
+}
+
+fn build_uri_from_pattern(
+    url_pattern: &str,
+    path_args: &HashMap<String, Value>,
+    query_args: &HashMap<String, Value>,
+) -> String {
+    let mut url = url_pattern.to_string();
+    for (key, value) in path_args {
+        let placeholder = format!("{{{}}}", key);
+        url = url.replace(&placeholder, value.as_str().unwrap_or_default());
+    }
+    if !query_args.is_empty() {
+        let query_string = query_args
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v.as_str().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("&");
+        url.push('?');
+        url.push_str(&query_string);
+    }
+    url
+}
+
+/// Fills the `{name}` placeholders of the TDS path and appends the other
+/// required params as the query. Values come from the `path` and `query`
+/// objects, falling back to flat top-level arguments; a param consumed by the
+/// path is never repeated in the query.
+fn build_uri(
+    path: &str,
+    required_params: &HashMap<String, Value>,
+    args: &HashMap<String, Value>,
+) -> Result<String> {
+    let (path_params, query_params): (HashMap<String, Value>, HashMap<String, Value>) =
+        required_params
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .partition(|(k, _)| path.contains(&format!("{{{}}}", k)));
+    let flat_args = json!(args);
+    // path parameters
+    let path_args = args.get("path").unwrap_or(&flat_args);
+    let extracted_path_args = extract_required_args(&path_params, Some(path_args))?;
+    // query parameters
+    let query_args = args.get("query").unwrap_or(&flat_args);
+    let extracted_query_args = extract_required_args(&query_params, Some(query_args))?;
+    Ok(build_uri_from_pattern(
+        path,
+        &extracted_path_args,
+        &extracted_query_args,
+    ))
+}
+
+/// Executes a REST-backed tool: domain + method + path from `tds_ext_info`.
+pub async fn execute(
+    tds: &TDS,
+    args: &HashMap<String, Value>,
+    reqx: &Requestx<'_>,
+) -> Result<ToolCallResult> {
+    let tds_ext_info = &tds.tds_ext_info;
+
+    // 1.build request url
+    let uri = build_uri(&tds_ext_info.path, &tds_ext_info.required_params, args)?;
+
+    // 2. build request body
+    let encoding = tds_ext_info.request_encoding;
+    let body = match &tds_ext_info.body_template {
+        Some(body_template) => {
+            // xml/text bodies are sent as rendered, everything else must be JSON
+            let rendered = if encoding.is_text() {
+                body_template
+                    .render_text(args, &ids_metadata(reqx))
+                    .map(Value::String)
+            } else {
+                body_template.render(args, &ids_metadata(reqx))
+            };
+            match rendered {
+                Ok(body) => Some(body),
+                Err(e) => {
+                    warn!("mcp_protocol[tool/call] body template failed: {}", e);
+                    return Ok(ToolCallResult::error(format!("Body template failed: {}", e)));
+                }
+            }
+        }
+        None => args.get("body").cloned(),
+    };
+
+    // 3. call API
+    // url + method + body
+    let url = format!("{}{}", tds_ext_info.domain, uri);
+    let method = tds_ext_info.method.clone();
+    debug!("mcp_protocol[tool/call] request url: {}", url);
+    debug!("mcp_protocol[tool/call] request method: {}", method);
+    debug!("mcp_protocol[tool/call] request body: {:?}", body);
+
+    // TODO: auth need rewrite
+    let headers = tds_ext_info
+        .content_type
+        .as_ref()
+        .map(|content_type| HashMap::from([("Content-Type".to_string(), content_type.clone())]));
+    let (url, method, headers, body) = match &tds_ext_info.scripts {
+        Some(scripts) if scripts.pre_request.is_some() => {
+            let request = ScriptRequest {
+                method,
+                url,
+                headers: headers.unwrap_or_default(),
+                body,
+            };
+            match get_script_engine()
+                .pre_request(&tds.id, scripts, args, request)
+                .await
+            {
+                Ok(request) => {
+                    debug!("mcp_protocol[tool/call] scripted request url: {}", request.url);
+                    let headers = (!request.headers.is_empty()).then_some(request.headers);
+                    (request.url, request.method, headers, request.body)
+                }
+                Err(e) => {
+                    warn!("mcp_protocol[tool/call] {}", e);
+                    return Ok(ToolCallResult::error(e.to_string()));
+                }
+            }
+        }
+        _ => (url, method, headers, body),
+    };
+    let toolcall_req = HttpRequestOptions::<Value> {
+        method,
+        headers,
+        body,
+        tls_profile: tds_ext_info.tls_profile.clone(),
+        call_policy: tds_ext_info.call_policy.clone(),
+        encoding,
+        response_limits: tds_ext_info.response_limits.clone(),
+        follow_redirects: true,
+    };
+
+    let response = match upstream::send(tds, &url, toolcall_req, reqx.notifier).await? {
+        Ok(response) => response,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    // 4. tool call result
+    Ok(upstream::finish(tds, args, response).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(names: &[&str]) -> HashMap<String, Value> {
+        names.iter().map(|n| (n.to_string(), json!({ "type": "string" }))).collect()
+    }
+
+    fn args(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn flat_args_fill_the_path_once() {
+        let uri = build_uri(
+            "/orders/{id}",
+            &params(&["id", "status"]),
+            &args(json!({ "id": "7", "status": "open" })),
+        )
+        .unwrap();
+        assert_eq!(uri, "/orders/7?status=open");
+    }
+
+    #[test]
+    fn path_and_query_objects_are_read_separately() {
+        let uri = build_uri(
+            "/orders/{id}",
+            &params(&["id", "status"]),
+            &args(json!({ "path": { "id": "7" }, "query": { "status": "open" } })),
+        )
+        .unwrap();
+        assert_eq!(uri, "/orders/7?status=open");
+    }
+
+    #[test]
+    fn missing_params_are_rejected() {
+        let err = build_uri("/orders/{id}", &params(&["id"]), &args(json!({}))).unwrap_err();
+        assert_eq!(err.to_string(), "Missing required parameter: id");
+    }
+}
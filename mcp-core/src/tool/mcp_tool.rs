@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use mcp_common::{
+    backend::mcp::McpBackend,
+    http_client::error::HttpCallError,
+    mcp_client::{
+        error::McpCallError,
+        mcp_client_provider::{get_mcp_client, McpCallOptions},
+    },
+    provider::global_provider::get_http_client,
+    xds::tds::TDS,
+};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    model::spec::protocol::ToolCallResult,
+    tool::upstream::{call_with_breaker, cap_relayed, error_result, is_upstream_failure},
+};
+
+/// Executes a tool proxied to an upstream MCP server: the arguments are
+/// forwarded with `tools/call` and the upstream result is relayed as is,
+/// within the result size cap.
+pub async fn execute(
+    tds: &TDS,
+    mcp: &McpBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let tds_ext_info = &tds.tds_ext_info;
+    let url = format!("{}{}", tds_ext_info.domain, tds_ext_info.path);
+    debug!("mcp_protocol[tool/call] upstream mcp call: {} {}", url, mcp.tool);
+
+    let options = McpCallOptions {
+        headers: mcp.headers.clone(),
+        tls_profile: tds_ext_info.tls_profile.clone(),
+        call_policy: tds_ext_info.call_policy.clone(),
+        response_limits: tds_ext_info.response_limits.clone(),
+    };
+
+    // 1. call upstream tool, through the upstream's circuit breaker
+    let request = get_mcp_client().call_tool(&url, &mcp.tool, args, &options);
+    let result = match call_with_breaker(&tds_ext_info.domain, request, is_upstream_failure).await? {
+        Ok(result) => result,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    // 2. tool call result
+    let limits = get_http_client()?.response_limits(options.response_limits.as_ref());
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            let message = match (
+                err.downcast_ref::<HttpCallError>(),
+                err.downcast_ref::<McpCallError>(),
+            ) {
+                (Some(call_err), _) => call_err.to_string(),
+                (_, Some(mcp_err)) => mcp_err.to_string(),
+                _ => return Err(err),
+            };
+            warn!("mcp_protocol[tool/call] upstream mcp call failed: {}", message);
+            return Ok(error_result(message, &limits));
+        }
+    };
+    match serde_json::from_value::<ToolCallResult>(result) {
+        Ok(mut result) => {
+            cap_relayed(&mut result, &limits);
+            Ok(result)
+        }
+        Err(e) => Ok(ToolCallResult::error(format!(
+            "Invalid upstream MCP tool result: {}",
+            e
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+    use mcp_common::{
+        circuit_breaker::circuit_breaker::get_circuit_breakers,
+        test_util::{init_test_config, serve},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// An MCP server whose tools answer `tools/call` with `result`, or with
+    /// a body that is not JSON-RPC when `result` is null.
+    async fn server(result: Value) -> String {
+        let router = Router::new().route(
+            "/mcp",
+            post(move |Json(message): Json<Value>| {
+                let result = result.clone();
+                async move {
+                    let Some(id) = message.get("id").cloned() else {
+                        return StatusCode::ACCEPTED.into_response();
+                    };
+                    let result = match message["method"].as_str() {
+                        Some("tools/call") if result.is_null() => {
+                            return "not json-rpc".into_response()
+                        }
+                        Some("tools/call") => result,
+                        _ => json!({}),
+                    };
+                    Json(json!({ "jsonrpc": "2.0", "id": id, "result": result })).into_response()
+                }
+            }),
+        );
+        format!("http://{}", serve(router).await)
+    }
+
+    fn tds(domain: &str) -> (TDS, McpBackend) {
+        let backend = json!({ "type": "mcp", "tool": "search" });
+        let tds: TDS = serde_json::from_value(json!({
+            "id": "test/mcp",
+            "name": "search",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": {
+                "domain": domain,
+                "method": "POST",
+                "path": "/mcp",
+                "required_params": {},
+                "ext_info": {},
+                "backend": backend,
+                "response_limits": { "max_result_bytes": 1024 }
+            }
+        }))
+        .unwrap();
+        (tds, serde_json::from_value(backend).unwrap())
+    }
+
+    #[tokio::test]
+    async fn relayed_results_are_capped() {
+        init_test_config();
+        let image = json!({ "type": "image", "data": "A".repeat(4096), "mimeType": "image/png" });
+        let domain = server(json!({
+            "content": [{ "type": "text", "text": "x".repeat(800) }, image],
+            "structuredContent": { "items": "y".repeat(2048) }
+        }))
+        .await;
+        let (tds, mcp) = tds(&domain);
+
+        let result = execute(&tds, &mcp, &HashMap::new()).await.unwrap();
+        assert!(!result.is_error);
+        assert_eq!(result.content.len(), 2);
+        assert_eq!(result.content[0].text.as_deref().map(str::len), Some(800));
+        assert_eq!(
+            result.content[1].text.as_deref(),
+            Some("[truncated: result exceeded 1024 bytes]")
+        );
+        assert!(result.structured_content.is_none());
+    }
+
+    #[tokio::test]
+    async fn invalid_upstream_answers_count_against_the_breaker() {
+        init_test_config();
+        let domain = server(Value::Null).await;
+        let (tds, mcp) = tds(&domain);
+
+        let result = execute(&tds, &mcp, &HashMap::new()).await.unwrap();
+        assert!(result.is_error);
+        let snapshot = get_circuit_breakers()
+            .unwrap()
+            .snapshots()
+            .into_iter()
+            .find(|s| s.key == domain)
+            .unwrap();
+        assert_eq!((snapshot.calls, snapshot.failure_rate), (1, 1.0));
+    }
+}
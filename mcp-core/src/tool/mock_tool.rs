@@ -0,0 +1,184 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use mcp_common::{
+    backend::{backend::ToolBackend, mock::MockBackend},
+    provider::global_provider::get_http_client,
+    transform::template,
+    xds::tds::TDS,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    model::spec::protocol::{ToolCallResult, ToolContent},
+    tool::{
+        native_tool,
+        upstream::{self, UpstreamResponse},
+    },
+};
+
+/// Answers a call from canned responses.
+pub async fn execute(
+    tds: &TDS,
+    mock: &MockBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let response = match mock.respond(args) {
+        Ok(Some(response)) => response,
+        Ok(None) => {
+            return Ok(ToolCallResult::error(format!(
+                "No mock response of `{}` matches the arguments",
+                tds.name
+            )))
+        }
+        Err(e) => {
+            warn!("mcp_protocol[tool/call] mock rule failed: {}", e);
+            return Ok(ToolCallResult::error(format!("Mock rule failed: {}", e)));
+        }
+    };
+    if let Some(latency_ms) = response.latency_ms {
+        // no longer than the tool's upstream calls may take
+        let policy = get_http_client()?.call_policy(tds.tds_ext_info.call_policy.as_ref());
+        let latency = Duration::from_millis(latency_ms)
+            .min(policy.timeout())
+            .min(policy.total_timeout());
+        tokio::time::sleep(latency).await;
+    }
+    let body = match template::render_json(&response.body, &json!({ "args": args })) {
+        Ok(Value::String(text)) => text,
+        Ok(Value::Null) => String::new(),
+        Ok(body) => body.to_string(),
+        Err(e) => return Ok(ToolCallResult::error(format!("Mock response failed: {}", e))),
+    };
+    let status = StatusCode::from_u16(response.status())?;
+    debug!("mcp_protocol[tool/call] mock response: {} {}", tds.name, status);
+
+    let limits = get_http_client()?.response_limits(tds.tds_ext_info.response_limits.as_ref());
+    if let Some(media_type) = response.media_type() {
+        // base64 data is not something to transform, but scripts and caps apply
+        let (body, is_error) = match upstream::post_response(tds, args, status, body).await {
+            Ok(response) => response,
+            Err(failed) => return Ok(failed),
+        };
+        let mut extra = Map::new();
+        extra.insert("data".into(), Value::String(body));
+        extra.insert("mimeType".into(), json!(response.content_type));
+        let mut result = ToolCallResult {
+            is_error,
+            content: vec![ToolContent {
+                content_type: media_type.into(),
+                text: None,
+                extra,
+            }],
+            structured_content: None,
+        };
+        upstream::cap_relayed(&mut result, &limits);
+        return Ok(result);
+    }
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status,
+            body,
+            notes: Vec::new(),
+            limits,
+        },
+    )
+    .await)
+}
+
+/// Runs a call made through an IDS in mock mode: the TDS `mock` answers,
+/// mock and native tools run as usual, and nothing else is called.
+pub async fn execute_in_mock_mode(
+    tds: &TDS,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    if let Some(mock) = &tds.tds_ext_info.mock {
+        return execute(tds, mock, args).await;
+    }
+    match &tds.tds_ext_info.backend {
+        ToolBackend::Mock(mock) => execute(tds, mock, args).await,
+        ToolBackend::Native(native) => native_tool::execute(tds, native, args).await,
+        _ => Ok(ToolCallResult::error(format!(
+            "Tool `{}` has no mock and is not called in mock mode",
+            tds.name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mcp_common::{http_client::model::HttpCallPolicy, test_util::init_test_config};
+
+    use super::*;
+
+    fn tds(response: Value, post_response: &str) -> (TDS, MockBackend) {
+        let tds: TDS = serde_json::from_value(json!({
+            "id": "test/mock",
+            "name": "mock",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": {
+                "domain": "",
+                "method": "GET",
+                "path": "",
+                "required_params": {},
+                "ext_info": {},
+                "backend": { "type": "mock", "default": response },
+                "scripts": { "post_response": post_response },
+                "response_limits": { "max_result_bytes": 64 }
+            }
+        }))
+        .unwrap();
+        let ToolBackend::Mock(mock) = tds.tds_ext_info.backend.clone() else {
+            panic!("not a mock backend");
+        };
+        (tds, mock)
+    }
+
+    #[tokio::test]
+    async fn latency_is_clamped_to_the_call_timeout() {
+        init_test_config();
+        let (mut tds, mock) = tds(json!({ "body": "late", "latency_ms": 60_000 }), "body");
+        tds.tds_ext_info.call_policy = Some(HttpCallPolicy {
+            timeout_ms: Some(20),
+            ..Default::default()
+        });
+        let started = std::time::Instant::now();
+        let result = execute(&tds, &mock, &HashMap::new()).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(result.content[0].text.as_deref(), Some("late"));
+    }
+
+    #[tokio::test]
+    async fn media_responses_go_through_post_response() {
+        init_test_config();
+        let (tds, mock) = tds(
+            json!({ "status": 500, "content_type": "image/png", "body": "iVBORw0K" }),
+            r#"is_error = false; "aGVsbG8=""#,
+        );
+        let result = execute(&tds, &mock, &HashMap::new()).await.unwrap();
+        assert!(!result.is_error);
+        assert_eq!(result.content.len(), 1);
+        assert_eq!(result.content[0].content_type, "image");
+        assert_eq!(result.content[0].extra["data"], "aGVsbG8=");
+    }
+
+    #[tokio::test]
+    async fn media_responses_are_capped() {
+        init_test_config();
+        let (tds, mock) = tds(
+            json!({ "content_type": "image/png", "body": "A".repeat(1024) }),
+            "body",
+        );
+        let result = execute(&tds, &mock, &HashMap::new()).await.unwrap();
+        assert_eq!(result.content.len(), 1);
+        assert_eq!(
+            result.content[0].text.as_deref(),
+            Some("[truncated: result exceeded 64 bytes]")
+        );
+    }
+}
@@ -0,0 +1,14 @@
+pub mod builtin;
+pub mod executor;
+pub mod fan_out_tool;
+pub mod graphql_tool;
+pub mod grpc_tool;
+pub mod http_tool;
+pub mod mcp_tool;
+pub mod mock_tool;
+pub mod native_tool;
+pub mod pagination;
+pub mod sql_tool;
+pub mod upstream;
+pub mod wasm_tool;
+pub mod workflow_tool;
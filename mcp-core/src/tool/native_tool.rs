@@ -0,0 +1,173 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use mcp_common::{
+    backend::{backend::ToolBackend, native::NativeBackend},
+    cache::mcp_cache::McpCache,
+    constants::constants::mcp_cache_consts::BUILTIN_TDS_PREFIX,
+    provider::global_provider::get_http_client,
+    xds::tds::{TDSx, TDS},
+};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::native_tool_error::NativeToolError,
+    model::spec::protocol::ToolCallResult,
+    tool::upstream::{self, UpstreamResponse},
+};
+
+static NATIVE_TOOLS: Lazy<DashMap<&'static str, NativeTool>> = Lazy::new(DashMap::new);
+
+pub type NativeToolFuture = Pin<Box<dyn Future<Output = Result<Value, NativeToolError>> + Send>>;
+
+/// A Rust function registered as a tool by `#[mcp_tool]`.
+#[derive(Clone, Copy)]
+pub struct NativeTool {
+    pub name: &'static str,
+    pub description: &'static str,
+    // JSON schema of the parameters, as generated by schemars
+    pub input_schema: fn() -> Value,
+    // Deserializes the arguments and runs the function
+    pub call: fn(Value) -> NativeToolFuture,
+}
+
+impl NativeTool {
+    /// The built-in TDS exposing this tool.
+    fn tds(&self) -> TDS {
+        let mut input_schema = match (self.input_schema)() {
+            Value::Object(schema) => schema.into_iter().collect::<HashMap<_, _>>(),
+            _ => HashMap::new(),
+        };
+        // the schema describes a generated struct, its name means nothing to clients
+        input_schema.remove("$schema");
+        input_schema.remove("title");
+        TDS {
+            id: format!("{}{}", BUILTIN_TDS_PREFIX, self.name),
+            name: self.name.to_string(),
+            description: self.description.to_string(),
+            input_schema,
+            tds_ext_info: TDSx {
+                backend: ToolBackend::Native(NativeBackend {
+                    tool: self.name.to_string(),
+                }),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+pub fn register_native_tool(tool: NativeTool) {
+    NATIVE_TOOLS.insert(tool.name, tool);
+}
+
+pub fn get_native_tool(name: &str) -> Option<NativeTool> {
+    NATIVE_TOOLS.get(name).map(|tool| *tool)
+}
+
+/// Adds a built-in TDS for every native tool to the cache, so IDS entries
+/// can list them by id (`builtin/<name>`).
+pub fn register_builtin_tds(mcp_cache: &McpCache) {
+    let tools = NATIVE_TOOLS.iter().map(|tool| *tool).collect::<Vec<_>>();
+    for tool in &tools {
+        mcp_cache.insert_builtin_tds(tool.tds());
+    }
+    info!("Registered {} native tools", tools.len());
+}
+
+/// Executes a native tool in-process; its output goes through the same
+/// transform and size limits as an upstream body.
+pub async fn execute(
+    tds: &TDS,
+    native: &NativeBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let Some(tool) = get_native_tool(&native.tool) else {
+        return Ok(ToolCallResult::error(format!(
+            "Unknown native tool `{}`",
+            native.tool
+        )));
+    };
+    debug!("mcp_protocol[tool/call] native tool call: {}", tool.name);
+    let arguments = Value::Object(args.clone().into_iter().collect::<Map<_, _>>());
+    let body = match (tool.call)(arguments).await {
+        Ok(Value::String(text)) => text,
+        Ok(output) => output.to_string(),
+        Err(e) => {
+            warn!("mcp_protocol[tool/call] native tool {} failed: {}", tool.name, e);
+            return Ok(ToolCallResult::error(e.to_string()));
+        }
+    };
+    let limits = get_http_client()?.response_limits(tds.tds_ext_info.response_limits.as_ref());
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status: StatusCode::OK,
+            body,
+            notes: Vec::new(),
+            limits,
+        },
+    )
+    .await)
+}
+
+#[cfg(test)]
+mod tests {
+    use mcp_common::test_util::init_test_config;
+    use serde_json::json;
+
+    use super::*;
+
+    fn args(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn registered_builtins_are_callable_through_their_tds() {
+        init_test_config();
+        let mcp_cache = McpCache::new();
+        register_builtin_tds(&mcp_cache);
+        let tds = mcp_cache.get_tds_by_name("calculate").expect("builtin TDS");
+        assert_eq!(tds.id, "builtin/calculate");
+        let ToolBackend::Native(native) = &tds.tds_ext_info.backend else {
+            panic!("not a native backend");
+        };
+
+        let sum = execute(&tds, native, &args(json!({ "operation": "add", "a": 2, "b": 3 })))
+            .await
+            .unwrap();
+        assert!(!sum.is_error);
+        assert_eq!(sum.content[0].text.as_deref(), Some("5.0"));
+
+        let missing = execute(&tds, native, &args(json!({ "operation": "add", "a": 2 })))
+            .await
+            .unwrap();
+        assert!(missing.is_error);
+    }
+
+    #[test]
+    fn data_source_tools_cannot_shadow_builtins() {
+        init_test_config();
+        let mcp_cache = McpCache::new();
+        register_builtin_tds(&mcp_cache);
+        let builtin = mcp_cache.get_tds_by_name("calculate").unwrap();
+
+        let mut named_alike = builtin.clone();
+        named_alike.id = "team/calculate".to_string();
+        assert!(mcp_cache.check_builtin_collision(&named_alike).is_err());
+        mcp_cache.insert_tds(named_alike.id.clone(), named_alike);
+
+        let mut reserved_id = builtin.clone();
+        reserved_id.name = "calc".to_string();
+        assert!(mcp_cache.check_builtin_collision(&reserved_id).is_err());
+        mcp_cache.insert_tds(reserved_id.id.clone(), reserved_id);
+
+        assert_eq!(mcp_cache.get_tds_by_name("calculate").unwrap().id, "builtin/calculate");
+        assert!(mcp_cache.get_tds_by_name("calc").is_none());
+        assert!(mcp_cache.get_tds("team/calculate").is_none());
+    }
+}
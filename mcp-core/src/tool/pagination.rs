@@ -0,0 +1,219 @@
+use anyhow::Result;
+use mcp_common::http_client::{
+    http_client_provider::HttpClientProvider,
+    model::{HttpRequestOptions, LimitedText},
+    pagination::Pagination,
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    model::spec::protocol::ToolCallResult,
+    tool::upstream::{call_with_breaker, is_upstream_failure},
+};
+
+/// Fetches up to `max_pages` pages starting at `url` and concatenates their
+/// items into one JSON array.
+///
+/// Returns the last page's status, the combined body and, when the listing
+/// was cut short, a note telling the model the result is partial. A first page
+/// that is not JSON (or was truncated) is returned unchanged.
+///
+/// Every page is a request of its own through the circuit breaker of
+/// `breaker_key`; the inner `Err` is the first page rejected by an open
+/// circuit.
+pub async fn fetch_pages(
+    http_client: &HttpClientProvider,
+    breaker_key: &str,
+    url: &str,
+    options: HttpRequestOptions<Value>,
+    pagination: &Pagination,
+) -> Result<std::result::Result<(StatusCode, LimitedText, Option<String>), ToolCallResult>> {
+    let max_pages = pagination.max_pages();
+    let max_items = pagination.max_items();
+    let mut page_url = pagination.first_url(url)?;
+    let mut items: Vec<Value> = Vec::new();
+    let mut pages = 0;
+    let mut status = StatusCode::OK;
+    // every page counts against the one response cap
+    let max_bytes = http_client
+        .response_limits(options.response_limits.as_ref())
+        .max_response_bytes();
+    let mut total_bytes = 0;
+
+    let note = loop {
+        let request = http_client.request_with_headers::<Value, LimitedText>(&page_url, options.clone());
+        let result = match call_with_breaker(breaker_key, request, is_upstream_failure).await? {
+            Ok(result) => result,
+            Err(rejected) if pages == 0 => return Ok(Err(rejected)),
+            Err(_) => {
+                break Some(format!(
+                    "[pagination: circuit open before page {}; returning {} items from {} pages]",
+                    pages + 1,
+                    items.len(),
+                    pages
+                ))
+            }
+        };
+        let (page_status, headers, body) = match result {
+            Ok(res) => res,
+            // the first page failing is the tool call failing
+            Err(e) if pages == 0 => return Err(e),
+            Err(e) => {
+                warn!("mcp_protocol[tool/call] page {} failed: {}", pages + 1, e);
+                break Some(format!(
+                    "[pagination: page {} failed ({}); returning {} items from {} pages]",
+                    pages + 1,
+                    e,
+                    items.len(),
+                    pages
+                ));
+            }
+        };
+        pages += 1;
+        debug!("mcp_protocol[tool/call] fetched page {}: {}", pages, page_url);
+
+        let page = match serde_json::from_str::<Value>(&body.text) {
+            Ok(page) if !body.truncated => page,
+            _ if pages == 1 => return Ok(Ok((page_status, body, None))),
+            _ => {
+                break Some(format!(
+                    "[pagination: page {} was not readable JSON; returning {} items from {} pages]",
+                    pages,
+                    items.len(),
+                    pages - 1
+                ))
+            }
+        };
+        status = page_status;
+
+        total_bytes += body.text.len();
+        if total_bytes > max_bytes {
+            break Some(format!(
+                "[pagination: stopped at max_response_bytes ({}) with {} items from {} pages; more results may be available]",
+                max_bytes,
+                items.len(),
+                pages - 1
+            ));
+        }
+
+        let page_items = pagination.items(&page)?;
+        let count = page_items.len();
+        items.extend(page_items);
+        let next = match pagination.next_url(&page_url, &headers, &page, count) {
+            Ok(next) => next,
+            Err(e) => {
+                warn!("mcp_protocol[tool/call] pagination stopped: {}", e);
+                break Some(format!(
+                    "[pagination: stopped after page {} ({}); returning {} items]",
+                    pages,
+                    e,
+                    items.len()
+                ));
+            }
+        };
+
+        if items.len() >= max_items {
+            let more = items.len() > max_items || next.is_some();
+            items.truncate(max_items);
+            break more.then(|| {
+                format!(
+                    "[pagination: stopped at max_items ({}) after {} pages; more results are available]",
+                    max_items, pages
+                )
+            });
+        }
+        match next {
+            None => break None,
+            Some(_) if pages >= max_pages => {
+                break Some(format!(
+                    "[pagination: stopped at max_pages ({}) with {} items; more results are available]",
+                    max_pages,
+                    items.len()
+                ))
+            }
+            Some(next) => page_url = next,
+        }
+    };
+
+    let body = LimitedText {
+        text: serde_json::to_string(&items)?,
+        truncated: false,
+    };
+    Ok(Ok((status, body, note)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::Query,
+        http::{header::LINK, HeaderMap, HeaderValue},
+        routing::get,
+        Router,
+    };
+    use mcp_common::{
+        http_client::model::ResponseLimits,
+        provider::global_provider::get_http_client,
+        test_util::{init_test_config, serve},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// Endless pages of ten 100-byte items, each linking to `next(page)`.
+    async fn listing(next: fn(u64) -> String) -> String {
+        let router = Router::new().route(
+            "/items",
+            get(move |Query(query): Query<std::collections::HashMap<String, u64>>| async move {
+                let page = query.get("page").copied().unwrap_or(1);
+                let mut headers = HeaderMap::new();
+                let link = format!("<{}>; rel=\"next\"", next(page));
+                headers.insert(LINK, HeaderValue::from_str(&link).unwrap());
+                (headers, json!(vec!["x".repeat(100); 10]).to_string())
+            }),
+        );
+        format!("http://{}/items", serve(router).await)
+    }
+
+    fn options(max_response_bytes: usize) -> HttpRequestOptions<Value> {
+        HttpRequestOptions {
+            response_limits: Some(ResponseLimits {
+                max_response_bytes: Some(max_response_bytes),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn link_pagination() -> Pagination {
+        serde_json::from_value(json!({ "style": "link_header", "max_pages": 10 })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn pages_share_one_byte_cap() {
+        init_test_config();
+        let url = listing(|page| format!("/items?page={}", page + 1)).await;
+        let (_, body, note) =
+            fetch_pages(&get_http_client().unwrap(), "pages", &url, options(2_500), &link_pagination())
+                .await
+                .unwrap()
+                .unwrap();
+        let items = serde_json::from_str::<Vec<Value>>(&body.text).unwrap();
+        assert_eq!(items.len(), 20);
+        assert!(note.unwrap().contains("max_response_bytes (2500)"));
+    }
+
+    #[tokio::test]
+    async fn links_to_other_origins_are_not_followed() {
+        init_test_config();
+        let url = listing(|_| "http://127.0.0.2:9/steal".to_string()).await;
+        let (_, body, note) =
+            fetch_pages(&get_http_client().unwrap(), "pages", &url, options(100_000), &link_pagination())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Value>>(&body.text).unwrap().len(), 10);
+        assert!(note.unwrap().contains("another origin"));
+    }
+}
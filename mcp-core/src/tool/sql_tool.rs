@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use mcp_common::{
+    backend::sql::SqlBackend,
+    provider::global_provider::get_http_client,
+    sql::sql_pool_provider::{get_sql_pools, SqlOutcome, SqlQuery},
+    xds::tds::TDS,
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    model::spec::protocol::ToolCallResult,
+    tool::upstream::{self, call_with_breaker, UpstreamResponse},
+};
+
+/// Executes a SQL-backed tool: the arguments are bound to the statement's
+/// placeholders and the rows come back as a JSON array.
+pub async fn execute(
+    tds: &TDS,
+    sql: &SqlBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let tds_ext_info = &tds.tds_ext_info;
+
+    // 1. bind arguments
+    let prepared = sql.prepare()?;
+    let query = SqlQuery {
+        sql: &prepared.sql,
+        params: sql.parameters(&prepared, args),
+        returns_rows: sql.returns_rows(),
+        read_only: sql.read_only,
+        max_rows: sql.max_rows(),
+        timeout: sql.timeout(),
+    };
+    debug!(
+        "mcp_protocol[tool/call] sql query on pool {}: {}",
+        sql.pool, prepared.sql
+    );
+
+    // 2. run query, through the circuit breaker of the pool: the database is
+    // the upstream, whatever domain the tool was registered under
+    let request = get_sql_pools().execute(&sql.pool, &query);
+    let result = match call_with_breaker(&breaker_key(sql), request, |e| e.is_upstream_failure())
+        .await?
+    {
+        Ok(result) => result,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    // 3. tool call result
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            warn!("mcp_protocol[tool/call] sql query failed: {}", e);
+            return Ok(ToolCallResult::error(e.to_string()));
+        }
+    };
+    let mut notes = Vec::new();
+    if let SqlOutcome::Rows {
+        truncated: true, ..
+    } = outcome
+    {
+        notes.push(format!(
+            "[truncated: query returned more than {} rows]",
+            query.max_rows
+        ));
+    }
+    let limits = get_http_client()?.response_limits(tds_ext_info.response_limits.as_ref());
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status: StatusCode::OK,
+            body: outcome.to_json().to_string(),
+            notes,
+            limits,
+        },
+    )
+    .await)
+}
+
+/// Circuit breaker key of the pool a SQL tool runs on.
+fn breaker_key(sql: &SqlBackend) -> String {
+    format!("sql:{}", sql.pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use mcp_common::{
+        circuit_breaker::circuit_breaker::get_circuit_breakers, test_util::init_test_config,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn tool(pool: &str, statement: &str, read_only: bool) -> (TDS, SqlBackend) {
+        let backend = json!({
+            "type": "sql",
+            "pool": pool,
+            "statement": statement,
+            "read_only": read_only
+        });
+        let tds: TDS = serde_json::from_value(json!({
+            "id": "test/sql",
+            "name": "sql",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": {
+                "domain": "reporting.internal",
+                "method": "POST",
+                "path": "/",
+                "required_params": {},
+                "ext_info": {},
+                "backend": backend
+            }
+        }))
+        .unwrap();
+        (tds, serde_json::from_value(backend).unwrap())
+    }
+
+    async fn run(pool: &str, statement: &str, read_only: bool, args: Value) -> ToolCallResult {
+        let (tds, sql) = tool(pool, statement, read_only);
+        let args = serde_json::from_value(args).unwrap();
+        execute(&tds, &sql, &args).await.unwrap()
+    }
+
+    fn text(result: &ToolCallResult) -> &str {
+        result.content[0].text.as_deref().unwrap()
+    }
+
+    #[tokio::test]
+    async fn statements_run_on_the_pool_behind_its_breaker() {
+        init_test_config();
+        let create = "CREATE TABLE IF NOT EXISTS sql_tool_orders (id INTEGER, total REAL)";
+        assert!(!run("local", create, false, json!({})).await.is_error);
+        let insert = "INSERT INTO sql_tool_orders (id, total) VALUES (:id, :total)";
+        for (id, total) in [(1, 5.0), (2, 50.0)] {
+            let result = run("local", insert, false, json!({ "id": id, "total": total })).await;
+            assert_eq!(text(&result), r#"{"rows_affected":1}"#);
+        }
+
+        let select = "SELECT id FROM sql_tool_orders WHERE total > :min ORDER BY id";
+        let result = run("ro", select, true, json!({ "min": 10 })).await;
+        assert!(!result.is_error);
+        assert_eq!(text(&result), r#"[{"id":2}]"#);
+
+        let keys = get_circuit_breakers()
+            .unwrap()
+            .snapshots()
+            .into_iter()
+            .map(|s| s.key)
+            .collect::<Vec<_>>();
+        assert!(keys.contains(&"sql:local".to_string()));
+        assert!(keys.contains(&"sql:ro".to_string()));
+        assert!(!keys.contains(&"reporting.internal".to_string()));
+    }
+
+    #[tokio::test]
+    async fn writes_hidden_in_reading_statements_are_refused() {
+        init_test_config();
+        let create = "CREATE TABLE IF NOT EXISTS sql_tool_notes (body TEXT)";
+        assert!(!run("local", create, false, json!({})).await.is_error);
+        let write = "WITH x AS (SELECT 1) INSERT INTO sql_tool_notes (body) SELECT 'x' FROM x";
+        let result = run("ro", write, true, json!({})).await;
+        assert!(result.is_error);
+        let count = "SELECT COUNT(*) AS n FROM sql_tool_notes";
+        assert_eq!(text(&run("ro", count, true, json!({})).await), r#"[{"n":0}]"#);
+    }
+}
@@ -0,0 +1,466 @@
+use std::{collections::HashMap, future::Future};
+
+use anyhow::Result;
+use mcp_common::{
+    circuit_breaker::circuit_breaker::get_circuit_breakers,
+    http_client::{
+        error::HttpCallError,
+        http_client_provider::HttpClientProvider,
+        model::{HttpRequestOptions, HttpResponseFormat, LimitedText, ResponseLimits},
+        stream::{read_items, StreamFormat},
+    },
+    mcp_client::error::McpCallError,
+    provider::global_provider::get_http_client,
+    recording::recorder::{get_recorder, RecordingMode},
+    script::script_engine::get_script_engine,
+    xds::tds::TDS,
+};
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    mcp::notifier::ToolNotifier,
+    model::spec::protocol::{ToolCallResult, ToolContent},
+    tool::pagination::fetch_pages,
+};
+
+/// An upstream response on its way to becoming a tool result.
+pub struct UpstreamResponse {
+    pub status: StatusCode,
+    pub body: String,
+    // marks for partial data, appended after the result text
+    pub notes: Vec<String>,
+    pub limits: ResponseLimits,
+}
+
+/// Sends a tool's HTTP request through its circuit breaker, following
+/// pagination when the TDS declares it. In recording mode the exchange is
+/// written to the tool's cassette; in replay mode it is answered from there.
+/// With a `notifier`, NDJSON and event-stream bodies are relayed to the
+/// client as they arrive.
+///
+/// The inner `Err` is a finished `isError` result (open circuit, timeout,
+/// error status); the outer one is a failure of dynmcp itself.
+pub async fn send(
+    tds: &TDS,
+    url: &str,
+    options: HttpRequestOptions<Value>,
+    notifier: Option<&ToolNotifier>,
+) -> Result<std::result::Result<UpstreamResponse, ToolCallResult>> {
+    let tds_ext_info = &tds.tds_ext_info;
+    let http_client = get_http_client()?;
+    let limits = http_client.response_limits(tds_ext_info.response_limits.as_ref());
+
+    let recorder = get_recorder()?;
+    let recorded_request = match recorder.mode() {
+        RecordingMode::Off => None,
+        _ => Some(recorder.request(url, &options)),
+    };
+    let call_result = match (recorder.mode(), recorded_request) {
+        // replay never reaches the network
+        (RecordingMode::Replay, Some(request)) => {
+            match recorder.replay(&tds.id, &request).await? {
+                Some(response) => {
+                    debug!("mcp_protocol[tool/call] replayed response: {}", request.url);
+                    response.to_call_result()
+                }
+                None => {
+                    warn!(
+                        "mcp_protocol[tool/call] no recording of {}: {} {}",
+                        tds.name, request.method, request.url
+                    );
+                    return Ok(Err(ToolCallResult::error(format!(
+                        "No recorded response of `{}` matches {} {}",
+                        tds.name, request.method, request.url
+                    ))));
+                }
+            }
+        }
+        (_, recorded_request) => {
+            let call_result = match &tds_ext_info.pagination {
+                Some(pagination) => {
+                    match fetch_pages(&http_client, &tds_ext_info.domain, url, options, pagination)
+                        .await
+                    {
+                        Ok(Err(rejected)) => return Ok(Err(rejected)),
+                        Ok(Ok(res)) => Ok(res),
+                        Err(err) => Err(err),
+                    }
+                }
+                None => {
+                    let request = async {
+                        match notifier {
+                            Some(notifier) => {
+                                let max_bytes = limits.max_response_bytes();
+                                fetch_relayed(&http_client, tds, url, options, max_bytes, notifier)
+                                    .await
+                            }
+                            None => http_client
+                                .request_uri::<Value, LimitedText>(url, options)
+                                .await
+                                .map(|(status, body)| (status, body, None)),
+                        }
+                    };
+                    match call_with_breaker(&tds_ext_info.domain, request, is_upstream_failure)
+                        .await?
+                    {
+                        Ok(call_result) => call_result,
+                        Err(rejected) => return Ok(Err(rejected)),
+                    }
+                }
+            };
+            if let Some(request) = recorded_request {
+                // a lost recording must not fail the call
+                if let Err(e) = recorder.record(&tds.id, request, &call_result).await {
+                    warn!("mcp_protocol[tool/call] recording failed: {}", e);
+                }
+            }
+            call_result
+        }
+    };
+
+    // upstream timeouts and error statuses are tool errors, not server errors
+    let (status, body, pagination_note) = match call_result {
+        Ok(res) => res,
+        Err(err) => {
+            return match err.downcast_ref::<HttpCallError>() {
+                // a post_response script decides what an error status means
+                Some(HttpCallError::Status { status, body }) if has_post_response(tds) => {
+                    Ok(Ok(UpstreamResponse {
+                        status: *status,
+                        body: body.clone(),
+                        notes: Vec::new(),
+                        limits,
+                    }))
+                }
+                Some(call_err) => {
+                    warn!("mcp_protocol[tool/call] upstream call failed: {}", call_err);
+                    Ok(Err(error_result(call_err.to_string(), &limits)))
+                }
+                None => Err(err),
+            }
+        }
+    };
+    debug!("mcp_protocol[tool/call] response status: {}", status);
+    debug!("mcp_protocol[tool/call] response body: {:?}", body.text);
+
+    let mut notes = Vec::new();
+    if body.truncated {
+        warn!(
+            "mcp_protocol[tool/call] upstream response truncated at {} bytes: {}",
+            limits.max_response_bytes(),
+            url
+        );
+        notes.push(format!(
+            "[truncated: upstream response exceeded {} bytes]",
+            limits.max_response_bytes()
+        ));
+    }
+    notes.extend(pagination_note);
+
+    Ok(Ok(UpstreamResponse {
+        status,
+        body: body.text,
+        notes,
+        limits,
+    }))
+}
+
+/// Runs one upstream request through the circuit breaker of `key`, recording
+/// its outcome and latency. The inner `Err` is the `isError` result of a call
+/// rejected by an open circuit.
+pub async fn call_with_breaker<T, E>(
+    key: &str,
+    request: impl Future<Output = std::result::Result<T, E>>,
+    is_failure: impl FnOnce(&E) -> bool,
+) -> Result<std::result::Result<std::result::Result<T, E>, ToolCallResult>> {
+    let permit = match get_circuit_breakers()?.get(key) {
+        Some(breaker) => match breaker.try_acquire() {
+            Some(permit) => Some(permit),
+            None => {
+                warn!("mcp_protocol[tool/call] circuit open for upstream: {}", key);
+                return Ok(Err(ToolCallResult::error(format!(
+                    "Circuit open for upstream `{}`, call rejected",
+                    key
+                ))));
+            }
+        },
+        None => None,
+    };
+    // a request dropped before it finishes is not counted; the permit's drop
+    // only frees its probe slot
+    let result = request.await;
+    if let Some(permit) = permit {
+        permit.record(result.as_ref().is_err_and(is_failure));
+    }
+    Ok(Ok(result))
+}
+
+/// Whether an HTTP or upstream MCP call failed because of the upstream,
+/// rather than the request.
+pub fn is_upstream_failure(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<HttpCallError>()
+        .is_some_and(HttpCallError::is_upstream_failure)
+        || err
+            .downcast_ref::<McpCallError>()
+            .is_some_and(McpCallError::is_upstream_failure)
+}
+
+/// Sends the request and, when the upstream streams its answer (NDJSON,
+/// event-stream), hands every item to the notifier as it arrives. Other
+/// bodies are read whole.
+async fn fetch_relayed(
+    http_client: &HttpClientProvider,
+    tds: &TDS,
+    url: &str,
+    options: HttpRequestOptions<Value>,
+    max_bytes: usize,
+    notifier: &ToolNotifier,
+) -> Result<(StatusCode, LimitedText, Option<String>)> {
+    let resp = http_client.request_stream(url, options).await?;
+    let status = resp.status();
+    let body = match StreamFormat::of(&resp) {
+        Some(format) => {
+            debug!("mcp_protocol[tool/call] relaying {:?} response: {}", format, url);
+            read_items(resp, format, max_bytes, |item| notifier.chunk(&tds.name, item)).await?
+        }
+        None => LimitedText::from_response(resp, max_bytes).await?,
+    };
+    Ok((status, body, None))
+}
+
+/// Applies the TDS `post_response` script, response transform and the
+/// result size cap.
+pub async fn finish(
+    tds: &TDS,
+    args: &HashMap<String, Value>,
+    response: UpstreamResponse,
+) -> ToolCallResult {
+    let UpstreamResponse {
+        status,
+        body,
+        mut notes,
+        limits,
+    } = response;
+
+    let (body, is_error) = match post_response(tds, args, status, body).await {
+        Ok(response) => response,
+        Err(failed) => return failed,
+    };
+
+    let mut text = match &tds.tds_ext_info.response_transform {
+        Some(transform) => match transform.apply(&body) {
+            Ok((text, false)) => text,
+            Ok((text, true)) => {
+                notes.push(format!(
+                    "[truncated: arrays longer than {} items were cut]",
+                    transform.max_array_items.unwrap_or_default()
+                ));
+                text
+            }
+            Err(e) => {
+                warn!("mcp_protocol[tool/call] response transform failed: {}", e);
+                let mut message = format!("Response transform failed: {}", e);
+                for note in &notes {
+                    message = format!("{} {}", message, note);
+                }
+                return ToolCallResult::error(message);
+            }
+        },
+        None => body,
+    };
+
+    // cap what is handed back to the model, marking every cut
+    cap_result(&mut text, &limits, &mut notes);
+    for note in notes {
+        text = format!("{}\n{}", text, note);
+    }
+    ToolCallResult::text(text, is_error)
+}
+
+/// Runs the TDS `post_response` script, if any, on a response body. Returns
+/// the body and whether it is an error, or the error result of a failed run.
+pub async fn post_response(
+    tds: &TDS,
+    args: &HashMap<String, Value>,
+    status: StatusCode,
+    body: String,
+) -> Result<(String, bool), ToolCallResult> {
+    let is_error = !status.is_success();
+    let Some(scripts) = tds
+        .tds_ext_info
+        .scripts
+        .as_ref()
+        .filter(|scripts| scripts.post_response.is_some())
+    else {
+        return Ok((body, is_error));
+    };
+    match get_script_engine()
+        .post_response(&tds.id, scripts, args, status.as_u16(), body, is_error)
+        .await
+    {
+        Ok(response) => Ok((response.text, response.is_error)),
+        Err(e) => {
+            warn!("mcp_protocol[tool/call] {}", e);
+            Err(ToolCallResult::error(e.to_string()))
+        }
+    }
+}
+
+/// An `isError` result for a failed upstream call, capped like any other
+/// result: error bodies can be as large as `max_response_bytes`.
+pub fn error_result(message: String, limits: &ResponseLimits) -> ToolCallResult {
+    let mut text = message;
+    let mut notes = Vec::new();
+    cap_result(&mut text, limits, &mut notes);
+    for note in notes {
+        text = format!("{}\n{}", text, note);
+    }
+    ToolCallResult::error(text)
+}
+
+/// Caps a result relayed as is, e.g. from an upstream MCP tool, to
+/// `max_result_bytes`: text is cut like any other result and other content
+/// (images, audio, resources) that does not fit in what is left is dropped.
+pub fn cap_relayed(result: &mut ToolCallResult, limits: &ResponseLimits) {
+    let max_result_bytes = limits.max_result_bytes();
+    let mut left = max_result_bytes;
+    let mut cut = false;
+    result.content.retain_mut(|content| {
+        let size = content_size(content);
+        if size <= left {
+            left -= size;
+            return true;
+        }
+        cut = true;
+        match &mut content.text {
+            Some(text) if content.extra.is_empty() && left > 0 => {
+                text.truncate(floor_char_boundary(text, left));
+                left = 0;
+                true
+            }
+            _ => false,
+        }
+    });
+    // structured content repeats the text content, so it gets the cap on its own
+    if result
+        .structured_content
+        .as_ref()
+        .is_some_and(|value| value.to_string().len() > max_result_bytes)
+    {
+        result.structured_content = None;
+        cut = true;
+    }
+    if cut {
+        result.content.push(ToolContent {
+            content_type: "text".into(),
+            text: Some(format!(
+                "[truncated: result exceeded {} bytes]",
+                max_result_bytes
+            )),
+            extra: Map::new(),
+        });
+    }
+}
+
+fn content_size(content: &ToolContent) -> usize {
+    let extra = match content.extra.is_empty() {
+        true => 0,
+        false => serde_json::to_string(&content.extra).map_or(0, |s| s.len()),
+    };
+    content.text.as_ref().map_or(0, String::len) + extra
+}
+
+fn cap_result(text: &mut String, limits: &ResponseLimits, notes: &mut Vec<String>) {
+    let max_result_bytes = limits.max_result_bytes();
+    if text.len() > max_result_bytes {
+        text.truncate(floor_char_boundary(text, max_result_bytes));
+        notes.push(format!(
+            "[truncated: result exceeded {} bytes]",
+            max_result_bytes
+        ));
+    }
+}
+
+fn has_post_response(tds: &TDS) -> bool {
+    tds.tds_ext_info
+        .scripts
+        .as_ref()
+        .is_some_and(|scripts| scripts.post_response.is_some())
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    (0..=index.min(text.len()))
+        .rev()
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use mcp_common::{
+        circuit_breaker::circuit_breaker::CircuitState, test_util::init_test_config,
+    };
+
+    use super::*;
+
+    fn state(key: &str) -> (CircuitState, usize, f64) {
+        let snapshot = get_circuit_breakers()
+            .unwrap()
+            .snapshots()
+            .into_iter()
+            .find(|s| s.key == key)
+            .unwrap();
+        (snapshot.state, snapshot.calls, snapshot.failure_rate)
+    }
+
+    fn state_or_closed(key: &str) -> CircuitState {
+        get_circuit_breakers()
+            .unwrap()
+            .snapshots()
+            .into_iter()
+            .find(|s| s.key == key)
+            .map_or(CircuitState::Closed, |s| s.state)
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_call_is_not_counted() {
+        init_test_config();
+        let key = "http://cancelled.test";
+        let call = call_with_breaker(key, std::future::pending::<Result<()>>(), |_| true);
+        assert!(tokio::time::timeout(Duration::from_millis(10), call).await.is_err());
+        assert_eq!(state(key), (CircuitState::Closed, 0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn an_open_circuit_rejects_the_call() {
+        init_test_config();
+        let key = "http://failing.test";
+        let mut failed = 0;
+        while state_or_closed(key) == CircuitState::Closed {
+            let result = call_with_breaker(key, async { Err::<(), ()>(()) }, |_| true)
+                .await
+                .unwrap();
+            assert!(result.is_ok_and(|r| r.is_err()));
+            failed += 1;
+            assert!(failed <= 50, "circuit never opened");
+        }
+        let rejected = call_with_breaker(key, async { Ok::<(), ()>(()) }, |_| true)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(rejected.is_error);
+    }
+
+    #[test]
+    fn error_bodies_are_capped_like_results() {
+        let result = error_result("x".repeat(1024 * 1024), &ResponseLimits::default());
+        let text = result.content[0].text.as_deref().unwrap();
+        assert!(result.is_error);
+        assert!(text.len() < 101 * 1024, "{} bytes", text.len());
+        assert!(text.ends_with("[truncated: result exceeded 102400 bytes]"));
+    }
+}
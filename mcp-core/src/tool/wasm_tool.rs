@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use mcp_common::{
+    backend::wasm::WasmBackend, provider::global_provider::get_http_client,
+    wasm::wasm_runtime::get_wasm_runtime, xds::tds::TDS,
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    model::spec::protocol::ToolCallResult,
+    tool::upstream::{self, UpstreamResponse},
+};
+
+/// Executes a WASM plugin tool: the arguments go in as a JSON string and
+/// the string the plugin returns is the result.
+pub async fn execute(
+    tds: &TDS,
+    wasm: &WasmBackend,
+    args: &HashMap<String, Value>,
+) -> Result<ToolCallResult> {
+    let arguments = serde_json::to_string(args)?;
+    debug!(
+        "mcp_protocol[tool/call] wasm plugin {} call: {}",
+        wasm.plugin, tds.name
+    );
+    let body = match get_wasm_runtime()?.call(tds, wasm, &arguments).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("mcp_protocol[tool/call] wasm plugin {} failed: {}", wasm.plugin, e);
+            return Ok(ToolCallResult::error(e.to_string()));
+        }
+    };
+    let limits = get_http_client()?.response_limits(tds.tds_ext_info.response_limits.as_ref());
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status: StatusCode::OK,
+            body,
+            notes: Vec::new(),
+            limits,
+        },
+    )
+    .await)
+}
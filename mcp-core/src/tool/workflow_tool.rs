@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use mcp_common::{
+    backend::{
+        backend::ToolBackend,
+        workflow::{StepCall, WorkflowBackend, WorkflowStep},
+    },
+    http_client::model::HttpRequestOptions,
+    provider::global_provider::get_http_client,
+    rate_limit::rate_limiter::{get_rate_limiters, RateLimitConfig},
+    transform::template,
+    xds::tds::TDS,
+};
+use reqwest::{StatusCode, Url};
+use serde_json::{json, Map, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    mcp::protocol::mcp_protocol::Requestx,
+    model::spec::protocol::ToolCallResult,
+    tool::{
+        executor::{execute_tool, ids_metadata},
+        upstream::{self, UpstreamResponse},
+    },
+};
+
+/// What a finished step left for the ones after it.
+struct StepOutput {
+    text: String,
+    // parsed JSON (or structured content) when there is any, else the text
+    result: Value,
+}
+
+/// Executes a workflow tool: runs its steps in dependency order, feeding
+/// each one the outputs of the steps before it, and renders the result.
+pub async fn execute(
+    tds: &TDS,
+    workflow: &WorkflowBackend,
+    args: &HashMap<String, Value>,
+    reqx: &Requestx<'_>,
+) -> Result<ToolCallResult> {
+    let ids = ids_metadata(reqx);
+    let mut steps = Map::new();
+    let mut last_text = String::new();
+
+    for step in workflow.order()? {
+        let ctx = json!({ "args": args, "ids": ids, "steps": steps });
+        let run = match &step.when {
+            Some(when) => template::eval_condition(when, &ctx),
+            None => Ok(true),
+        };
+        let outcome = match run {
+            Ok(false) => {
+                debug!("mcp_protocol[tool/call] workflow step skipped: {}", step.id);
+                steps.insert(
+                    step.id.clone(),
+                    json!({ "result": null, "text": null, "skipped": true }),
+                );
+                continue;
+            }
+            Ok(true) => match &step.call {
+                StepCall::Tool { tool_id } => run_tool(step, tool_id, &ctx, reqx).await?,
+                StepCall::Http { .. } => run_http(tds, step, &ctx).await?,
+            },
+            Err(e) => Err(e.to_string()),
+        };
+        let output = match outcome {
+            Ok(output) => output,
+            Err(message) => {
+                warn!(
+                    "mcp_protocol[tool/call] workflow {} failed at step {}: {}",
+                    tds.name, step.id, message
+                );
+                return Ok(ToolCallResult::error(format!(
+                    "Workflow step `{}` failed: {}",
+                    step.id, message
+                )));
+            }
+        };
+        debug!("mcp_protocol[tool/call] workflow step done: {}", step.id);
+        steps.insert(
+            step.id.clone(),
+            json!({ "result": output.result, "text": output.text, "skipped": false }),
+        );
+        last_text = output.text;
+    }
+
+    let body = match &workflow.output {
+        Some(output) => {
+            let ctx = json!({ "args": args, "ids": ids, "steps": steps });
+            match template::render(output, &ctx) {
+                Ok(body) => body,
+                Err(e) => {
+                    return Ok(ToolCallResult::error(format!(
+                        "Workflow output failed: {}",
+                        e
+                    )))
+                }
+            }
+        }
+        None => last_text,
+    };
+    let limits = get_http_client()?.response_limits(tds.tds_ext_info.response_limits.as_ref());
+    Ok(upstream::finish(
+        tds,
+        args,
+        UpstreamResponse {
+            status: StatusCode::OK,
+            body,
+            notes: Vec::new(),
+            limits,
+        },
+    )
+    .await)
+}
+
+/// Calls another TDS. The inner `Err` is the step's failure message.
+async fn run_tool(
+    step: &WorkflowStep,
+    tool_id: &str,
+    ctx: &Value,
+    reqx: &Requestx<'_>,
+) -> Result<std::result::Result<StepOutput, String>> {
+    let Some(target) = reqx.mcp_cache.get_tds(tool_id) else {
+        return Ok(Err(format!("unknown tool `{}`", tool_id)));
+    };
+    // keeps a workflow from reaching itself
+    if matches!(target.tds_ext_info.backend, ToolBackend::Workflow(_)) {
+        return Ok(Err(format!(
+            "tool `{}` is a workflow, workflows do not nest",
+            tool_id
+        )));
+    }
+    let arguments = match template::render_json(&Value::Object(step.arguments.clone()), ctx) {
+        Ok(Value::Object(arguments)) => arguments.into_iter().collect(),
+        Ok(_) => HashMap::new(),
+        Err(e) => return Ok(Err(e.to_string())),
+    };
+
+    let result = Box::pin(execute_tool(&target, &arguments, reqx))
+        .await
+        .with_context(|| format!("workflow step `{}`", step.id))?;
+    let text = result_text(&result);
+    if result.is_error {
+        return Ok(Err(text));
+    }
+    let result = match result.structured_content {
+        Some(structured) => structured,
+        None => match parse_result(&text) {
+            Ok(result) => result,
+            Err(incomplete) => return Ok(Err(incomplete)),
+        },
+    };
+    Ok(Ok(StepOutput { text, result }))
+}
+
+/// Sends an inline HTTP request, with the workflow TDS's TLS profile, call
+/// policy and response limits, through the upstream's domain rate limit and
+/// circuit breaker like an HTTP tool. The inner `Err` is the step's failure
+/// message.
+async fn run_http(
+    tds: &TDS,
+    step: &WorkflowStep,
+    ctx: &Value,
+) -> Result<std::result::Result<StepOutput, String>> {
+    let StepCall::Http {
+        method,
+        url,
+        headers,
+        body,
+    } = &step.call
+    else {
+        return Ok(Err("not an http step".to_string()));
+    };
+    let rendered = (|| -> Result<_> {
+        let url = template::render_url(url, ctx)?;
+        let headers = headers
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), template::render(value, ctx)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let body = body
+            .as_ref()
+            .map(|body| template::render_json(body, ctx))
+            .transpose()?;
+        Ok((url, headers, body))
+    })();
+    let (url, headers, body) = match rendered {
+        Ok(rendered) => rendered,
+        Err(e) => return Ok(Err(e.to_string())),
+    };
+    debug!("mcp_protocol[tool/call] workflow step {} request url: {}", step.id, url);
+
+    // a rendered URL that does not parse fails the step, like any bad request
+    let step_tds = match step_tds(tds, &url) {
+        Ok(step_tds) => step_tds,
+        Err(e) => return Ok(Err(format!("Invalid step URL `{}`: {}", url, e))),
+    };
+    let tds_ext_info = &step_tds.tds_ext_info;
+    // held until the request completes
+    let _permits = match &tds_ext_info.rate_limit {
+        Some(config) => {
+            match get_rate_limiters()
+                .acquire(&step_tds.id, &tds_ext_info.domain, config)
+                .await
+            {
+                Ok(permits) => permits,
+                Err(e) => return Ok(Err(e.to_string())),
+            }
+        }
+        None => Vec::new(),
+    };
+    let options = HttpRequestOptions::<Value> {
+        method: method.clone(),
+        headers: (!headers.is_empty()).then_some(headers),
+        body,
+        tls_profile: tds_ext_info.tls_profile.clone(),
+        call_policy: tds_ext_info.call_policy.clone(),
+        response_limits: tds_ext_info.response_limits.clone(),
+        ..Default::default()
+    };
+    let response = match upstream::send(&step_tds, &url, options, None).await? {
+        Ok(response) => response,
+        Err(failed) => return Ok(Err(result_text(&failed))),
+    };
+    // later steps would read a cut document as if it were whole
+    if !response.notes.is_empty() {
+        return Ok(Err(response.notes.join(" ")));
+    }
+    Ok(Ok(StepOutput {
+        result: parse_body(&response.body),
+        text: response.body,
+    }))
+}
+
+/// The workflow TDS as seen by one of its inline requests: an HTTP tool on
+/// the request's origin, so the breaker and domain limits are the origin's.
+/// The tool limits were taken by the workflow call itself, and its scripts,
+/// transform and pagination apply to the workflow result only.
+fn step_tds(tds: &TDS, url: &str) -> Result<TDS> {
+    let origin = Url::parse(url)?.origin().ascii_serialization();
+    let mut step_tds = tds.clone();
+    let tds_ext_info = &mut step_tds.tds_ext_info;
+    tds_ext_info.domain = origin;
+    tds_ext_info.backend = ToolBackend::Http;
+    tds_ext_info.cluster = None;
+    tds_ext_info.pagination = None;
+    tds_ext_info.response_transform = None;
+    tds_ext_info.scripts = None;
+    tds_ext_info.rate_limit = tds_ext_info.rate_limit.take().map(|config| RateLimitConfig {
+        tool: None,
+        ..config
+    });
+    Ok(step_tds)
+}
+
+fn result_text(result: &ToolCallResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| content.text.as_deref())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Notes appended to results (cuts, partial pages, failed fan-out members),
+/// each on a line of its own.
+const NOTE_PREFIXES: [&str; 3] = ["[truncated: ", "[pagination: ", "[member `"];
+
+/// Reads a tool result as JSON, or as a string when it is plain text. A
+/// result with notes appended (cut, partial pages, failed members) is the
+/// `Err` of its notes: callers would read it as if it were whole.
+pub(crate) fn parse_result(text: &str) -> std::result::Result<Value, String> {
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+    let mut notes = text
+        .lines()
+        .rev()
+        .take_while(|line| {
+            line.ends_with(']') && NOTE_PREFIXES.iter().any(|prefix| line.starts_with(prefix))
+        })
+        .collect::<Vec<_>>();
+    if !notes.is_empty() {
+        notes.reverse();
+        return Err(format!("result is incomplete: {}", notes.join(" ")));
+    }
+    Ok(parse_body(text))
+}
+
+fn parse_body(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Request, routing::get, Router};
+    use mcp_common::{
+        circuit_breaker::circuit_breaker::get_circuit_breakers,
+        test_util::{init_test_config, serve},
+    };
+
+    use super::*;
+
+    fn workflow_tds(url: &str) -> (TDS, WorkflowStep) {
+        let step = json!({
+            "id": "lookup",
+            "call": { "type": "http", "method": "GET", "url": url }
+        });
+        let tds: TDS = serde_json::from_value(json!({
+            "id": "test/workflow",
+            "name": "workflow",
+            "description": "",
+            "input_schema": {},
+            "tds_ext_info": {
+                "domain": "",
+                "method": "POST",
+                "path": "",
+                "required_params": {},
+                "ext_info": {},
+                "backend": { "type": "workflow", "steps": [step] }
+            }
+        }))
+        .unwrap();
+        (tds, serde_json::from_value(step).unwrap())
+    }
+
+    #[tokio::test]
+    async fn inline_requests_encode_arguments_and_use_the_origin_breaker() {
+        init_test_config();
+        // echoes the path and query it was called with
+        let router = Router::new().fallback(get(|req: Request| async move {
+            req.uri().path_and_query().unwrap().to_string()
+        }));
+        let origin = format!("http://{}", serve(router).await);
+        let url = format!("{}/users/{{{{ args.id }}}}?q={{{{ args.q }}}}", origin);
+        let (tds, step) = workflow_tds(&url);
+        let ctx = json!({ "args": { "id": "7/orders", "q": "a&admin=1" } });
+
+        let output = run_http(&tds, &step, &ctx).await.unwrap().unwrap();
+        assert_eq!(output.text, "/users/7%2Forders?q=a%26admin%3D1");
+        assert!(get_circuit_breakers()
+            .unwrap()
+            .snapshots()
+            .iter()
+            .any(|s| s.key == origin && s.calls == 1));
+    }
+
+    #[tokio::test]
+    async fn failed_inline_requests_fail_the_step() {
+        init_test_config();
+        let router = Router::new().fallback(get(|| async {
+            (axum::http::StatusCode::NOT_FOUND, "no such user")
+        }));
+        let origin = format!("http://{}", serve(router).await);
+        let (tds, step) = workflow_tds(&format!("{}/users/1", origin));
+
+        let Err(message) = run_http(&tds, &step, &json!({})).await.unwrap() else {
+            panic!("the step should fail");
+        };
+        assert!(message.contains("no such user"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn unparsable_urls_fail_the_step() {
+        init_test_config();
+        let (tds, step) = workflow_tds("http://[oops/users/{{ args.id }}");
+
+        let Err(message) = run_http(&tds, &step, &json!({ "args": { "id": "1" } }))
+            .await
+            .unwrap()
+        else {
+            panic!("the step should fail");
+        };
+        assert!(message.starts_with("Invalid step URL `http://[oops/users/1`"), "{}", message);
+    }
+
+    #[test]
+    fn results_with_notes_are_incomplete() {
+        assert_eq!(parse_result(r#"{"a":1}"#), Ok(json!({ "a": 1 })));
+        assert_eq!(parse_result("plain text"), Ok(json!("plain text")));
+        // a JSON array that is the whole result is not a note
+        assert_eq!(parse_result("[1, 2]"), Ok(json!([1, 2])));
+
+        let cut = "{\"items\": [1, 2\n[truncated: result exceeded 64 bytes]";
+        let Err(message) = parse_result(cut) else {
+            panic!("a cut result should not parse");
+        };
+        assert!(message.contains("exceeded 64 bytes"), "{}", message);
+
+        let partial = "[1,2]\n[pagination: stopped at max_pages (2) with 2 items; more results are available]\n[member `b` failed: timed out after 10 ms]";
+        let Err(message) = parse_result(partial) else {
+            panic!("a partial result should not parse");
+        };
+        assert!(message.contains("[pagination: ") && message.ends_with("10 ms]"), "{}", message);
+    }
+}
@@ -2,7 +2,10 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::{ quote, format_ident };
-use syn::{ parse_macro_input, Expr, ExprLit, ItemImpl, Lit };
+use syn::{
+    parse_macro_input, Expr, ExprLit, FnArg, ItemFn, ItemImpl, Lit, LitStr, Meta, MetaNameValue,
+    Pat, PatType,
+};
 use proc_macro2::Span;
 
 fn sanitize_key(key: &str) -> proc_macro2::Ident {
@@ -36,3 +39,140 @@ pub fn mcp_proto(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// Registers an async function as a native tool, served by the `native`
+/// backend without an upstream.
+///
+/// The tool name defaults to the function name and the description to its
+/// doc comments; both can be overridden with `#[mcp_tool(name = "...",
+/// description = "...")]`. Each parameter becomes a property of the
+/// `input_schema`, derived with `schemars`, so parameter types must
+/// implement `Deserialize` and `JsonSchema`; `Option` parameters are not
+/// required. The function returns `anyhow::Result<T>` with `T: Serialize`:
+/// a string is the result text, anything else is sent as JSON, and `Err`
+/// becomes an `isError` result.
+///
+/// The expansion only refers to `::mcp_core`, so the calling crate needs
+/// `mcp-core` as a dependency and nothing else.
+#[proc_macro_attribute]
+pub fn mcp_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let mut description = None;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            description = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("expected `name` or `description`"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+    let func = parse_macro_input!(item as ItemFn);
+
+    if func.sig.asyncness.is_none() {
+        return syn::Error::new_spanned(func.sig.fn_token, "#[mcp_tool] requires an async fn")
+            .to_compile_error()
+            .into();
+    }
+    let mut arg_idents = Vec::new();
+    let mut arg_types = Vec::new();
+    for input in &func.sig.inputs {
+        let FnArg::Typed(PatType { pat, ty, .. }) = input else {
+            return syn::Error::new_spanned(input, "#[mcp_tool] functions cannot take self")
+                .to_compile_error()
+                .into();
+        };
+        let Pat::Ident(pat_ident) = pat.as_ref() else {
+            return syn::Error::new_spanned(pat, "#[mcp_tool] parameters must be plain identifiers")
+                .to_compile_error()
+                .into();
+        };
+        arg_idents.push(pat_ident.ident.clone());
+        arg_types.push(ty.clone());
+    }
+
+    let fn_ident = &func.sig.ident;
+    let name = name.unwrap_or_else(|| fn_ident.to_string());
+    let description = description.unwrap_or_else(|| doc_comment(&func.attrs));
+    let args_ident = format_ident!("__McpToolArgs_{}", fn_ident);
+    let register_ident = format_ident!("__register_tool_{}", fn_ident);
+
+    let expanded = quote! {
+        #func
+
+        #[allow(non_camel_case_types)]
+        #[derive(
+            ::mcp_core::__private::serde::Deserialize,
+            ::mcp_core::__private::schemars::JsonSchema,
+        )]
+        #[serde(crate = "::mcp_core::__private::serde")]
+        #[schemars(crate = "::mcp_core::__private::schemars")]
+        struct #args_ident {
+            #(#arg_idents: #arg_types,)*
+        }
+
+        #[::mcp_core::__private::ctor::ctor(crate_path = ::mcp_core::__private::ctor)]
+        fn #register_ident() {
+            use ::mcp_core::__private::{register_native_tool, NativeTool, NativeToolError};
+            register_native_tool(NativeTool {
+                name: #name,
+                description: #description,
+                input_schema: || {
+                    ::mcp_core::__private::serde_json::to_value(
+                        ::mcp_core::__private::schemars::schema_for!(#args_ident),
+                    )
+                    .unwrap_or_default()
+                },
+                call: |arguments| {
+                    ::std::boxed::Box::pin(async move {
+                        let #args_ident { #(#arg_idents,)* } =
+                            ::mcp_core::__private::serde_json::from_value(arguments)
+                                .map_err(NativeToolError::Arguments)?;
+                        let output = #fn_ident(#(#arg_idents),*)
+                            .await
+                            .map_err(NativeToolError::Failed)?;
+                        ::mcp_core::__private::serde_json::to_value(output)
+                            .map_err(|e| NativeToolError::Failed(e.into()))
+                    })
+                },
+            });
+        }
+    };
+
+    expanded.into()
+}
+
+/// The doc comments of an item, one paragraph per blank line.
+fn doc_comment(attrs: &[syn::Attribute]) -> String {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(MetaNameValue {
+                value: Expr::Lit(ExprLit { lit: Lit::Str(s), .. }),
+                ..
+            }) => Some(s.value()),
+            _ => None,
+        })
+        .map(|line| line.trim().to_string())
+        .collect::<Vec<_>>();
+    lines
+        .split(|line| line.is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn mcp_tool_expands_outside_mcp_core() {
+        let cases = trybuild::TestCases::new();
+        cases.pass("tests/ui/pass_*.rs");
+        cases.compile_fail("tests/ui/fail_*.rs");
+    }
+}
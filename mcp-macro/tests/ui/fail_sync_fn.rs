@@ -0,0 +1,8 @@
+use mcp_macro::mcp_tool;
+
+#[mcp_tool]
+fn not_async(text: String) -> anyhow::Result<String> {
+    Ok(text)
+}
+
+fn main() {}
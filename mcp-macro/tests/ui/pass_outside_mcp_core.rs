@@ -0,0 +1,17 @@
+// A crate with no `ctor`, `schemars` or `serde` dependency of its own.
+use anyhow::Result;
+use mcp_macro::mcp_tool;
+
+/// Upper-cases `text`.
+#[mcp_tool(name = "trybuild_shout")]
+async fn shout(text: String, times: Option<usize>) -> Result<String> {
+    Ok(text.to_uppercase().repeat(times.unwrap_or(1)))
+}
+
+fn main() {
+    let tool = mcp_core::tool::native_tool::get_native_tool("trybuild_shout")
+        .expect("the tool is registered before main");
+    assert_eq!(tool.description, "Upper-cases `text`.");
+    let schema = (tool.input_schema)();
+    assert_eq!(schema["required"].to_string(), r#"["text"]"#);
+}
@@ -19,4 +19,10 @@ pub trait DataSource {
     async fn get_all<T>(self: Arc<Self>) -> Result<Vec<T>>
     where
         T: for<'de> serde::Deserialize<'de>;
+
+    /// Takes, or keeps, the cluster-wide lock `name` and returns whether this
+    /// instance holds it, so work meant for one instance at a time (e.g. the
+    /// MDS tool sync) is not repeated by every instance. The lock is released
+    /// when the holder stops calling `try_lock` or loses its connection.
+    async fn try_lock(self: Arc<Self>, name: &str) -> Result<bool>;
 }
@@ -57,4 +57,11 @@ impl DataSource for DataSourceEnum {
             DataSourceEnum::Mysql(ds) => ds.clone().get_all().await,
         }
     }
+
+    async fn try_lock(self: Arc<Self>, name: &str) -> Result<bool> {
+        match self.as_ref() {
+            DataSourceEnum::Etcd(ds) => ds.clone().try_lock(name).await,
+            DataSourceEnum::Mysql(ds) => ds.clone().try_lock(name).await,
+        }
+    }
 }
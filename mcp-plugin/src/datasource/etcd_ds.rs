@@ -5,19 +5,32 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use mcp_common::{
     cache::mcp_cache::McpCache,
-    constants::constants::mcp_cache_consts::{ETCD_IDS_PREFIX, ETCD_TDS_PREFIX},
+    constants::constants::mcp_cache_consts::{
+        ETCD_CDS_PREFIX, ETCD_IDS_PREFIX, ETCD_LOCK_PREFIX, ETCD_MDS_PREFIX, ETCD_PDS_PREFIX,
+        ETCD_TDS_PREFIX,
+    },
     etcd::etcd_client_provider::{EtcdEventType, EtcdWatchEvent},
     provider::global_provider::get_etcd,
-    xds::{ids::IDS, tds::TDS},
+    xds::{cds::CDS, ids::IDS, mds::MDS, pds::PDS, tds::TDS},
 };
+use tokio::sync::{mpsc, Mutex};
+
+// Seconds a lock outlives an instance that stopped renewing it; renewed on
+// every `try_lock`
+const LOCK_LEASE_TTL: i64 = 10;
 
 pub struct EtcdDataSource {
     mcp_cache: Arc<McpCache>,
+    // the lease this instance's locks are attached to
+    lock_lease: Mutex<Option<i64>>,
 }
 
 impl EtcdDataSource {
     pub fn new(mcp_cache: Arc<McpCache>) -> Self {
-        Self { mcp_cache }
+        Self {
+            mcp_cache,
+            lock_lease: Mutex::new(None),
+        }
     }
 }
 
@@ -76,6 +89,88 @@ impl DataSource for EtcdDataSource {
         })
         .await?;
 
+        let mds_pairs = etcd.get_prefix(ETCD_MDS_PREFIX).await?;
+        for (k, v) in mds_pairs {
+            let mds: MDS = serde_json::from_str(&v)?;
+            self.mcp_cache.insert_mds(k, mds);
+        }
+        let mds_cache = self.mcp_cache.clone();
+        etcd.watch(ETCD_MDS_PREFIX, move |event: EtcdWatchEvent| {
+            match event.event_type {
+                EtcdEventType::Put => {
+                    if let Some(val_str) = &event.value {
+                        if let Ok(mds) = serde_json::from_str::<MDS>(val_str) {
+                            mds_cache.insert_mds(event.key, mds);
+                        } else {
+                            eprintln!("Failed to parse MDS");
+                        }
+                    }
+                }
+                EtcdEventType::Delete => {
+                    mds_cache.remove_mds(&event.key);
+                }
+                _ => {}
+            }
+        })
+        .await?;
+
+        let pds_pairs = etcd.get_prefix(ETCD_PDS_PREFIX).await?;
+        for (k, v) in pds_pairs {
+            let pds: PDS = serde_json::from_str(&v)?;
+            self.mcp_cache.insert_pds(k, pds).await;
+        }
+        // plugins compile off the watch callback, one event after another
+        let (pds_events, mut pending) = mpsc::unbounded_channel::<EtcdWatchEvent>();
+        let pds_cache = self.mcp_cache.clone();
+        tokio::spawn(async move {
+            while let Some(event) = pending.recv().await {
+                match event.event_type {
+                    EtcdEventType::Put => {
+                        if let Some(val_str) = &event.value {
+                            if let Ok(pds) = serde_json::from_str::<PDS>(val_str) {
+                                pds_cache.insert_pds(event.key, pds).await;
+                            } else {
+                                eprintln!("Failed to parse PDS");
+                            }
+                        }
+                    }
+                    EtcdEventType::Delete => {
+                        pds_cache.remove_pds(&event.key);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        etcd.watch(ETCD_PDS_PREFIX, move |event: EtcdWatchEvent| {
+            let _ = pds_events.send(event);
+        })
+        .await?;
+
+        let cds_pairs = etcd.get_prefix(ETCD_CDS_PREFIX).await?;
+        for (k, v) in cds_pairs {
+            let cds: CDS = serde_json::from_str(&v)?;
+            self.mcp_cache.insert_cds(k, cds);
+        }
+        let cds_cache = self.mcp_cache.clone();
+        etcd.watch(ETCD_CDS_PREFIX, move |event: EtcdWatchEvent| {
+            match event.event_type {
+                EtcdEventType::Put => {
+                    if let Some(val_str) = &event.value {
+                        if let Ok(cds) = serde_json::from_str::<CDS>(val_str) {
+                            cds_cache.insert_cds(event.key, cds);
+                        } else {
+                            eprintln!("Failed to parse CDS");
+                        }
+                    }
+                }
+                EtcdEventType::Delete => {
+                    cds_cache.remove_cds(&event.key);
+                }
+                _ => {}
+            }
+        })
+        .await?;
+
         Ok(())
     }
 
@@ -117,6 +212,12 @@ impl DataSource for EtcdDataSource {
             ETCD_TDS_PREFIX
         } else if type_name.contains("IDS") {
             ETCD_IDS_PREFIX
+        } else if type_name.contains("MDS") {
+            ETCD_MDS_PREFIX
+        } else if type_name.contains("PDS") {
+            ETCD_PDS_PREFIX
+        } else if type_name.contains("CDS") {
+            ETCD_CDS_PREFIX
         } else {
             return Err(anyhow!("Unsupported type for get_all: {}", type_name));
         };
@@ -128,4 +229,21 @@ impl DataSource for EtcdDataSource {
         }
         Ok(result)
     }
+
+    async fn try_lock(self: Arc<Self>, name: &str) -> Result<bool> {
+        let etcd = get_etcd();
+        let mut lock_lease = self.lock_lease.lock().await;
+        // an expired lease took its locks along; the next one starts over
+        if let Some(lease) = *lock_lease {
+            if !etcd.keep_alive(lease).await? {
+                *lock_lease = None;
+            }
+        }
+        let lease = match *lock_lease {
+            Some(lease) => lease,
+            None => *lock_lease.insert(etcd.grant_lease(LOCK_LEASE_TTL).await?),
+        };
+        let key = format!("{}{}", ETCD_LOCK_PREFIX, name);
+        Ok(etcd.create_with_lease(&key, "", lease).await? == lease)
+    }
 }
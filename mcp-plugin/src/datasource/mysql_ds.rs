@@ -1,16 +1,22 @@
-use std::{any::type_name, sync::Arc};
+use std::{
+    any::type_name,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+};
 
 use crate::datasource::datasource::DataSource;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{Duration, NaiveDateTime, Utc};
 use mcp_common::{
     cache::mcp_cache::McpCache,
     provider::global_provider::get_mysql_pool,
-    xds::{ids::IDS, tds::TDS},
+    xds::{cds::CDS, ids::IDS, mds::MDS, pds::PDS, tds::TDS},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{pool::PoolConnection, FromRow, MySql, MySqlPool};
 use tracing::{debug, info, warn};
 
 /// Represents a row in the `dynmcp_xds` table.
@@ -31,11 +37,24 @@ use tracing::{debug, info, warn};
 /// ### Field Descriptions
 /// - **`id`**: Auto-increment primary key.
 /// - **`key`**: Unique identifier for the XDS object (e.g., service ID).
-/// - **`xds_type`**: Type of the XDS object (e.g., `TDS`, `IDS`, `CDS`, etc.).
-/// - **`xds_json`**: Serialized JSON representation of the XDS object.
+/// - **`xds_type`**: Type of the XDS object (e.g., `TDS`, `IDS`, `MDS`, `PDS`, `CDS`, etc.).
+/// - **`xds_json`**: Serialized JSON representation of the XDS object. `PDS`
+///   objects carry a whole WASM component, so use MEDIUMTEXT when storing them.
 /// - **`status`**: Synchronization status: `pending`, `syncing`, or `synced`.
 /// - **`create_time`**: Timestamp when the record was created.
 /// - **`update_time`**: Timestamp when the record was last updated.
+///
+/// Deletes leave a row in `dynmcp_xds_deleted`, so other instances notice
+/// them without scanning `dynmcp_xds`:
+/// ```sql
+/// CREATE TABLE IF NOT EXISTS dynmcp_xds_deleted (
+///     id BIGINT PRIMARY KEY AUTO_INCREMENT,
+///     `key` VARCHAR(255) NOT NULL,
+///     xds_type VARCHAR(64) NOT NULL,
+///     delete_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///     INDEX idx_delete_time (delete_time)
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+/// ```
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct XDSRecord {
     pub id: i64,
@@ -47,15 +66,80 @@ pub struct XDSRecord {
     pub update_time: NaiveDateTime,
 }
 
+/// A row of `dynmcp_xds_deleted`, with the id of the `dynmcp_xds` row of
+/// the same key if it was written again since.
+#[derive(Debug, FromRow)]
+struct Tombstone {
+    id: i64,
+    key: String,
+    xds_type: String,
+    live_id: Option<i64>,
+}
+
+// How long tombstones are kept for instances that are behind
+const TOMBSTONE_RETENTION_HOURS: i64 = 24;
+
+/// XDS records in MySQL.
+///
+/// Rows written as `pending` are loaded by the watch loop; deletes are
+/// picked up from the tombstones in `dynmcp_xds_deleted` past the last one
+/// this instance has seen. Cluster-wide locks are MySQL named locks
+/// (`GET_LOCK`), held by a dedicated connection and released when it closes.
 pub struct MysqlDataSource {
     mcp_cache: Arc<McpCache>,
+    // Id of the last tombstone applied to the cache
+    last_tombstone: AtomicI64,
+    // The connection holding this instance's named locks
+    lock_conn: tokio::sync::Mutex<Option<PoolConnection<MySql>>>,
 }
 
 impl MysqlDataSource {
     pub fn new(mcp_cache: Arc<McpCache>) -> Self {
-        Self { mcp_cache }
+        Self {
+            mcp_cache,
+            last_tombstone: AtomicI64::new(0),
+            lock_conn: tokio::sync::Mutex::new(None),
+        }
     }
-    async fn insert_into_cache(&self, record: &XDSRecord) -> bool {
+
+    /// Removes from the cache the entries deleted since the last tombstone
+    /// seen, e.g. by another instance: deletes leave no pending row behind.
+    async fn remove_deleted(&self, pool: &MySqlPool, page_size: i64) -> Result<()> {
+        let tombstones: Vec<Tombstone> = sqlx::query_as(
+            r#"
+            SELECT d.id, d.`key`, d.xds_type, x.id AS live_id
+            FROM dynmcp_xds_deleted d
+            LEFT JOIN dynmcp_xds x ON x.`key` = d.`key`
+            WHERE d.id > ?
+            ORDER BY d.id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(self.last_tombstone.load(Ordering::Relaxed))
+        .bind(page_size)
+        .fetch_all(pool)
+        .await?;
+        for tombstone in tombstones {
+            self.last_tombstone.store(tombstone.id, Ordering::Relaxed);
+            // written again after the delete; the pending row replaces it
+            if tombstone.live_id.is_some() {
+                continue;
+            }
+            let key = tombstone.key;
+            info!("🗑 Record deleted: key = {}", key);
+            match tombstone.xds_type.as_str() {
+                "TDS" => self.mcp_cache.remove_tds(&key),
+                "IDS" => self.mcp_cache.remove_ids(&key),
+                "MDS" => self.mcp_cache.remove_mds(&key),
+                "PDS" => self.mcp_cache.remove_pds(&key),
+                "CDS" => self.mcp_cache.remove_cds(&key),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn parse_into_cache(&self, record: &XDSRecord) -> bool {
         match record.xds_type.as_str() {
             "TDS" => match serde_json::from_str::<TDS>(&record.xds_json) {
                 Ok(tds) => {
@@ -77,6 +161,36 @@ impl MysqlDataSource {
                     false
                 }
             },
+            "MDS" => match serde_json::from_str::<MDS>(&record.xds_json) {
+                Ok(mds) => {
+                    self.mcp_cache.insert_mds(record.key.clone(), mds);
+                    true
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to parse MDS from record {}: {}", record.key, e);
+                    false
+                }
+            },
+            "PDS" => match serde_json::from_str::<PDS>(&record.xds_json) {
+                Ok(pds) => {
+                    self.mcp_cache.insert_pds(record.key.clone(), pds).await;
+                    true
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to parse PDS from record {}: {}", record.key, e);
+                    false
+                }
+            },
+            "CDS" => match serde_json::from_str::<CDS>(&record.xds_json) {
+                Ok(cds) => {
+                    self.mcp_cache.insert_cds(record.key.clone(), cds);
+                    true
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to parse CDS from record {}: {}", record.key, e);
+                    false
+                }
+            },
             other => {
                 tracing::warn!("Unknown xds_type `{}` for key {}", other, record.key);
                 false
@@ -91,6 +205,13 @@ impl DataSource for MysqlDataSource {
         let pool = get_mysql_pool();
         const PAGE_SIZE: i64 = 100;
 
+        // deletes from here on are applied after the load
+        let last_tombstone: i64 =
+            sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM dynmcp_xds_deleted")
+                .fetch_one(&*pool)
+                .await?;
+        self.last_tombstone.store(last_tombstone, Ordering::Relaxed);
+
         // Initial full load
         info!("🔄 Starting initial full load of XDS records...");
 
@@ -121,7 +242,7 @@ impl DataSource for MysqlDataSource {
                     "📥 Inserting record into cache: id = {}, key = {}",
                     record.id, record.key
                 );
-                self.insert_into_cache(record).await;
+                self.parse_into_cache(record).await;
             }
 
             offset += PAGE_SIZE;
@@ -145,6 +266,7 @@ impl DataSource for MysqlDataSource {
             .await?;
 
             if pending_rows.is_empty() {
+                self.remove_deleted(&pool, PAGE_SIZE).await?;
                 debug!("⏸ No pending records found, sleeping 5s...");
                 tokio::time::sleep(std::time::Duration::from_secs(5)).await;
                 continue;
@@ -165,7 +287,7 @@ impl DataSource for MysqlDataSource {
                     "📥 Syncing record into cache: id = {}, key = {}",
                     record.id, record.key
                 );
-                let ok = self.insert_into_cache(&record).await;
+                let ok = self.parse_into_cache(&record).await;
                 let new_status = if ok { "synced" } else { "pending" };
 
                 if ok {
@@ -251,9 +373,27 @@ impl DataSource for MysqlDataSource {
                 .fetch_optional(&*pool)
                 .await?;
 
-        if let Some((_xds_type,)) = record {
+        if let Some((xds_type,)) = record {
+            let now = Utc::now().naive_utc();
+            let mut tx = pool.begin().await?;
             let result = sqlx::query("DELETE FROM dynmcp_xds WHERE `key` = ?")
                 .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if result.rows_affected() > 0 {
+                sqlx::query(
+                    "INSERT INTO dynmcp_xds_deleted (`key`, xds_type, delete_time) VALUES (?, ?, ?)",
+                )
+                .bind(id)
+                .bind(&xds_type)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+
+            sqlx::query("DELETE FROM dynmcp_xds_deleted WHERE delete_time < ?")
+                .bind(now - Duration::hours(TOMBSTONE_RETENTION_HOURS))
                 .execute(&*pool)
                 .await?;
             Ok(result.rows_affected() > 0)
@@ -301,4 +441,28 @@ impl DataSource for MysqlDataSource {
 
         Ok(result)
     }
+
+    async fn try_lock(self: Arc<Self>, name: &str) -> Result<bool> {
+        let mut lock_conn = self.lock_conn.lock().await;
+        let conn = match lock_conn.as_mut() {
+            Some(conn) => conn,
+            None => lock_conn.insert(get_mysql_pool().acquire().await?),
+        };
+        // 1 when this connection holds the lock or just took it, 0 when
+        // another instance holds it
+        let held: Result<Option<i64>, sqlx::Error> =
+            sqlx::query_scalar("SELECT IF(IS_USED_LOCK(?) = CONNECTION_ID(), 1, GET_LOCK(?, 0))")
+                .bind(name)
+                .bind(name)
+                .fetch_one(&mut **conn)
+                .await;
+        match held {
+            Ok(held) => Ok(held == Some(1)),
+            Err(e) => {
+                // a lost connection lost its locks too; the next call starts over
+                *lock_conn = None;
+                Err(e.into())
+            }
+        }
+    }
 }
@@ -1 +1,4 @@
-pub mod datasource;
\ No newline at end of file
+#![allow(clippy::module_inception)]
+
+pub mod datasource;
+pub mod sync;
@@ -0,0 +1,510 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use mcp_common::{
+    cache::mcp_cache::McpCache,
+    http_client::error::HttpCallError,
+    mcp_client::mcp_client_provider::{get_mcp_client, McpCallOptions},
+    recording::recorder::{get_recorder, RecordingMode},
+    xds::mds::MDS,
+};
+use serde_json::Value;
+use tokio::{task::JoinHandle, time::Instant};
+use tracing::{info, warn};
+
+use crate::datasource::{datasource::DataSource, ds_enum::DataSourceEnum};
+
+// How often registered servers are checked for due resyncs
+const SYNC_TICK: Duration = Duration::from_secs(1);
+// Delay before a failed sync is tried again
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+// Time limit of one server's sync, after which it counts as failed
+const SYNC_TIMEOUT: Duration = Duration::from_secs(120);
+// How often mirrored tools are checked for servers deleted while no
+// instance was syncing
+const ORPHAN_SWEEP_INTERVAL: Duration = Duration::from_secs(300);
+// DataSource lock held by the instance that syncs
+const SYNC_LOCK: &str = "dynmcp_mcp_tool_sync";
+const LISTEN_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const LISTEN_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Sync bookkeeping of one registered server.
+struct ServerState {
+    // the MDS as last seen, to notice edits
+    config: Value,
+    // None: only `list_changed` triggers the next sync
+    next_sync: Option<Instant>,
+    // set when the server announces `notifications/tools/list_changed`
+    list_changed: Arc<AtomicBool>,
+    listener: JoinHandle<()>,
+    // the sync in flight, if any
+    sync: Option<JoinHandle<Result<()>>>,
+}
+
+impl ServerState {
+    fn new(mds: &MDS, config: Value) -> Self {
+        let list_changed = Arc::new(AtomicBool::new(false));
+        Self {
+            config,
+            next_sync: Some(Instant::now()),
+            listener: spawn_listener(mds, list_changed.clone()),
+            list_changed,
+            sync: None,
+        }
+    }
+
+    fn abort(&self) {
+        self.listener.abort();
+        if let Some(sync) = &self.sync {
+            sync.abort();
+        }
+    }
+
+    fn is_due(&self) -> bool {
+        self.list_changed.swap(false, Ordering::Relaxed)
+            || self.next_sync.is_some_and(|at| at <= Instant::now())
+    }
+}
+
+/// Mirrors the tools of every registered MDS into TDS entries.
+///
+/// Each server is synced when it is registered or edited, every
+/// `resync_interval_ms`, and whenever it sends `list_changed` on its GET
+/// event stream. Servers sync concurrently, each on its own task within
+/// `SYNC_TIMEOUT`, so a slow server does not hold up the others.
+///
+/// Entries are written through the DataSource (so other dynmcp instances and
+/// restarts see them) and applied to the local cache at once; tools gone
+/// upstream, and all tools of a deleted MDS, are deleted; tools of a server
+/// deleted while no instance was syncing are swept up every
+/// `ORPHAN_SWEEP_INTERVAL`. Only the instance
+/// holding the DataSource's sync lock writes, so instances do not race each
+/// other over the same entries.
+pub struct McpToolSync<D = DataSourceEnum> {
+    mcp_cache: Arc<McpCache>,
+    data_source: Arc<D>,
+}
+
+impl<D: DataSource + Send + Sync + 'static> McpToolSync<D> {
+    pub fn spawn(mcp_cache: Arc<McpCache>, data_source: Arc<D>) -> JoinHandle<()> {
+        let sync = Arc::new(Self {
+            mcp_cache,
+            data_source,
+        });
+        tokio::spawn(sync.run())
+    }
+
+    async fn run(self: Arc<Self>) {
+        // the upstream servers are not recorded, and nothing is sent in replay
+        if get_recorder().is_ok_and(|recorder| recorder.mode() == RecordingMode::Replay) {
+            info!("MDS sync is off in replay mode");
+            return;
+        }
+        let mut states: HashMap<String, ServerState> = HashMap::new();
+        let mut tick = tokio::time::interval(SYNC_TICK);
+        let mut next_sweep = Instant::now();
+        loop {
+            tick.tick().await;
+            match self.data_source.clone().try_lock(SYNC_LOCK).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    // another instance syncs; start over if this one takes over
+                    for (_, state) in states.drain() {
+                        state.abort();
+                    }
+                    next_sweep = Instant::now();
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to take the MDS sync lock: {}", e);
+                    continue;
+                }
+            }
+            let servers = self.mcp_cache.list_mds();
+
+            // a deleted server takes its mirrored tools along
+            let removed: Vec<String> = states
+                .keys()
+                .filter(|id| !servers.iter().any(|mds| &mds.id == *id))
+                .cloned()
+                .collect();
+            for id in removed {
+                if let Some(state) = states.remove(&id) {
+                    state.abort();
+                }
+                match self.remove_mirrored(&id, &HashSet::new()).await {
+                    Ok(n) => info!("MDS `{}` removed, deleted {} mirrored tools", id, n),
+                    Err(e) => warn!("Failed to delete tools of removed MDS `{}`: {}", id, e),
+                }
+            }
+            if next_sweep <= Instant::now() {
+                next_sweep = Instant::now() + ORPHAN_SWEEP_INTERVAL;
+                if let Err(e) = self.sweep_orphans(&servers).await {
+                    warn!("Failed to sweep orphaned mirrored tools: {}", e);
+                }
+            }
+
+            for mds in servers {
+                let config = serde_json::to_value(&mds).unwrap_or_default();
+                // new or edited servers start over with a fresh listener
+                if states.get(&mds.id).is_none_or(|state| state.config != config) {
+                    if let Some(previous) = states.remove(&mds.id) {
+                        previous.abort();
+                    }
+                    states.insert(mds.id.clone(), ServerState::new(&mds, config));
+                }
+                let Some(state) = states.get_mut(&mds.id) else {
+                    continue;
+                };
+
+                // a finished sync schedules the next one
+                if let Some(sync) = state.sync.take_if(|sync| sync.is_finished()) {
+                    let interval = mds.resync_interval_ms.map(Duration::from_millis);
+                    state.next_sync = match sync.await {
+                        Ok(Ok(())) => interval.map(|interval| Instant::now() + interval),
+                        Ok(Err(e)) => {
+                            warn!("Failed to sync tools of MDS `{}`: {}", mds.id, e);
+                            Some(Instant::now() + RETRY_DELAY)
+                        }
+                        Err(e) => {
+                            warn!("Sync of MDS `{}` failed: {}", mds.id, e);
+                            Some(Instant::now() + RETRY_DELAY)
+                        }
+                    };
+                }
+                if state.sync.is_some() || !state.is_due() {
+                    continue;
+                }
+                let sync = self.clone();
+                state.sync = Some(tokio::spawn(async move {
+                    tokio::time::timeout(SYNC_TIMEOUT, sync.sync_server(&mds))
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(anyhow!("timed out after {}s", SYNC_TIMEOUT.as_secs()))
+                        })
+                }));
+            }
+        }
+    }
+
+    /// Mirrors one server's current `tools/list`.
+    async fn sync_server(&self, mds: &MDS) -> Result<()> {
+        let tools = get_mcp_client()
+            .list_tools(&mds.url, &call_options(mds))
+            .await?;
+        let mut mirrored = HashSet::new();
+        let mut updated = 0;
+        for tool in &tools {
+            let Some(name) = tool.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            if !mds.mirrors(name) {
+                continue;
+            }
+            let tds = match mds.mirror_tool(tool).and_then(|tds| tds.validate().map(|_| tds)) {
+                Ok(tds) => tds,
+                Err(e) => {
+                    warn!("Skipping upstream tool `{}` of MDS `{}`: {}", name, mds.id, e);
+                    continue;
+                }
+            };
+            if let Some(other) = self.mcp_cache.get_tds_by_name(&tds.name) {
+                if other.id != tds.id {
+                    warn!(
+                        "Skipping upstream tool `{}` of MDS `{}`: name `{}` is taken by TDS `{}`",
+                        name, mds.id, tds.name, other.id
+                    );
+                    continue;
+                }
+            }
+            mirrored.insert(tds.id.clone());
+
+            let unchanged = self.mcp_cache.get_tds(&tds.id).is_some_and(|current| {
+                serde_json::to_value(&current).ok() == serde_json::to_value(&tds).ok()
+            });
+            if !unchanged {
+                self.data_source.clone().put(&tds.id, &tds).await?;
+                self.mcp_cache.insert_tds(tds.id.clone(), tds);
+                updated += 1;
+            }
+        }
+        let removed = self.remove_mirrored(&mds.id, &mirrored).await?;
+        info!(
+            "Synced MDS `{}`: {} tools mirrored, {} updated, {} removed",
+            mds.id,
+            mirrored.len(),
+            updated,
+            removed
+        );
+        Ok(())
+    }
+
+    /// Deletes the tools mirrored from servers that are gone, e.g. deleted
+    /// while no instance was syncing. The stored entries are asked before
+    /// deleting, as the cache may still be loading them.
+    async fn sweep_orphans(&self, servers: &[MDS]) -> Result<()> {
+        let orphaned: HashSet<String> = self
+            .mcp_cache
+            .list_tds()
+            .into_iter()
+            .filter_map(|tds| tds.tds_ext_info.mirrored_from)
+            .filter(|id| !servers.iter().any(|mds| &mds.id == id))
+            .collect();
+        if orphaned.is_empty() {
+            return Ok(());
+        }
+        let stored = self.data_source.clone().get_all::<MDS>().await?;
+        for id in orphaned {
+            if stored.iter().any(|mds| mds.id == id) {
+                continue;
+            }
+            let removed = self.remove_mirrored(&id, &HashSet::new()).await?;
+            info!("MDS `{}` is gone, deleted {} orphaned mirrored tools", id, removed);
+        }
+        Ok(())
+    }
+
+    /// Deletes the tools mirrored from `mds_id` that are not in `keep`.
+    async fn remove_mirrored(&self, mds_id: &str, keep: &HashSet<String>) -> Result<usize> {
+        let mut removed = 0;
+        for tds in self.mcp_cache.list_tds_mirrored_from(mds_id) {
+            if keep.contains(&tds.id) {
+                continue;
+            }
+            self.data_source.clone().delete(&tds.id).await?;
+            self.mcp_cache.remove_tds(&tds.id);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+fn call_options(mds: &MDS) -> McpCallOptions {
+    McpCallOptions {
+        headers: mds.headers.clone(),
+        tls_profile: mds.tls_profile.clone(),
+        ..Default::default()
+    }
+}
+
+/// Keeps the server's GET event stream open and flags `list_changed`
+/// notifications. Servers without the stream (405) rely on periodic resyncs.
+fn spawn_listener(mds: &MDS, list_changed: Arc<AtomicBool>) -> JoinHandle<()> {
+    let id = mds.id.clone();
+    let url = mds.url.clone();
+    let options = call_options(mds);
+    tokio::spawn(async move {
+        let mut backoff = LISTEN_BACKOFF_MIN;
+        loop {
+            let result = get_mcp_client()
+                .listen(&url, &options, |method| {
+                    if method == "notifications/tools/list_changed" {
+                        info!("MDS `{}` announced list_changed", id);
+                        list_changed.store(true, Ordering::Relaxed);
+                    }
+                })
+                .await;
+            match result {
+                Ok(()) => backoff = LISTEN_BACKOFF_MIN,
+                Err(e) => {
+                    let unsupported = matches!(
+                        e.downcast_ref::<HttpCallError>(),
+                        Some(HttpCallError::Status { status, .. }) if status.as_u16() == 405
+                    );
+                    if unsupported {
+                        info!("MDS `{}` has no event stream, relying on periodic resync", id);
+                        return;
+                    }
+                    warn!("Event stream of MDS `{}` failed: {}", id, e);
+                    backoff = (backoff * 2).min(LISTEN_BACKOFF_MAX);
+                }
+            }
+            // notifications may have been missed while disconnected
+            list_changed.store(true, Ordering::Relaxed);
+            tokio::time::sleep(backoff).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+    use mcp_common::test_util::{init_test_config, serve};
+    use serde_json::json;
+
+    use super::*;
+
+    /// A DataSource keeping entries in memory; `leader` is the answer to
+    /// every `try_lock`.
+    struct MemoryDataSource {
+        entries: Mutex<HashMap<String, Value>>,
+        leader: AtomicBool,
+    }
+
+    impl MemoryDataSource {
+        fn new(leader: bool) -> Arc<Self> {
+            Arc::new(Self {
+                entries: Mutex::new(HashMap::new()),
+                leader: AtomicBool::new(leader),
+            })
+        }
+
+        fn keys(&self) -> Vec<String> {
+            let mut keys: Vec<_> = self.entries.lock().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        }
+    }
+
+    #[async_trait]
+    impl DataSource for MemoryDataSource {
+        async fn fetch_and_watch(self: Arc<Self>) -> Result<()> {
+            Ok(())
+        }
+
+        async fn put<T>(self: Arc<Self>, id: &str, value: &T) -> Result<T>
+        where
+            T: serde::Serialize + Clone + Send + Sync + 'static,
+        {
+            let json = serde_json::to_value(value)?;
+            self.entries.lock().unwrap().insert(id.to_string(), json);
+            Ok(value.clone())
+        }
+
+        async fn get<T>(self: Arc<Self>, id: &str) -> Result<T>
+        where
+            T: for<'de> serde::Deserialize<'de>,
+        {
+            let value = self.entries.lock().unwrap().get(id).cloned();
+            Ok(serde_json::from_value(value.ok_or_else(|| anyhow!("no `{}`", id))?)?)
+        }
+
+        async fn delete(self: Arc<Self>, id: &str) -> Result<bool> {
+            Ok(self.entries.lock().unwrap().remove(id).is_some())
+        }
+
+        async fn get_all<T>(self: Arc<Self>) -> Result<Vec<T>>
+        where
+            T: for<'de> serde::Deserialize<'de>,
+        {
+            let entries = self.entries.lock().unwrap();
+            Ok(entries
+                .values()
+                .filter_map(|value| serde_json::from_value(value.clone()).ok())
+                .collect())
+        }
+
+        async fn try_lock(self: Arc<Self>, _name: &str) -> Result<bool> {
+            Ok(self.leader.load(Ordering::Relaxed))
+        }
+    }
+
+    /// An MCP server listing `tools`, or never answering `tools/list` when
+    /// `hang` is set.
+    async fn server(tools: &[&str], hang: bool) -> String {
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|name| json!({ "name": name, "inputSchema": { "type": "object" } }))
+            .collect();
+        let router = Router::new().route(
+            "/mcp",
+            post(move |Json(message): Json<Value>| {
+                let tools = tools.clone();
+                async move {
+                    let Some(id) = message.get("id").cloned() else {
+                        return StatusCode::ACCEPTED.into_response();
+                    };
+                    let result = match message["method"].as_str() {
+                        Some("tools/list") if hang => std::future::pending().await,
+                        Some("tools/list") => json!({ "tools": tools }),
+                        _ => json!({}),
+                    };
+                    Json(json!({ "jsonrpc": "2.0", "id": id, "result": result })).into_response()
+                }
+            }),
+        );
+        format!("http://{}/mcp", serve(router).await)
+    }
+
+    fn mds(id: &str, url: &str) -> MDS {
+        serde_json::from_value(json!({ "id": id, "name": id, "url": url })).unwrap()
+    }
+
+    /// Waits up to 5s for `done`.
+    async fn eventually(done: impl Fn() -> bool) -> bool {
+        for _ in 0..50 {
+            if done() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn a_hanging_server_does_not_hold_up_the_others() {
+        init_test_config();
+        let cache = Arc::new(McpCache::new());
+        cache.insert_mds("slow".to_string(), mds("slow", &server(&["a"], true).await));
+        cache.insert_mds("fast".to_string(), mds("fast", &server(&["b", "c"], false).await));
+        let data_source = MemoryDataSource::new(true);
+        let sync = McpToolSync::spawn(cache.clone(), data_source.clone());
+
+        assert!(eventually(|| data_source.keys() == ["fast.b", "fast.c"]).await);
+        assert!(cache.get_tds("fast.b").is_some());
+
+        // a removed server takes its mirrored tools along
+        cache.remove_mds("fast");
+        assert!(eventually(|| data_source.keys().is_empty()).await);
+        assert!(cache.get_tds("fast.b").is_none());
+        sync.abort();
+    }
+
+    #[tokio::test]
+    async fn tools_of_servers_that_are_gone_are_swept() {
+        init_test_config();
+        let cache = Arc::new(McpCache::new());
+        let data_source = MemoryDataSource::new(true);
+        // `late` is stored but not loaded into the cache yet
+        let late = mds("late", "http://127.0.0.1:1/mcp");
+        data_source.clone().put("late", &late).await.unwrap();
+        for (server, tool) in [("gone", "x"), ("late", "y")] {
+            let tds = mds(server, &late.url).mirror_tool(&json!({ "name": tool })).unwrap();
+            data_source.clone().put(&tds.id, &tds).await.unwrap();
+            cache.insert_tds(tds.id.clone(), tds);
+        }
+        let sync = McpToolSync::spawn(cache.clone(), data_source.clone());
+
+        assert!(eventually(|| data_source.keys() == ["late", "late.y"]).await);
+        assert!(cache.get_tds("gone.x").is_none());
+        assert!(cache.get_tds("late.y").is_some());
+        sync.abort();
+    }
+
+    #[tokio::test]
+    async fn only_the_lock_holder_syncs() {
+        init_test_config();
+        let cache = Arc::new(McpCache::new());
+        cache.insert_mds("other".to_string(), mds("other", &server(&["x"], false).await));
+        let data_source = MemoryDataSource::new(false);
+        let sync = McpToolSync::spawn(cache.clone(), data_source.clone());
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(data_source.keys().is_empty());
+        assert!(cache.get_tds("other.x").is_none());
+
+        data_source.leader.store(true, Ordering::Relaxed);
+        assert!(eventually(|| data_source.keys() == ["other.x"]).await);
+        sync.abort();
+    }
+}